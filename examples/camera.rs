@@ -0,0 +1,66 @@
+use pine::{
+    prelude::{Color, HeadlessConfig, Renderer, Renderer2D, Vec2},
+    rendering::{
+        camera::ScalingMode,
+        scene::{Renderable, SceneNode2D, Transform},
+    },
+};
+use tracing_subscriber::EnvFilter;
+
+fn main() {
+    let log_filter = EnvFilter::try_new("pine=trace,camera=info")
+        .or_else(|_| EnvFilter::try_new("info"))
+        .expect("Failed to create tracing filter");
+    tracing_subscriber::fmt().with_env_filter(log_filter).init();
+
+    let config = HeadlessConfig::default()
+        .with_size(320, 240)
+        .with_force_fallback_adapter(std::env::var("PINE_FORCE_FALLBACK").is_ok());
+    let mut renderer =
+        pollster::block_on(Renderer2D::headless(&config)).expect("Failed to construct renderer");
+
+    // A row of squares along the x axis, one world unit apart.
+    let colors = [Color::RED, Color::GREEN, Color::BLUE, Color::WHITE];
+    let scene = (0..8).fold(SceneNode2D::new(), |scene, i| {
+        scene.add_node(
+            SceneNode2D::new()
+                .with_transform(Transform::from(i as f32 - 3.5, 0., 0.))
+                .with_renderable(Renderable::Rect {
+                    width: 0.8,
+                    height: 0.8,
+                    color: colors[i % colors.len()],
+                }),
+        )
+    });
+    renderer.set_scene_graph(scene);
+
+    // Show ten world units vertically, zoomed in and slightly rotated.
+    let camera = renderer.camera_mut();
+    camera.set_scaling_mode(ScalingMode::FixedHeight(10.));
+    camera.pan(Vec2::new(1., 0.));
+    camera.zoom_by(1.5);
+    camera.rotate(0.3);
+
+    let camera = renderer.camera();
+    let center = camera.screen_to_world(Vec2::new(160., 120.));
+    let corner = camera.screen_to_world(Vec2::ZERO);
+    tracing::info!(
+        "Screen center is at {} in the world, the top left corner at {} (back on screen at {})",
+        center,
+        corner,
+        camera.world_to_screen(corner)
+    );
+
+    let frame_data = renderer.prepare_offscreen(Color::BLACK);
+    renderer
+        .render(&frame_data)
+        .expect("Failed to render frame");
+
+    let frame = renderer.read_pixels().expect("Failed to read back frame");
+    frame.save("camera.png").expect("Failed to save frame");
+    tracing::info!(
+        "Saved {}x{} frame to camera.png",
+        frame.width(),
+        frame.height()
+    );
+}
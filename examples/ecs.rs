@@ -0,0 +1,155 @@
+use pine::{
+    prelude::{
+        Color, Entity, EventReader, Events, KeyCode, Pine, PineError, Res, SceneLink, System, Time,
+        Vec2, WindowConfig, WindowResized, World,
+    },
+    rendering::scene::{Renderable, SceneNode2D, Transform},
+    world::system::Access,
+};
+use tracing_subscriber::EnvFilter;
+
+/// How far squares may move from the center before bouncing back.
+const BOUNDS: f32 = 220.;
+
+struct Velocity(Vec2);
+
+/// Marks squares that spin, in radians per second.
+struct Spin(f32);
+
+/// Whether the squares have stopped spinning, toggled with P.
+struct Paused(bool);
+
+fn is_running(world: &World) -> bool {
+    world.resource::<Paused>().is_none_or(|paused| !paused.0)
+}
+
+/// Sent when a square bounces off an edge.
+struct Bounced(Entity);
+
+/// Bounces squares off the edges of the window.
+fn bounce(world: &mut World) {
+    let mut bounced = vec![];
+    world
+        .query::<(Entity, &mut Transform, &mut Velocity)>()
+        .for_each(|(entity, transform, velocity)| {
+            let position = transform.translation;
+            if position.x.abs() > BOUNDS {
+                velocity.0.x = -velocity.0.x.abs() * position.x.signum();
+            }
+            if position.y.abs() > BOUNDS {
+                velocity.0.y = -velocity.0.y.abs() * position.y.signum();
+            }
+            if position.abs().max_element() > BOUNDS {
+                bounced.push(Bounced(entity));
+            }
+        });
+    for event in bounced {
+        world.send_event(event);
+    }
+}
+
+/// Gives squares a new color whenever they bounce.
+fn recolor() -> System {
+    let mut access = Access::new();
+    access.read_resource::<Events<Bounced>>();
+    access.write::<Renderable>();
+
+    let mut reader = EventReader::<Bounced>::new();
+    let colors = [Color::RED, Color::GREEN, Color::BLUE, Color::WHITE];
+    let mut next = 0;
+    System::parallel("recolor", access, move |world| {
+        let Some(events) = world.events::<Bounced>() else {
+            return;
+        };
+        for Bounced(entity) in reader.read(&events) {
+            if let Some(mut renderable) = world.get_mut::<Renderable>(*entity) {
+                if let Renderable::Rect { color, .. } = &mut *renderable {
+                    *color = colors[next % colors.len()];
+                    next += 1;
+                }
+            }
+        }
+    })
+    .after("bounce")
+}
+
+fn main() -> Result<(), PineError> {
+    let log_filter = EnvFilter::try_new("pine=info,ecs=info")
+        .or_else(|_| EnvFilter::try_new("info"))
+        .expect("Failed to create tracing filter");
+    tracing_subscriber::fmt().with_env_filter(log_filter).init();
+
+    let mut resizes = EventReader::<WindowResized>::new();
+    Pine::app()
+        .with_window(WindowConfig::default().with_title("ECS"))
+        .with_setup(|ctx| {
+            tracing::info!("Systems:\n{}", ctx.schedule());
+            ctx.world_mut().insert_resource(Paused(false));
+            ctx.world_mut().add_event::<Bounced>();
+            let colors = [Color::RED, Color::GREEN, Color::BLUE, Color::WHITE];
+            for (i, color) in colors.into_iter().enumerate() {
+                let transform = Transform::from(-150. + 100. * i as f32, 0., 0.);
+                let renderable = Renderable::Rect {
+                    width: 40.,
+                    height: 40.,
+                    color,
+                };
+
+                // The scene node is what gets drawn, the entity is what the systems work with.
+                let Some(node) = ctx
+                    .scene_graph_mut(0)
+                    .and_then(|scene_graph| scene_graph.add(SceneNode2D::new()).ok())
+                else {
+                    continue;
+                };
+                let velocity = Vec2::new(60. + 30. * i as f32, 90. - 20. * i as f32);
+                let entity = ctx.world_mut().spawn((
+                    transform,
+                    renderable,
+                    Velocity(velocity),
+                    SceneLink::new(0, node),
+                ));
+                if i % 2 == 0 {
+                    ctx.world_mut()
+                        .insert(entity, Spin(2.0))
+                        .expect("Entity was just spawned");
+                }
+            }
+        })
+        .with_system(System::for_each::<(&mut Transform, &Velocity, Res<Time>)>(
+            "movement",
+            |(transform, velocity, time)| {
+                transform.translation += velocity.0 * time.delta_seconds();
+            },
+        ))
+        .with_system(
+            System::for_each::<(&mut Transform, &Spin, Res<Time>)>(
+                "spin",
+                |(transform, spin, time)| transform.rotation += spin.0 * time.delta_seconds(),
+            )
+            .run_if(is_running),
+        )
+        .with_system(System::new("bounce", bounce).after("movement"))
+        .with_system(recolor())
+        .with_update(move |ctx, _| {
+            if let Some(events) = ctx.events::<WindowResized>() {
+                for resized in resizes.read(&events) {
+                    tracing::info!("Resized to {}x{}", resized.width, resized.height);
+                }
+            }
+
+            // Stop the first square with space, by taking away its velocity.
+            if ctx.input().key_codes().just_pressed(&KeyCode::Space) {
+                let first = ctx.world().query::<(Entity, &Velocity)>().entities();
+                if let Some(&entity) = first.first() {
+                    ctx.world_mut().remove::<Velocity>(entity);
+                }
+            }
+            if ctx.input().key_codes().just_pressed(&KeyCode::KeyP) {
+                if let Some(mut paused) = ctx.world().resource_mut::<Paused>() {
+                    paused.0 = !paused.0;
+                }
+            }
+        })
+        .run()
+}
@@ -0,0 +1,81 @@
+use pine::{
+    prelude::{Color, Pine, PineError, WindowConfig},
+    rendering::scene::{Renderable, SceneNode2D, Transform},
+};
+use tracing_subscriber::EnvFilter;
+
+use std::{cell::Cell, rc::Rc};
+
+fn main() -> Result<(), PineError> {
+    let log_filter = EnvFilter::try_new("pine=info,fixed_timestep=info")
+        .or_else(|_| EnvFilter::try_new("info"))
+        .expect("Failed to create tracing filter");
+    tracing_subscriber::fmt().with_env_filter(log_filter).init();
+
+    // The ball is simulated at a deliberately low rate. Interpolation keeps it moving smoothly
+    // regardless.
+    let velocity = Rc::new(Cell::new(300.0f32));
+    let last_report = Rc::new(Cell::new(0.0f32));
+
+    Pine::app()
+        .with_window(WindowConfig::default().with_title("Fixed timestep"))
+        .with_fixed_rate(10.)
+        .with_setup(|ctx| {
+            if let Some(scene_graph) = ctx.scene_graph_mut(0) {
+                scene_graph.set_interpolation(true);
+                scene_graph
+                    .add(
+                        SceneNode2D::new()
+                            .with_name("ball")
+                            .with_renderable(Renderable::Rect {
+                                width: 30.,
+                                height: 30.,
+                                color: Color::WHITE,
+                            }),
+                    )
+                    .expect("Failed to add ball");
+            }
+        })
+        .with_fixed_update(move |ctx, dt| {
+            let Some(scene_graph) = ctx.scene_graph_mut(0) else {
+                return;
+            };
+            let Some(ball) = scene_graph
+                .find_by_name("ball")
+                .and_then(|ball| scene_graph.get_mut(ball))
+            else {
+                return;
+            };
+
+            let transform = ball.transform_mut();
+            transform.translation.x += velocity.get() * dt;
+            if transform.translation.x.abs() > 200. {
+                transform.translation.x = transform.translation.x.clamp(-200., 200.);
+                velocity.set(-velocity.get());
+            }
+        })
+        .with_update(move |ctx, _dt| {
+            let time = ctx.time();
+            if time.elapsed_seconds() - last_report.get() >= 1. {
+                last_report.set(time.elapsed_seconds());
+                tracing::info!(
+                    "Frame {} at {:.1} FPS, {} fixed steps this frame, alpha {:.2}",
+                    time.frame_count(),
+                    time.fps(),
+                    time.fixed_steps(),
+                    time.alpha()
+                );
+            }
+        })
+        .with_draw(|ctx| {
+            // The whole scene bobs along at the frame rate, so it skips interpolation.
+            let elapsed = ctx.time().elapsed_seconds();
+            if let Some(scene_graph) = ctx.scene_graph_mut(0) {
+                let root = scene_graph.root();
+                if let Some(root) = scene_graph.get_mut(root) {
+                    root.teleport(Transform::from(0., (elapsed * 2.).sin() * 20., 0.));
+                }
+            }
+        })
+        .run()
+}
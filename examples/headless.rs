@@ -0,0 +1,84 @@
+use pine::{
+    prelude::{Color, HeadlessConfig, Renderer, Renderer2D},
+    rendering::{
+        scene::{Renderable, SceneNode2D, Transform},
+        texture::TextureOptions,
+    },
+};
+use tracing_subscriber::EnvFilter;
+
+fn main() {
+    let log_filter = EnvFilter::try_new("pine=trace,headless=info")
+        .or_else(|_| EnvFilter::try_new("info"))
+        .expect("Failed to create tracing filter");
+    tracing_subscriber::fmt().with_env_filter(log_filter).init();
+
+    let config = HeadlessConfig::default()
+        .with_size(320, 240)
+        .with_force_fallback_adapter(std::env::var("PINE_FORCE_FALLBACK").is_ok());
+    let mut renderer =
+        pollster::block_on(Renderer2D::headless(&config)).expect("Failed to construct renderer");
+    tracing::info!("Rendering with adapter {:?}", renderer.adapter_info());
+
+    let checkerboard = image::RgbaImage::from_fn(8, 8, |x, y| {
+        if (x + y) % 2 == 0 {
+            image::Rgba([255, 255, 255, 255])
+        } else {
+            image::Rgba([40, 40, 40, 255])
+        }
+    });
+    let texture = renderer.create_texture(&checkerboard, TextureOptions::pixel_art());
+
+    // Loading the same file again returns the same handle, so all trees share one texture.
+    let pine_path = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/assets/pine.png");
+    let pine = renderer
+        .load_texture(pine_path, TextureOptions::pixel_art())
+        .expect("Failed to load texture");
+    let trees = (0..3).fold(SceneNode2D::new(), |trees, i| {
+        let pine = renderer
+            .load_texture(pine_path, TextureOptions::pixel_art())
+            .expect("Failed to load texture");
+        trees.add_node(
+            SceneNode2D::new()
+                .with_transform(Transform::from(-64. + 64. * i as f32, -80., 0.))
+                .with_renderable(Renderable::sprite(64., 64., pine)),
+        )
+    });
+    tracing::info!(
+        "Loaded tree texture {:?}, {} textures in cache",
+        pine,
+        renderer.texture_cache().len()
+    );
+
+    renderer.set_scene_graph(
+        SceneNode2D::new()
+            .add_node(
+                SceneNode2D::new()
+                    .with_transform(Transform::from(-80., 0., 0.))
+                    .with_renderable(Renderable::Rect {
+                        width: 100.,
+                        height: 60.,
+                        color: Color::RED,
+                    }),
+            )
+            .add_node(
+                SceneNode2D::new()
+                    .with_transform(Transform::from(80., 0., 0.))
+                    .with_renderable(Renderable::sprite(96., 96., texture)),
+            )
+            .add_node(trees),
+    );
+
+    let frame_data = renderer.prepare_offscreen(Color::BLACK);
+    renderer
+        .render(&frame_data)
+        .expect("Failed to render frame");
+
+    let frame = renderer.read_pixels().expect("Failed to read back frame");
+    frame.save("headless.png").expect("Failed to save frame");
+    tracing::info!(
+        "Saved {}x{} frame to headless.png",
+        frame.width(),
+        frame.height()
+    );
+}
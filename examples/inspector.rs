@@ -0,0 +1,39 @@
+use pine::prelude::{Color, KeyCode, Pine, PineError, WindowConfig, WindowLifecycleEvent};
+use tracing_subscriber::EnvFilter;
+
+const INSPECTOR: &str = "inspector";
+
+fn main() -> Result<(), PineError> {
+    let log_filter = EnvFilter::try_new("pine=info,inspector=info")
+        .or_else(|_| EnvFilter::try_new("info"))
+        .expect("Failed to create tracing filter");
+    tracing_subscriber::fmt().with_env_filter(log_filter).init();
+
+    Pine::app()
+        .with_window(WindowConfig::default().with_key("main").with_title("Main"))
+        .with_update(|ctx, _| {
+            // Toggle the inspector window with I.
+            if ctx.input().key_codes().just_pressed(&KeyCode::KeyI)
+                && !ctx.close_window_by_key(INSPECTOR)
+            {
+                ctx.open_window(
+                    WindowConfig::default()
+                        .with_key(INSPECTOR)
+                        .with_title("Inspector")
+                        .with_clear_color(Color::BLUE),
+                );
+            }
+        })
+        .with_lifecycle_event(|ctx, event| match event {
+            WindowLifecycleEvent::Opened { key, .. } => {
+                tracing::info!("Opened {:?}, {} window(s) open", key, ctx.windows().len());
+            }
+            WindowLifecycleEvent::Focused { key, focused, .. } => {
+                tracing::info!("{:?} focused: {}", key, focused);
+            }
+            WindowLifecycleEvent::Closed { key, .. } => {
+                tracing::info!("Closed {:?}, {} window(s) open", key, ctx.windows().len());
+            }
+        })
+        .run()
+}
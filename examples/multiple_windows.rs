@@ -1,24 +1,59 @@
-use pine::prelude::{Color, Pine, WindowConfig};
+use pine::{
+    prelude::{Color, Pine, PineError, PresentMode, WindowConfig},
+    rendering::{
+        scene::{Renderable, SceneNode2D},
+        texture::TextureOptions,
+    },
+};
 use tracing_subscriber::EnvFilter;
 
-fn main() {
+const PINE_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/assets/pine.png");
+
+fn main() -> Result<(), PineError> {
     let log_filter = EnvFilter::try_new("pine=trace")
         .or_else(|_| EnvFilter::try_new("info"))
         .expect("Failed to create tracing filter");
     tracing_subscriber::fmt().with_env_filter(log_filter).init();
 
     Pine::app()
-        .with_window(WindowConfig::default().with_clear_color(Color::RED))
+        .with_window(
+            WindowConfig::default()
+                .with_icon(PINE_PATH)
+                .with_clear_color(Color::RED),
+        )
         .with_window(
             WindowConfig::default()
                 .with_title("Second window!")
+                .with_size(400, 300)
+                .with_min_size(200, 150)
+                .with_present_mode(PresentMode::Mailbox)
                 .with_clear_color(Color::BLUE),
         )
         .with_window(
             WindowConfig::default()
                 .with_title("Non-resizable window 😯")
                 .with_resizable(false)
+                .with_always_on_top(true)
                 .with_clear_color(Color::GREEN),
         )
-        .run();
+        .with_setup(|ctx| {
+            // All windows share one GPU, so the texture is loaded once and drawn everywhere.
+            let pine = ctx
+                .gpu()
+                .expect("Windows draw on the GPU")
+                .load_texture(PINE_PATH, TextureOptions::pixel_art())
+                .expect("Failed to load pine texture");
+
+            for i in 0..ctx.windows().len() {
+                if let Some(scene_graph) = ctx.scene_graph_mut(i) {
+                    scene_graph
+                        .add(
+                            SceneNode2D::new()
+                                .with_renderable(Renderable::sprite(128., 128., pine)),
+                        )
+                        .expect("Failed to add pine");
+                }
+            }
+        })
+        .run()
 }
@@ -0,0 +1,123 @@
+use pine::{
+    prelude::{
+        Bloom, ChromaticAberration, Color, ColorGrading, ColorLut, Crt, HeadlessConfig,
+        PostProcessing, Renderer, Renderer2D, Vignette,
+    },
+    rendering::scene::{Renderable, SceneNode2D, Transform},
+};
+use tracing_subscriber::EnvFilter;
+
+/// Builds a 16x16x16 LUT giving colors a sepia tone.
+fn sepia_lut() -> image::RgbaImage {
+    const SIZE: u32 = 16;
+    image::RgbaImage::from_fn(SIZE * SIZE, SIZE, |x, y| {
+        let channel = |value: u32| value as f32 / (SIZE - 1) as f32;
+        let (r, g, b) = (channel(x % SIZE), channel(y), channel(x / SIZE));
+        let sepia = |rw: f32, gw: f32, bw: f32| ((r * rw + g * gw + b * bw).min(1.0) * 255.0) as u8;
+        image::Rgba([
+            sepia(0.393, 0.769, 0.189),
+            sepia(0.349, 0.686, 0.168),
+            sepia(0.272, 0.534, 0.131),
+            255,
+        ])
+    })
+}
+
+fn main() {
+    let log_filter = EnvFilter::try_new("pine=info,post_processing=info")
+        .or_else(|_| EnvFilter::try_new("info"))
+        .expect("Failed to create tracing filter");
+    tracing_subscriber::fmt().with_env_filter(log_filter).init();
+
+    let config = HeadlessConfig::default()
+        .with_size(320, 240)
+        .with_force_fallback_adapter(std::env::var("PINE_FORCE_FALLBACK").is_ok());
+    let mut renderer =
+        pollster::block_on(Renderer2D::headless(&config)).expect("Failed to construct renderer");
+
+    renderer.set_scene_graph(
+        SceneNode2D::new()
+            .add_node(
+                SceneNode2D::new()
+                    .with_transform(Transform::from(-70., 0., 0.))
+                    .with_renderable(Renderable::Rect {
+                        width: 80.,
+                        height: 80.,
+                        color: Color::WHITE,
+                    }),
+            )
+            .add_node(
+                SceneNode2D::new()
+                    .with_transform(Transform::from(70., 0., 0.))
+                    .with_renderable(Renderable::Rect {
+                        width: 80.,
+                        height: 80.,
+                        color: Color::RED,
+                    }),
+            ),
+    );
+
+    let lut = ColorLut::from_image(renderer.backend(), &sepia_lut()).expect("Failed to create LUT");
+    let variants = [
+        ("none", PostProcessing::default()),
+        (
+            "bloom",
+            PostProcessing::default().with_bloom(Bloom {
+                enabled: true,
+                ..Default::default()
+            }),
+        ),
+        (
+            "graded",
+            PostProcessing::default().with_color_grading(ColorGrading {
+                enabled: true,
+                strength: 1.0,
+                lut: Some(lut),
+            }),
+        ),
+        (
+            "vignette",
+            PostProcessing::default().with_vignette(Vignette {
+                enabled: true,
+                intensity: 1.0,
+                ..Default::default()
+            }),
+        ),
+        (
+            "aberration",
+            PostProcessing::default().with_chromatic_aberration(ChromaticAberration {
+                enabled: true,
+                amount: 6.0,
+            }),
+        ),
+        (
+            "crt",
+            PostProcessing::default().with_crt(Crt {
+                enabled: true,
+                ..Default::default()
+            }),
+        ),
+    ];
+
+    // The same renderer is reused, as effects can be switched between frames.
+    for (name, settings) in variants {
+        renderer
+            .set_post_processing(settings)
+            .expect("Failed to enable post-processing");
+
+        let frame_data = renderer.prepare_offscreen(Color::rgb(0.1, 0.2, 0.4));
+        renderer
+            .render(&frame_data)
+            .expect("Failed to render frame");
+
+        let path = format!("post_{}.png", name);
+        let frame = renderer.read_pixels().expect("Failed to read back frame");
+        frame.save(&path).expect("Failed to save frame");
+        tracing::info!(
+            "Saved {} with center pixel {:?} and corner pixel {:?}",
+            path,
+            frame.get_pixel(90, 120),
+            frame.get_pixel(2, 2)
+        );
+    }
+}
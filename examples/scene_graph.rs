@@ -0,0 +1,106 @@
+use pine::{
+    prelude::{Color, HeadlessConfig, Renderer, Renderer2D},
+    rendering::scene::{Renderable, SceneNode2D, Transform},
+};
+use tracing_subscriber::EnvFilter;
+
+fn main() {
+    let log_filter = EnvFilter::try_new("pine=debug,scene_graph=info")
+        .or_else(|_| EnvFilter::try_new("info"))
+        .expect("Failed to create tracing filter");
+    tracing_subscriber::fmt().with_env_filter(log_filter).init();
+
+    let config = HeadlessConfig::default()
+        .with_size(320, 240)
+        .with_force_fallback_adapter(std::env::var("PINE_FORCE_FALLBACK").is_ok());
+    let mut renderer =
+        pollster::block_on(Renderer2D::headless(&config)).expect("Failed to construct renderer");
+
+    let crate_node = |x: f32, color: Color| {
+        SceneNode2D::new()
+            .with_tag("crate")
+            .with_transform(Transform::from(x, 0., 0.))
+            .with_renderable(Renderable::Rect {
+                width: 30.,
+                height: 30.,
+                color,
+            })
+    };
+
+    // Two shelves holding a few crates each.
+    let scene_graph = renderer.scene_graph_mut();
+    let left = scene_graph
+        .add(
+            SceneNode2D::new()
+                .with_name("left shelf")
+                .with_transform(Transform::from(-80., 0., 0.))
+                .add_node(crate_node(-20., Color::RED).with_name("red crate"))
+                .add_node(crate_node(20., Color::GREEN)),
+        )
+        .expect("Failed to add left shelf");
+    let right = scene_graph
+        .add(
+            SceneNode2D::new()
+                .with_name("right shelf")
+                .with_transform(Transform::from(80., 40., 0.)),
+        )
+        .expect("Failed to add right shelf");
+    let blue = scene_graph
+        .insert(right, crate_node(0., Color::BLUE))
+        .expect("Failed to add blue crate");
+
+    // Names are unique, so a second red crate is rejected.
+    if let Err(err) = scene_graph.add(SceneNode2D::new().with_name("red crate")) {
+        tracing::info!("Rejected duplicate name: {}", err);
+    }
+
+    // Move the red crate onto the right shelf. It keeps its local transform and follows the shelf.
+    let red = scene_graph
+        .find_by_name("red crate")
+        .expect("The red crate is part of the scene");
+    scene_graph
+        .reparent(red, right)
+        .expect("Failed to move red crate");
+
+    // A shelf can't be put onto one of its own crates.
+    if let Err(err) = scene_graph.reparent(right, blue) {
+        tracing::info!("Rejected reparenting: {}", err);
+    }
+
+    // Take the left shelf, with whatever is left on it, out of the scene.
+    let removed = scene_graph.remove(left);
+    tracing::info!("Removed left shelf: {}", removed.is_some());
+
+    let frame_data = renderer.prepare_offscreen(Color::BLACK);
+    renderer
+        .render(&frame_data)
+        .expect("Failed to render frame");
+
+    let scene_graph = renderer.scene_graph();
+    for crate_id in scene_graph.find_by_tag("crate") {
+        let node = scene_graph.get(crate_id).expect("Found nodes exist");
+        tracing::info!(
+            "Crate {} ({:?}) is at {}",
+            crate_id,
+            node.name(),
+            node.global_transform().translation()
+        );
+    }
+    let depth_first: Vec<_> = scene_graph
+        .iter_depth_first(scene_graph.root())
+        .map(|node| node.id())
+        .collect();
+    tracing::info!(
+        "{} nodes in depth-first order: {:?}",
+        scene_graph.len(),
+        depth_first
+    );
+
+    let frame = renderer.read_pixels().expect("Failed to read back frame");
+    frame.save("scene_graph.png").expect("Failed to save frame");
+    tracing::info!(
+        "Saved {}x{} frame to scene_graph.png",
+        frame.width(),
+        frame.height()
+    );
+}
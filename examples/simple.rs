@@ -1,13 +1,79 @@
-use pine::prelude::{Pine, WindowConfig};
+use pine::{
+    prelude::{Bloom, Color, KeyCode, Pine, PineError, PostProcessing, WindowConfig},
+    rendering::scene::{Renderable, SceneNode2D},
+};
 use tracing_subscriber::EnvFilter;
 
-fn main() {
-    let log_filter = EnvFilter::try_new("pine=trace")
+use std::{cell::Cell, rc::Rc};
+
+const CONTROLS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/assets/controls.ron");
+
+fn main() -> Result<(), PineError> {
+    let log_filter = EnvFilter::try_new("pine=trace,simple=info")
         .or_else(|_| EnvFilter::try_new("info"))
         .expect("Failed to create tracing filter");
     tracing_subscriber::fmt().with_env_filter(log_filter).init();
 
+    let direction = Rc::new(Cell::new(1.0f32));
+
     Pine::app()
-        .with_window(WindowConfig::default().with_resizable(false))
-        .run();
+        .with_window(
+            WindowConfig::default()
+                .with_resizable(false)
+                .with_post_processing(PostProcessing::default().with_bloom(Bloom {
+                    enabled: true,
+                    ..Default::default()
+                })),
+        )
+        .with_setup(|ctx| {
+            if let Err(err) = ctx.input_mut().load_actions(CONTROLS_PATH) {
+                tracing::error!("Failed to load controls: {}", err);
+            }
+
+            let square = SceneNode2D::new()
+                .with_name("square")
+                .with_renderable(Renderable::Rect {
+                    width: 100.,
+                    height: 100.,
+                    color: Color::GREEN,
+                });
+            if let Some(scene_graph) = ctx.scene_graph_mut(0) {
+                scene_graph.add(square).expect("Failed to add square");
+            }
+        })
+        .with_update(move |ctx, dt| {
+            let input = ctx.input();
+            if input.is_action_just_pressed("quit") {
+                ctx.exit();
+                return;
+            }
+            if input.is_action_just_pressed("reverse") {
+                direction.set(-direction.get());
+            }
+
+            // Spin the square, faster while boosting.
+            let speed = if input.is_action_pressed("boost") {
+                4.
+            } else {
+                1.
+            };
+            let angle = direction.get() * speed * dt;
+
+            // Toggle bloom with B and the CRT look with C.
+            let toggle_bloom = input.key_codes().just_pressed(&KeyCode::KeyB);
+            let toggle_crt = input.key_codes().just_pressed(&KeyCode::KeyC);
+            if let Some(post) = ctx.post_processing_mut(0) {
+                post.bloom.enabled ^= toggle_bloom;
+                post.crt.enabled ^= toggle_crt;
+            }
+            if let Some(scene_graph) = ctx.scene_graph_mut(0) {
+                if let Some(square) = scene_graph
+                    .find_by_name("square")
+                    .and_then(|square| scene_graph.get_mut(square))
+                {
+                    square.transform_mut().rotation += angle;
+                }
+            }
+        })
+        .run()
 }
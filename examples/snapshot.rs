@@ -0,0 +1,36 @@
+use pine::{
+    prelude::{Color, HeadlessConfig, Renderer2D},
+    rendering::snapshot::{assert_snapshot, SnapshotConfig},
+};
+use tracing_subscriber::EnvFilter;
+
+fn main() {
+    let log_filter = EnvFilter::try_new("pine=trace,snapshot=info")
+        .or_else(|_| EnvFilter::try_new("info"))
+        .expect("Failed to create tracing filter");
+    tracing_subscriber::fmt().with_env_filter(log_filter).init();
+
+    let config = HeadlessConfig::default()
+        .with_size(128, 128)
+        .with_force_fallback_adapter(std::env::var("PINE_FORCE_FALLBACK").is_ok());
+    let renderer =
+        pollster::block_on(Renderer2D::headless(&config)).expect("Failed to construct renderer");
+
+    // Run with PINE_UPDATE_SNAPSHOTS=1 the first time to store the snapshot.
+    let snapshot_config = SnapshotConfig::default()
+        .with_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/target/snapshots"))
+        .with_tolerance(1);
+
+    let frame_data = renderer.prepare_offscreen(Color::BLUE);
+    match assert_snapshot(&renderer, &frame_data, "clear_blue", &snapshot_config) {
+        Ok(diff) => tracing::info!(
+            "Snapshot matched ({} similar pixels, max delta {})",
+            diff.similar_pixels,
+            diff.max_delta
+        ),
+        Err(err) => {
+            tracing::error!("Snapshot failed: {}", err);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use pine::{
+    prelude::{Color, HeadlessConfig, Renderer, Renderer2D},
+    rendering::{
+        scene::{Renderable, SceneNode2D, Transform},
+        texture::{TextureAtlas, TextureOptions},
+    },
+};
+use tracing_subscriber::EnvFilter;
+
+const TILE_SIZE: u32 = 8;
+const COLUMNS: u32 = 80;
+const ROWS: u32 = 60;
+
+fn main() {
+    let log_filter = EnvFilter::try_new("pine=debug,tiles=info")
+        .or_else(|_| EnvFilter::try_new("info"))
+        .expect("Failed to create tracing filter");
+    tracing_subscriber::fmt().with_env_filter(log_filter).init();
+
+    let config = HeadlessConfig::default()
+        .with_size(COLUMNS * TILE_SIZE, ROWS * TILE_SIZE)
+        .with_force_fallback_adapter(std::env::var("PINE_FORCE_FALLBACK").is_ok());
+    let mut renderer =
+        pollster::block_on(Renderer2D::headless(&config)).expect("Failed to construct renderer");
+
+    // A 2x2 tileset with one color per tile.
+    let colors = [
+        [90, 160, 60],
+        [200, 180, 100],
+        [60, 110, 200],
+        [120, 120, 120],
+    ];
+    let tileset = image::RgbaImage::from_fn(TILE_SIZE * 2, TILE_SIZE * 2, |x, y| {
+        let [r, g, b] = colors[((y / TILE_SIZE) * 2 + x / TILE_SIZE) as usize];
+        image::Rgba([r, g, b, 255])
+    });
+    let texture = renderer.create_texture(&tileset, TextureOptions::pixel_art());
+    let atlas =
+        TextureAtlas::from_grid(texture, tileset.dimensions(), (TILE_SIZE, TILE_SIZE), 2, 2);
+
+    let (origin_x, origin_y) = (
+        -((COLUMNS * TILE_SIZE) as f32 - TILE_SIZE as f32) / 2.,
+        -((ROWS * TILE_SIZE) as f32 - TILE_SIZE as f32) / 2.,
+    );
+
+    let mut scene = SceneNode2D::new();
+    for row in 0..ROWS {
+        for column in 0..COLUMNS {
+            let tile = ((row / 4 + column / 4) % atlas.len() as u32) as usize;
+            let renderable =
+                Renderable::atlas_sprite(TILE_SIZE as f32, TILE_SIZE as f32, &atlas, tile)
+                    .expect("Tile index out of range");
+            scene = scene.add_node(
+                SceneNode2D::new()
+                    .with_transform(Transform::from(
+                        origin_x + (column * TILE_SIZE) as f32,
+                        origin_y + (row * TILE_SIZE) as f32,
+                        0.,
+                    ))
+                    .with_renderable(renderable),
+            );
+        }
+    }
+
+    // A marker on a higher layer, drawn on top of the tiles.
+    scene = scene.add_node(
+        SceneNode2D::new()
+            .with_transform(Transform::from(0., 0., 1.))
+            .with_renderable(Renderable::Rect {
+                width: 40.,
+                height: 40.,
+                color: Color::RED,
+            }),
+    );
+    renderer.set_scene_graph(scene);
+
+    let frame_data = renderer.prepare_offscreen(Color::BLACK);
+    renderer
+        .render(&frame_data)
+        .expect("Failed to render frame");
+    tracing::info!("{:?}", renderer.batch_stats());
+
+    let frame = renderer.read_pixels().expect("Failed to read back frame");
+    frame.save("tiles.png").expect("Failed to save frame");
+    tracing::info!(
+        "Saved {}x{} frame to tiles.png",
+        frame.width(),
+        frame.height()
+    );
+}
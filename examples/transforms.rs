@@ -0,0 +1,85 @@
+use pine::{
+    prelude::{Color, HeadlessConfig, Renderer, Renderer2D, Vec2},
+    rendering::scene::{Renderable, SceneNode2D, Transform},
+};
+use tracing_subscriber::EnvFilter;
+
+use std::f32::consts::FRAC_PI_4;
+
+fn main() {
+    let log_filter = EnvFilter::try_new("pine=debug,transforms=info")
+        .or_else(|_| EnvFilter::try_new("info"))
+        .expect("Failed to create tracing filter");
+    tracing_subscriber::fmt().with_env_filter(log_filter).init();
+
+    let config = HeadlessConfig::default()
+        .with_size(320, 240)
+        .with_force_fallback_adapter(std::env::var("PINE_FORCE_FALLBACK").is_ok());
+    let mut renderer =
+        pollster::block_on(Renderer2D::headless(&config)).expect("Failed to construct renderer");
+
+    // A sun with an orbiting planet, which in turn has an orbiting moon. Rotating and scaling a
+    // parent carries its children along.
+    let moon = SceneNode2D::new()
+        .with_name("moon")
+        .with_transform(Transform::from(40., 0., 0.).with_rotation(FRAC_PI_4))
+        .with_renderable(Renderable::Rect {
+            width: 10.,
+            height: 10.,
+            color: Color::WHITE,
+        });
+    let planet = SceneNode2D::new()
+        .with_name("planet")
+        .with_transform(Transform::from(90., 0., 1.).with_scale(Vec2::new(1.5, 0.75)))
+        .with_renderable(Renderable::Rect {
+            width: 24.,
+            height: 24.,
+            color: Color::BLUE,
+        })
+        .add_node(moon);
+    let sun = SceneNode2D::new()
+        .with_name("sun")
+        .with_renderable(Renderable::Rect {
+            width: 60.,
+            height: 60.,
+            color: Color::rgb(1., 0.8, 0.),
+        })
+        .add_node(planet);
+    renderer.set_scene_graph(sun);
+
+    // Spin the whole system a bit. Only the root is marked dirty, but its children follow.
+    let scene_graph = renderer.scene_graph_mut();
+    let sun = scene_graph.root();
+    if let Some(sun) = scene_graph.get_mut(sun) {
+        sun.transform_mut().rotation = FRAC_PI_4 / 2.;
+    }
+
+    let frame_data = renderer.prepare_offscreen(Color::BLACK);
+    renderer
+        .render(&frame_data)
+        .expect("Failed to render frame");
+
+    let scene_graph = renderer.scene_graph();
+    let moon = scene_graph
+        .find_by_name("moon")
+        .and_then(|moon| scene_graph.get(moon))
+        .expect("The moon is part of the scene");
+    tracing::info!(
+        "The moon ended up at {} in the world",
+        moon.global_transform().translation()
+    );
+
+    let order: Vec<_> = scene_graph
+        .iter_breadth_first(scene_graph.root())
+        .filter_map(|node| node.name())
+        .collect();
+    tracing::info!("Nodes in breadth-first order: {:?}", order);
+
+    let frame = renderer.read_pixels().expect("Failed to read back frame");
+    frame.save("transforms.png").expect("Failed to save frame");
+    tracing::info!(
+        "Saved {}x{} frame to transforms.png",
+        frame.width(),
+        frame.height()
+    );
+}
@@ -1,22 +1,84 @@
 use winit::{
-    event::{Event as WinitEvent, WindowEvent},
-    event_loop::{ControlFlow, EventLoop},
-    keyboard::{Key, NamedKey},
+    event::{Event as WinitEvent, StartCause, WindowEvent},
+    event_loop::{ControlFlow, EventLoop, EventLoopWindowTarget},
+    window::WindowId,
 };
 
 use crate::{
+    context::{Context, WindowCommand},
     error::PineError,
-    windowing::{Window, WindowConfig},
+    input::{Input, InputEvent},
+    rendering::gpu::GpuContext,
+    time::Time,
+    windowing::{Window, WindowConfig, WindowLifecycleEvent, WindowResized},
+    world::{
+        scene,
+        schedule::{Schedule, Stage},
+        system::System,
+        World,
+    },
 };
 
+use std::sync::Arc;
+
+/// The default number of fixed updates per second.
+const DEFAULT_FIXED_RATE: f64 = 60.0;
+/// The default maximum number of fixed updates per frame.
+const DEFAULT_MAX_FIXED_STEPS: u32 = 5;
+
+type SetupCallback = Box<dyn FnMut(&mut Context)>;
+type UpdateCallback = Box<dyn FnMut(&mut Context, f32)>;
+type FixedUpdateCallback = Box<dyn FnMut(&mut Context, f32)>;
+type DrawCallback = Box<dyn FnMut(&mut Context)>;
+type EventCallback = Box<dyn FnMut(&mut Context, WindowId, &WindowEvent)>;
+type LifecycleCallback = Box<dyn FnMut(&mut Context, &WindowLifecycleEvent)>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Changes in the lifecycle of the app, published as events.
+pub enum AppLifecycleEvent {
+    /// The setup callback and the startup systems ran, the first frame is next.
+    Started,
+    /// The app was sent to the background, e.g. on mobile. Windows may lose their surfaces.
+    Suspended,
+    /// The app can render. Sent once at startup on desktop platforms, and whenever the app comes
+    /// back to the foreground on mobile.
+    Resumed,
+}
+
+#[derive(Default)]
+/// The user callbacks hooked into the event loop.
+struct Callbacks {
+    setup: Option<SetupCallback>,
+    fixed_update: Option<FixedUpdateCallback>,
+    update: Option<UpdateCallback>,
+    draw: Option<DrawCallback>,
+    event: Option<EventCallback>,
+    lifecycle: Option<LifecycleCallback>,
+}
+
 /// Holds the relevant items for the Pine engine.
 pub struct Pine {
+    /// Only created once a window draws to a surface.
+    gpu: Option<Arc<GpuContext>>,
     windows: Vec<Window>,
+    input: Input,
+    callbacks: Callbacks,
+    time: Time,
+    world: World,
+    schedule: Schedule,
+    shader_hot_reload: bool,
+    /// The error that stopped the event loop, if any.
+    error: Option<PineError>,
 }
 
 /// The Pine configuration.
 pub struct PineConfig {
     window_configs: Vec<WindowConfig>,
+    callbacks: Callbacks,
+    schedule: Schedule,
+    fixed_rate: f64,
+    max_fixed_steps: u32,
+    shader_hot_reload: bool,
 }
 
 impl Pine {
@@ -26,89 +88,382 @@ impl Pine {
     ///
     /// # Example
     ///
-    /// ```
-    /// Pine::app().with_window(WindowConfig::default()).run();
+    /// ```no_run
+    /// # use pine::prelude::{Pine, PineError, WindowConfig};
+    /// Pine::app().with_window(WindowConfig::default()).run()?;
+    /// # Ok::<(), PineError>(())
     /// ```
     pub fn app() -> PineConfig {
         PineConfig::new()
     }
 
     /// Constructs a new Pine instance.
-    pub fn new(windows: Vec<Window>) -> Self {
-        Self { windows }
+    ///
+    /// The windows are expected to draw with the given GPU context.
+    pub fn new(gpu: Arc<GpuContext>, windows: Vec<Window>) -> Self {
+        Self::with_windows(Some(gpu), windows)
+    }
+
+    /// Constructs a new Pine instance, with a GPU context if any of the windows draws with one.
+    fn with_windows(gpu: Option<Arc<GpuContext>>, windows: Vec<Window>) -> Self {
+        let mut world = World::new();
+        world.add_event::<AppLifecycleEvent>();
+        world.add_event::<WindowLifecycleEvent>();
+        world.add_event::<WindowResized>();
+        world.add_event::<InputEvent>();
+
+        Self {
+            gpu,
+            windows,
+            input: Input::default(),
+            callbacks: Callbacks::default(),
+            time: Time::new(DEFAULT_FIXED_RATE, DEFAULT_MAX_FIXED_STEPS),
+            world,
+            schedule: Schedule::new(),
+            shader_hot_reload: cfg!(debug_assertions),
+            error: None,
+        }
     }
 
     /// Spins up the pine engine.
-    pub fn run(mut self, event_loop: EventLoop<()>) {
+    ///
+    /// Returns once all windows are closed or an exit was requested. Errors that stop the engine,
+    /// such as a renderer failing, are returned instead of being logged.
+    pub fn run(mut self, event_loop: EventLoop<()>) -> Result<(), PineError> {
         event_loop.set_control_flow(ControlFlow::Poll);
         let result = event_loop
             .run(|event, elwt| match event {
-                WinitEvent::WindowEvent { window_id, event } => match event {
-                    WindowEvent::KeyboardInput { event, .. } => {
-                        if event.logical_key == Key::Named(NamedKey::Space) {
-                            tracing::info!("Space key pressed!");
-                        }
-                    }
-                    WindowEvent::RedrawRequested => {
-                        if let Some(window) = self
-                            .windows
-                            .iter()
-                            .find(|window| window.handle.id() == window_id)
-                        {
-                            let frame_data = window.renderer.prepare(&window);
-                            if let Some(frame_data) = frame_data.ok() {
-                                window.renderer.render(&frame_data);
-                            }
-                        } else {
-                            tracing::warn!(
-                                "Redraw requested for window {:?} but no such window was found",
-                                window_id
-                            );
-                        }
-                    }
-                    WindowEvent::Resized(new_size) => {
-                        tracing::info!("Window {:?} resized to {:?}", window_id, new_size);
-                        if let Some(window) = self
-                            .windows
-                            .iter_mut()
-                            .find(|window| window.handle.id() == window_id)
-                        {
-                            window.renderer.resize(new_size);
-                        }
-                    }
-                    WindowEvent::ScaleFactorChanged {
-                        scale_factor,
-                        inner_size_writer: _inner_size_writer,
-                    } => {
-                        tracing::info!("Scale factor changed to {}", scale_factor);
-                    }
-                    WindowEvent::CloseRequested => {
-                        tracing::info!("Window close requested for window {:?}", window_id);
-                        if let Some(i) = self
-                            .windows
-                            .iter()
-                            .position(|window| window.handle.id() == window_id)
-                        {
-                            self.windows.remove(i);
-                            tracing::info!("Window {:?} closed", window_id);
+                WinitEvent::NewEvents(StartCause::Init) => {
+                    self.with_context(elwt, |callbacks, context| {
+                        if let Some(setup) = &mut callbacks.setup {
+                            setup(context);
                         }
+                    });
+                    self.insert_frame_resources();
+                    self.run_stage(Stage::Startup);
+                    self.world.send_event(AppLifecycleEvent::Started);
 
-                        if self.windows.is_empty() {
-                            tracing::info!("No more windows. Shutting down...");
-                            elwt.exit();
-                        }
+                    let opened: Vec<_> = self
+                        .windows
+                        .iter()
+                        .map(|window| WindowLifecycleEvent::Opened {
+                            id: window.handle.id(),
+                            key: window.key.clone(),
+                        })
+                        .collect();
+                    for event in opened {
+                        self.emit_lifecycle_event(elwt, event);
                     }
-                    _ => {}
-                },
+
+                    self.time.reset();
+                }
+                WinitEvent::Suspended => self.world.send_event(AppLifecycleEvent::Suspended),
+                WinitEvent::Resumed => self.world.send_event(AppLifecycleEvent::Resumed),
+                WinitEvent::AboutToWait => self.update(elwt),
+                WinitEvent::WindowEvent { window_id, event } => {
+                    self.handle_window_event(elwt, window_id, event)
+                }
                 _ => {}
             })
-            .map_err(|err| PineError::EventLoopError(err));
+            .map_err(PineError::EventLoopError);
+
+        match self.error.take() {
+            Some(err) => Err(err),
+            None => result,
+        }
+    }
+
+    /// Stops the event loop because of the given error, which is returned from [`Pine::run`].
+    fn fail(&mut self, elwt: &EventLoopWindowTarget<()>, err: PineError) {
+        tracing::error!("Pine error: {}", err);
+        self.error.get_or_insert(err);
+        elwt.exit();
+    }
+
+    /// Runs a frame: as many fixed updates as are due, then the update callback and the draw
+    /// callback with the stages of systems around them, before asking all windows to redraw.
+    fn update(&mut self, elwt: &EventLoopWindowTarget<()>) {
+        self.time.advance();
+        self.world.update_events();
+        self.insert_frame_resources();
+
+        while self.time.consume_fixed_step() {
+            for window in &mut self.windows {
+                window.renderer.scene_graph_mut().snapshot_transforms();
+            }
+
+            let fixed_dt = self.time.fixed_delta_seconds();
+            self.with_context(elwt, |callbacks, context| {
+                if let Some(fixed_update) = &mut callbacks.fixed_update {
+                    fixed_update(context, fixed_dt);
+                }
+            });
+            if !self.schedule.is_empty() {
+                self.world.insert_resource(self.time.fixed_time());
+            }
+            self.run_stage(Stage::FixedUpdate);
+        }
+
+        self.run_stage(Stage::PreUpdate);
+        let dt = self.time.delta_seconds();
+        self.with_context(elwt, |callbacks, context| {
+            if let Some(update) = &mut callbacks.update {
+                update(context, dt);
+            }
+        });
+        for stage in [Stage::Update, Stage::PostUpdate, Stage::RenderPrepare] {
+            self.run_stage(stage);
+        }
+        scene::sync_scenes(&self.world, &mut self.windows);
+        self.with_context(elwt, |callbacks, context| {
+            if let Some(draw) = &mut callbacks.draw {
+                draw(context);
+            }
+        });
+        self.input.end_frame();
+
+        self.reload_shaders();
 
-        match result {
-            Ok(_) => (),
-            Err(err) => tracing::error!("Pine error: {:?}", err),
+        let alpha = self.time.alpha();
+        for window in &mut self.windows {
+            window
+                .renderer
+                .scene_graph_mut()
+                .set_interpolation_alpha(alpha);
+            window.handle.request_redraw();
         }
     }
+
+    /// Swaps in the shaders whose files changed, if hot reloading is on.
+    ///
+    /// Shader files aren't even checked otherwise, so the loaded modules always match the
+    /// pipelines drawing with them.
+    fn reload_shaders(&mut self) {
+        let shaders_changed = self.shader_hot_reload
+            && self
+                .gpu
+                .as_ref()
+                .is_some_and(|gpu| !gpu.poll_shaders().is_empty());
+        if shaders_changed {
+            for window in &mut self.windows {
+                window.renderer.update_shaders();
+            }
+        }
+    }
+
+    /// Makes the frame's timing available to systems.
+    fn insert_frame_resources(&mut self) {
+        if !self.schedule.is_empty() {
+            self.world.insert_resource(self.time.clone());
+            self.world.insert_resource(self.time.fixed_time());
+        }
+    }
+
+    /// Runs the systems of a stage, lending them the input state as a resource.
+    ///
+    /// The input is moved into the world and back rather than copied, so changes systems make
+    /// through `ResMut<Input>` are kept.
+    fn run_stage(&mut self, stage: Stage) {
+        if self.schedule.is_empty() {
+            return;
+        }
+        self.world.insert_resource(std::mem::take(&mut self.input));
+        self.schedule.run(stage, &mut self.world);
+        self.input = self.world.remove_resource().unwrap_or_default();
+    }
+
+    fn handle_window_event(
+        &mut self,
+        elwt: &EventLoopWindowTarget<()>,
+        window_id: WindowId,
+        event: WindowEvent,
+    ) {
+        // Windows are already gone by the time they're destroyed, so fall back to a scale of 1.
+        let scale_factor = self
+            .window(window_id)
+            .map_or(1.0, |window| window.handle.scale_factor());
+        self.input.handle_event(window_id, &event, scale_factor);
+        self.publish_window_event(window_id, &event, scale_factor);
+
+        self.with_context(elwt, |callbacks, context| {
+            if let Some(on_event) = &mut callbacks.event {
+                on_event(context, window_id, &event);
+            }
+        });
+
+        match event {
+            WindowEvent::RedrawRequested => {
+                if let Some(window) = self.window(window_id) {
+                    let result = window
+                        .renderer
+                        .prepare(window)
+                        .and_then(|frame_data| window.renderer.render(&frame_data));
+                    if let Err(err) = result {
+                        tracing::error!("Failed to render window {:?}", window_id);
+                        self.fail(elwt, err);
+                    }
+                } else {
+                    tracing::warn!(
+                        "Redraw requested for window {:?} but no such window was found",
+                        window_id
+                    );
+                }
+            }
+            WindowEvent::Resized(new_size) => {
+                tracing::info!("Window {:?} resized to {:?}", window_id, new_size);
+                if let Some(window) = self.window_mut(window_id) {
+                    window.renderer.resize(new_size);
+                }
+            }
+            WindowEvent::ScaleFactorChanged {
+                scale_factor,
+                inner_size_writer: _inner_size_writer,
+            } => {
+                tracing::info!("Scale factor changed to {}", scale_factor);
+                if let Some(window) = self.window_mut(window_id) {
+                    window.renderer.set_scale_factor(scale_factor);
+                }
+            }
+            WindowEvent::Focused(focused) => {
+                if let Some(window) = self.window(window_id) {
+                    let event = WindowLifecycleEvent::Focused {
+                        id: window_id,
+                        key: window.key.clone(),
+                        focused,
+                    };
+                    self.emit_lifecycle_event(elwt, event);
+                }
+            }
+            WindowEvent::CloseRequested => {
+                tracing::info!("Window close requested for window {:?}", window_id);
+                self.close_window(elwt, window_id);
+            }
+            _ => {}
+        }
+    }
+
+    /// Sends the input and resizes among the window events to the world's event channels.
+    fn publish_window_event(
+        &mut self,
+        window_id: WindowId,
+        event: &WindowEvent,
+        scale_factor: f64,
+    ) {
+        if let Some(input) = InputEvent::from_window_event(window_id, event, scale_factor) {
+            self.world.send_event(input);
+        }
+        if let WindowEvent::Resized(size) = event {
+            self.world.send_event(WindowResized {
+                id: window_id,
+                width: size.width,
+                height: size.height,
+            });
+        }
+    }
+
+    /// Hands the callbacks a context, exiting afterwards if they asked to.
+    fn with_context(
+        &mut self,
+        elwt: &EventLoopWindowTarget<()>,
+        f: impl FnOnce(&mut Callbacks, &mut Context),
+    ) {
+        let mut context = Context::new(
+            self.gpu.as_ref(),
+            &mut self.windows,
+            &mut self.input,
+            &self.time,
+            &mut self.world,
+            &self.schedule,
+        );
+        f(&mut self.callbacks, &mut context);
+
+        if context.exit_requested() {
+            tracing::info!("Exit requested. Shutting down...");
+            elwt.exit();
+        }
+
+        for command in context.take_commands() {
+            match command {
+                WindowCommand::Open(config) => self.open_window(elwt, &config),
+                WindowCommand::Close(id) => self.close_window(elwt, id),
+            }
+        }
+    }
+
+    /// Opens a window at runtime, unless its key is already taken.
+    fn open_window(&mut self, elwt: &EventLoopWindowTarget<()>, config: &WindowConfig) {
+        if let Some(key) = config.key() {
+            if self.windows.iter().any(|w| w.key.as_deref() == Some(key)) {
+                tracing::warn!("Not opening window, key {:?} is already taken", key);
+                return;
+            }
+        }
+
+        let window = match build_window(config, elwt, &mut self.gpu) {
+            Ok(window) => window,
+            Err(err) => {
+                tracing::error!("Failed to open window: {}", err);
+                return;
+            }
+        };
+
+        let event = WindowLifecycleEvent::Opened {
+            id: window.handle.id(),
+            key: window.key.clone(),
+        };
+        tracing::info!("Window {:?} opened", window.handle.id());
+        self.windows.push(window);
+        self.emit_lifecycle_event(elwt, event);
+    }
+
+    /// Closes a window, shutting down if it was the last one.
+    fn close_window(&mut self, elwt: &EventLoopWindowTarget<()>, id: WindowId) {
+        let Some(i) = self
+            .windows
+            .iter()
+            .position(|window| window.handle.id() == id)
+        else {
+            return;
+        };
+
+        let window = self.windows.remove(i);
+        tracing::info!("Window {:?} closed", id);
+        self.emit_lifecycle_event(
+            elwt,
+            WindowLifecycleEvent::Closed {
+                id,
+                key: window.key.clone(),
+            },
+        );
+
+        if self.windows.is_empty() {
+            tracing::info!("No more windows. Shutting down...");
+            elwt.exit();
+        }
+    }
+
+    fn emit_lifecycle_event(
+        &mut self,
+        elwt: &EventLoopWindowTarget<()>,
+        event: WindowLifecycleEvent,
+    ) {
+        self.world.send_event(event.clone());
+        self.with_context(elwt, |callbacks, context| {
+            if let Some(lifecycle) = &mut callbacks.lifecycle {
+                lifecycle(context, &event);
+            }
+        });
+    }
+
+    fn window(&self, id: WindowId) -> Option<&Window> {
+        self.windows.iter().find(|window| window.handle.id() == id)
+    }
+
+    fn window_mut(&mut self, id: WindowId) -> Option<&mut Window> {
+        self.windows
+            .iter_mut()
+            .find(|window| window.handle.id() == id)
+    }
 }
 
 impl PineConfig {
@@ -116,6 +471,11 @@ impl PineConfig {
     pub fn new() -> Self {
         PineConfig {
             window_configs: vec![],
+            callbacks: Callbacks::default(),
+            schedule: Schedule::new(),
+            fixed_rate: DEFAULT_FIXED_RATE,
+            max_fixed_steps: DEFAULT_MAX_FIXED_STEPS,
+            shader_hot_reload: cfg!(debug_assertions),
         }
     }
 
@@ -127,20 +487,261 @@ impl PineConfig {
         self
     }
 
+    /// Sets the callback run once, after the windows are opened and before the first update.
+    pub fn with_setup(&mut self, setup: impl FnMut(&mut Context) + 'static) -> &mut Self {
+        self.callbacks.setup = Some(Box::new(setup));
+        self
+    }
+
+    /// Adds a system, run in the [`Stage`] it was configured for.
+    ///
+    /// Systems that don't conflict run in parallel, see [`Schedule`]. The frame's [`Time`] and
+    /// [`Input`](crate::prelude::Input) are available to them as resources.
+    pub fn with_system(&mut self, system: System) -> &mut Self {
+        self.schedule.add_system(system);
+        self
+    }
+
+    /// Sets the number of fixed updates per second. Defaults to 60.
+    pub fn with_fixed_rate(&mut self, fixed_rate: f64) -> &mut Self {
+        self.fixed_rate = fixed_rate;
+        self
+    }
+
+    /// Sets the maximum number of fixed updates run per frame. Defaults to 5.
+    ///
+    /// Should the game fall further behind, the excess time is dropped and the game slows down
+    /// instead of grinding to a halt.
+    pub fn with_max_fixed_steps(&mut self, max_fixed_steps: u32) -> &mut Self {
+        self.max_fixed_steps = max_fixed_steps;
+        self
+    }
+
+    /// Sets whether shader files are watched and reloaded when they change.
+    ///
+    /// Defaults to on in debug builds and off in release builds.
+    pub fn with_shader_hot_reload(&mut self, shader_hot_reload: bool) -> &mut Self {
+        self.shader_hot_reload = shader_hot_reload;
+        self
+    }
+
+    /// Sets the callback run at the fixed rate with the fixed time step in seconds.
+    ///
+    /// The place for physics and other logic that should behave the same regardless of the frame
+    /// rate. Runs before the update callback, zero or more times per frame.
+    pub fn with_fixed_update(
+        &mut self,
+        fixed_update: impl FnMut(&mut Context, f32) + 'static,
+    ) -> &mut Self {
+        self.callbacks.fixed_update = Some(Box::new(fixed_update));
+        self
+    }
+
+    /// Sets the callback run every frame with the time since the last frame in seconds.
+    pub fn with_update(&mut self, update: impl FnMut(&mut Context, f32) + 'static) -> &mut Self {
+        self.callbacks.update = Some(Box::new(update));
+        self
+    }
+
+    /// Sets the callback run every frame after the update, right before the windows are redrawn.
+    ///
+    /// The place to sync scenes and cameras with the state of the game.
+    pub fn with_draw(&mut self, draw: impl FnMut(&mut Context) + 'static) -> &mut Self {
+        self.callbacks.draw = Some(Box::new(draw));
+        self
+    }
+
+    /// Sets the callback run for every window event, after the input state has been updated.
+    pub fn with_event(
+        &mut self,
+        event: impl FnMut(&mut Context, WindowId, &WindowEvent) + 'static,
+    ) -> &mut Self {
+        self.callbacks.event = Some(Box::new(event));
+        self
+    }
+
+    /// Sets the callback run when a window is opened, focused or closed.
+    ///
+    /// Windows from the config are announced as opened right after the setup callback.
+    pub fn with_lifecycle_event(
+        &mut self,
+        lifecycle: impl FnMut(&mut Context, &WindowLifecycleEvent) + 'static,
+    ) -> &mut Self {
+        self.callbacks.lifecycle = Some(Box::new(lifecycle));
+        self
+    }
+
     /// Constructs a Pine instance from the config.
-    pub fn build(&mut self, event_loop: &EventLoop<()>) -> Pine {
+    ///
+    /// All windows share one GPU context, picked to be compatible with the first window drawing
+    /// to a surface. Windows with a [recorder](WindowConfig::with_recorder) don't need one, so
+    /// no GPU context is created while every window records. The callbacks are moved into the
+    /// instance.
+    pub fn build(&mut self, event_loop: &EventLoop<()>) -> Result<Pine, PineError> {
+        let mut gpu = None;
         let windows = self
             .window_configs
             .iter()
-            .map(|config| config.build(&event_loop).expect("Failed to build window"))
-            .collect();
+            .map(|config| build_window(config, event_loop, &mut gpu))
+            .collect::<Result<_, _>>()?;
 
-        Pine::new(windows)
+        let mut pine = Pine::with_windows(gpu, windows);
+        pine.callbacks = std::mem::take(&mut self.callbacks);
+        pine.schedule = std::mem::take(&mut self.schedule);
+        pine.schedule.build()?;
+        pine.time = Time::new(self.fixed_rate, self.max_fixed_steps);
+        pine.shader_hot_reload = self.shader_hot_reload;
+        Ok(pine)
     }
 
     /// Shortcut to spin up Pine from config.
-    pub fn run(&mut self) {
-        let event_loop = EventLoop::new().expect("Failed to initialize event loop");
-        self.build(&event_loop).run(event_loop);
+    pub fn run(&mut self) -> Result<(), PineError> {
+        let event_loop = EventLoop::new().map_err(PineError::EventLoopError)?;
+        self.build(&event_loop)?.run(event_loop)
+    }
+}
+
+/// Opens the window described by the config.
+///
+/// The GPU context is created for the first window drawing to a surface, so that it can present
+/// to it. Recording windows never need one.
+fn build_window(
+    config: &WindowConfig,
+    elwt: &EventLoopWindowTarget<()>,
+    gpu: &mut Option<Arc<GpuContext>>,
+) -> Result<Window, PineError> {
+    let handle = config.build_handle(elwt)?;
+    if let Some(recorder) = config.recorder() {
+        return config.build_recording(handle, recorder);
+    }
+
+    let (gpu, surface) = match gpu {
+        Some(gpu) => {
+            let surface = gpu.create_surface(handle.clone())?;
+            (&*gpu, surface)
+        }
+        None => {
+            let instance = GpuContext::create_instance();
+            let surface = instance
+                .create_surface(handle.clone())
+                .map_err(PineError::CreateSurfaceError)?;
+            let context = pollster::block_on(GpuContext::new(instance, Some(&surface), false))?;
+            (&*gpu.insert(Arc::new(context)), surface)
+        }
+    };
+    config.build_with_surface(gpu, handle, surface)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::world::event::EventReader;
+
+    use glam::Vec2;
+    use winit::{
+        dpi::{PhysicalPosition, PhysicalSize},
+        event::{DeviceId, ElementState, MouseButton},
+    };
+
+    use std::{
+        fs,
+        time::{Duration, SystemTime},
+    };
+
+    #[test]
+    fn window_events_are_published() {
+        let mut pine = Pine::with_windows(None, vec![]);
+        // SAFETY: the IDs are only compared, never passed to the platform.
+        let (window, device) = unsafe { (WindowId::dummy(), DeviceId::dummy()) };
+
+        pine.publish_window_event(
+            window,
+            &WindowEvent::Resized(PhysicalSize::new(640, 480)),
+            2.0,
+        );
+        let cursor = WindowEvent::CursorMoved {
+            device_id: device,
+            position: PhysicalPosition::new(100.0, 50.0),
+        };
+        pine.publish_window_event(window, &cursor, 2.0);
+        let click = WindowEvent::MouseInput {
+            device_id: device,
+            state: ElementState::Pressed,
+            button: MouseButton::Left,
+        };
+        pine.publish_window_event(window, &click, 2.0);
+        pine.publish_window_event(window, &WindowEvent::RedrawRequested, 2.0);
+
+        let resized: Vec<_> = pine
+            .world
+            .events::<WindowResized>()
+            .unwrap()
+            .iter()
+            .cloned()
+            .collect();
+        assert_eq!(
+            resized,
+            [WindowResized {
+                id: window,
+                width: 640,
+                height: 480
+            }]
+        );
+        let input: Vec<_> = EventReader::new()
+            .read(&pine.world.events::<InputEvent>().unwrap())
+            .cloned()
+            .collect();
+        assert_eq!(
+            input,
+            [
+                InputEvent::CursorMoved {
+                    window,
+                    position: Vec2::new(50.0, 25.0)
+                },
+                InputEvent::MouseButton {
+                    window,
+                    button: MouseButton::Left,
+                    pressed: true
+                }
+            ]
+        );
+
+        // Lifecycle events have their channels from the start, so readers can be set up early.
+        assert!(pine.world.events::<AppLifecycleEvent>().is_some());
+        assert!(pine.world.events::<WindowLifecycleEvent>().is_some());
+    }
+
+    #[test]
+    fn shaders_are_only_reloaded_with_hot_reload_on() {
+        let Ok(gpu) = pollster::block_on(GpuContext::headless(false)) else {
+            eprintln!("Skipping, no adapter available");
+            return;
+        };
+        let path =
+            std::env::temp_dir().join(format!("pine-hot-reload-{}.wgsl", std::process::id()));
+        let shader =
+            "@vertex fn main() -> @builtin(position) vec4<f32> { return vec4<f32>(0.0); }\n";
+        fs::write(&path, shader).unwrap();
+        let id = gpu.load_shader(&path).unwrap();
+
+        let mut pine = Pine::with_windows(Some(Arc::new(gpu)), vec![]);
+        let generation = |pine: &Pine| pine.gpu.as_ref().unwrap().shaders().generation(id);
+
+        // Set the modification time explicitly, the file system may not tell the writes apart.
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+        pine.shader_hot_reload = false;
+        pine.reload_shaders();
+        assert_eq!(generation(&pine), 0);
+
+        pine.shader_hot_reload = true;
+        pine.reload_shaders();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(generation(&pine), 1);
     }
 }
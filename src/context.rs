@@ -0,0 +1,220 @@
+use winit::window::WindowId;
+
+use std::sync::Arc;
+
+use crate::{
+    input::Input,
+    rendering::{
+        camera::Camera2D, gpu::GpuContext, post::PostProcessing, scene_graph::SceneGraph, Renderer,
+    },
+    time::Time,
+    windowing::{Window, WindowConfig},
+    world::{event::Events, schedule::Schedule, storage::Component, ResourceRef, World},
+};
+
+/// Changes to the set of windows requested by user code, applied once the callback returns.
+pub(crate) enum WindowCommand {
+    Open(Box<WindowConfig>),
+    Close(WindowId),
+}
+
+/// What user callbacks get to work with: the windows, their renderers and scenes, the input state
+/// and frame timing.
+///
+/// Windows are indexed in the order they were opened. Closing a window shifts the index of the
+/// windows after it, so prefer looking up windows by key when windows come and go.
+pub struct Context<'pine> {
+    gpu: Option<&'pine Arc<GpuContext>>,
+    windows: &'pine mut Vec<Window>,
+    input: &'pine mut Input,
+    time: &'pine Time,
+    world: &'pine mut World,
+    schedule: &'pine Schedule,
+    commands: Vec<WindowCommand>,
+    exit_requested: bool,
+}
+
+impl<'pine> Context<'pine> {
+    pub(crate) fn new(
+        gpu: Option<&'pine Arc<GpuContext>>,
+        windows: &'pine mut Vec<Window>,
+        input: &'pine mut Input,
+        time: &'pine Time,
+        world: &'pine mut World,
+        schedule: &'pine Schedule,
+    ) -> Self {
+        Self {
+            gpu,
+            windows,
+            input,
+            time,
+            world,
+            schedule,
+            commands: vec![],
+            exit_requested: false,
+        }
+    }
+
+    /// Returns the GPU context shared by all windows, e.g. to load textures any window can draw.
+    ///
+    /// There is none while every window draws with a [`RecordingRenderer`].
+    ///
+    /// [`RecordingRenderer`]: crate::rendering::recording::RecordingRenderer
+    pub fn gpu(&self) -> Option<&Arc<GpuContext>> {
+        self.gpu
+    }
+
+    pub fn windows(&self) -> &[Window] {
+        self.windows
+    }
+
+    pub fn windows_mut(&mut self) -> &mut [Window] {
+        self.windows
+    }
+
+    pub fn window(&self, index: usize) -> Option<&Window> {
+        self.windows.get(index)
+    }
+
+    pub fn window_mut(&mut self, index: usize) -> Option<&mut Window> {
+        self.windows.get_mut(index)
+    }
+
+    /// Returns the index of the window with the given winit ID, if it's still open.
+    pub fn window_index(&self, id: WindowId) -> Option<usize> {
+        self.windows
+            .iter()
+            .position(|window| window.handle.id() == id)
+    }
+
+    /// Returns the window with the given key, if it's open.
+    pub fn window_by_key(&self, key: &str) -> Option<&Window> {
+        self.windows
+            .iter()
+            .find(|window| window.key.as_deref() == Some(key))
+    }
+
+    pub fn window_by_key_mut(&mut self, key: &str) -> Option<&mut Window> {
+        self.windows
+            .iter_mut()
+            .find(|window| window.key.as_deref() == Some(key))
+    }
+
+    /// Returns the index of the window with the given key, if it's open.
+    pub fn window_index_by_key(&self, key: &str) -> Option<usize> {
+        self.windows
+            .iter()
+            .position(|window| window.key.as_deref() == Some(key))
+    }
+
+    /// Opens a new window once the current callback returns.
+    ///
+    /// The window is announced with [`WindowLifecycleEvent::Opened`](crate::windowing::WindowLifecycleEvent::Opened).
+    /// Windows whose key is already taken aren't opened.
+    pub fn open_window(&mut self, config: WindowConfig) {
+        self.commands.push(WindowCommand::Open(Box::new(config)));
+    }
+
+    /// Closes the window with the given ID once the current callback returns.
+    ///
+    /// NB: the engine shuts down once the last window is closed.
+    pub fn close_window(&mut self, id: WindowId) {
+        self.commands.push(WindowCommand::Close(id));
+    }
+
+    /// Closes the window with the given key once the current callback returns.
+    ///
+    /// Returns whether such a window is open.
+    pub fn close_window_by_key(&mut self, key: &str) -> bool {
+        let Some(window) = self.window_by_key(key) else {
+            return false;
+        };
+        let id = window.handle.id();
+        self.close_window(id);
+        true
+    }
+
+    /// Returns the renderer of the window at the given index.
+    pub fn renderer_mut(&mut self, index: usize) -> Option<&mut dyn Renderer> {
+        Some(self.windows.get_mut(index)?.renderer.as_mut())
+    }
+
+    /// Returns the scene graph drawn in the window at the given index.
+    pub fn scene_graph_mut(&mut self, index: usize) -> Option<&mut SceneGraph> {
+        Some(self.renderer_mut(index)?.scene_graph_mut())
+    }
+
+    /// Returns the camera of the window at the given index.
+    pub fn camera_mut(&mut self, index: usize) -> Option<&mut Camera2D> {
+        Some(self.renderer_mut(index)?.camera_mut())
+    }
+
+    /// Returns the post-processing settings of the window at the given index, if post-processing
+    /// is enabled for it.
+    pub fn post_processing_mut(&mut self, index: usize) -> Option<&mut PostProcessing> {
+        self.renderer_mut(index)?.post_processing_mut()
+    }
+
+    /// Enables post-processing for the window at the given index if needed, returning its
+    /// settings.
+    ///
+    /// Returns None if there's no such window or its renderer failed to set up post-processing,
+    /// which is logged.
+    pub fn enable_post_processing(&mut self, index: usize) -> Option<&mut PostProcessing> {
+        self.renderer_mut(index)?
+            .enable_post_processing()
+            .map_err(|err| tracing::error!("Failed to enable post-processing: {}", err))
+            .ok()
+    }
+
+    /// Returns the world holding the entities of the game.
+    pub fn world(&self) -> &World {
+        self.world
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        self.world
+    }
+
+    /// Sends an event to the systems and callbacks reading its channel.
+    pub fn send_event<T: Component>(&mut self, event: T) {
+        self.world.send_event(event);
+    }
+
+    /// Returns the channel of the given event type, e.g. [`InputEvent`](crate::prelude::InputEvent)
+    /// to go through the input of the frame in order.
+    pub fn events<T: Component>(&self) -> Option<ResourceRef<'_, Events<T>>> {
+        self.world.events()
+    }
+
+    /// Returns the systems and the batches they run in, which can be printed for debugging.
+    pub fn schedule(&self) -> &Schedule {
+        self.schedule
+    }
+
+    pub fn input(&self) -> &Input {
+        self.input
+    }
+
+    /// Returns the input state for modification, e.g. to rebind actions.
+    pub fn input_mut(&mut self) -> &mut Input {
+        self.input
+    }
+
+    pub fn time(&self) -> &Time {
+        self.time
+    }
+
+    /// Shuts down the engine once the current callback returns.
+    pub fn exit(&mut self) {
+        self.exit_requested = true;
+    }
+
+    pub(crate) fn exit_requested(&self) -> bool {
+        self.exit_requested
+    }
+
+    pub(crate) fn take_commands(&mut self) -> Vec<WindowCommand> {
+        std::mem::take(&mut self.commands)
+    }
+}
@@ -1,4 +1,7 @@
+use std::{error::Error, fmt, path::PathBuf};
+
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 /// Defines possible errors in Pine.
 pub enum PineError {
     // Window and event loop
@@ -7,6 +10,231 @@ pub enum PineError {
 
     // Rendering
     CreateSurfaceError(wgpu::CreateSurfaceError),
+    SurfaceError(wgpu::SurfaceError),
+    /// The surface has no texture format the adapter can render to.
+    UnsupportedSurfaceError,
     RequestDeviceError(wgpu::RequestDeviceError),
     RequestAdapterError,
+    BufferAsyncError(wgpu::BufferAsyncError),
+    ReadbackError,
+    /// The frame data builder was missing the named field.
+    IncompleteFrameDataError(&'static str),
+
+    // Shaders
+    LoadShaderError {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// The shader failed to parse or validate. The message points at the offending source.
+    ShaderValidationError {
+        path: PathBuf,
+        message: String,
+    },
+    /// A preprocessor directive in the shader is invalid.
+    ShaderPreprocessError {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+    /// The shader includes itself, through the given chain of files.
+    ShaderIncludeCycleError(Vec<PathBuf>),
+    CreatePipelineError(wgpu::Error),
+
+    // Render graph
+    DuplicateRenderPassError(String),
+    DuplicateRenderTextureError(String),
+    /// The pass uses a texture the graph doesn't have or the pass didn't declare.
+    UndeclaredRenderTextureError {
+        pass: String,
+        texture: String,
+    },
+    /// The pass reads a texture no other pass writes.
+    UnwrittenRenderTextureError {
+        pass: String,
+        texture: String,
+    },
+    /// The passes depend on each other's output in a cycle.
+    RenderGraphCycleError(Vec<String>),
+
+    // Post-processing
+    /// The renderer can't post-process its frames.
+    UnsupportedPostProcessingError,
+    /// The color LUT image isn't a strip of `height` squares, each `height` pixels wide.
+    LutSizeError {
+        width: u32,
+        height: u32,
+    },
+
+    // World
+    EntityNotFoundError(crate::world::entity::Entity),
+    DuplicateSystemError(String),
+    /// The systems are ordered before and after each other in a cycle.
+    SystemOrderCycleError(Vec<String>),
+
+    // Scene graph
+    SceneNodeNotFoundError(crate::rendering::scene_graph::NodeId),
+    SceneCycleError {
+        node: crate::rendering::scene_graph::NodeId,
+        parent: crate::rendering::scene_graph::NodeId,
+    },
+    DuplicateNodeNameError(String),
+
+    // Snapshot testing
+    SnapshotSizeError {
+        actual: (u32, u32),
+        expected: (u32, u32),
+    },
+    SnapshotMismatchError {
+        name: String,
+        mismatched_pixels: usize,
+    },
+    MissingSnapshotError(std::path::PathBuf),
+
+    // Assets and IO
+    ImageError(image::ImageError),
+    BadIconError(winit::window::BadIcon),
+    IoError(std::io::Error),
+    ParseActionMapError(ron::error::SpannedError),
+    SerializeActionMapError(ron::Error),
+}
+
+impl fmt::Display for PineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PineError::EventLoopError(_) => write!(f, "event loop failed"),
+            PineError::OsError(_) => write!(f, "failed to create window"),
+
+            PineError::CreateSurfaceError(_) => write!(f, "failed to create surface"),
+            PineError::SurfaceError(_) => write!(f, "failed to acquire surface texture"),
+            PineError::UnsupportedSurfaceError => {
+                write!(f, "surface is not supported by the adapter")
+            }
+            PineError::RequestDeviceError(_) => write!(f, "failed to request GPU device"),
+            PineError::RequestAdapterError => write!(f, "no suitable GPU adapter found"),
+            PineError::BufferAsyncError(_) => write!(f, "failed to map buffer"),
+            PineError::ReadbackError => write!(f, "failed to read pixels back from the GPU"),
+            PineError::IncompleteFrameDataError(field) => {
+                write!(f, "frame data is missing its {}", field)
+            }
+
+            PineError::LoadShaderError { path, .. } => {
+                write!(f, "failed to load shader {}", path.display())
+            }
+            PineError::ShaderValidationError { path, .. } => {
+                write!(f, "shader {} is invalid", path.display())
+            }
+            PineError::ShaderPreprocessError {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", path.display(), line, message),
+            PineError::ShaderIncludeCycleError(chain) => {
+                let chain: Vec<_> = chain
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect();
+                write!(f, "shader include cycle: {}", chain.join(" -> "))
+            }
+            PineError::CreatePipelineError(_) => write!(f, "failed to create render pipeline"),
+
+            PineError::DuplicateRenderPassError(name) => {
+                write!(f, "a render pass is already named {:?}", name)
+            }
+            PineError::DuplicateRenderTextureError(name) => {
+                write!(f, "a render texture is already named {:?}", name)
+            }
+            PineError::UndeclaredRenderTextureError { pass, texture } => write!(
+                f,
+                "render pass {:?} uses undeclared texture {:?}",
+                pass, texture
+            ),
+            PineError::UnwrittenRenderTextureError { pass, texture } => write!(
+                f,
+                "render pass {:?} reads texture {:?}, which no other pass writes",
+                pass, texture
+            ),
+            PineError::RenderGraphCycleError(passes) => {
+                write!(
+                    f,
+                    "render passes depend on each other: {}",
+                    passes.join(", ")
+                )
+            }
+
+            PineError::UnsupportedPostProcessingError => {
+                write!(f, "the renderer doesn't support post-processing")
+            }
+            PineError::LutSizeError { width, height } => write!(
+                f,
+                "color LUT is {}x{} but must be a strip of square slices, e.g. 256x16",
+                width, height
+            ),
+
+            PineError::EntityNotFoundError(entity) => write!(f, "entity {} not found", entity),
+            PineError::DuplicateSystemError(name) => {
+                write!(f, "a system is already named {:?}", name)
+            }
+            PineError::SystemOrderCycleError(systems) => write!(
+                f,
+                "systems are ordered before and after each other: {}",
+                systems.join(", ")
+            ),
+
+            PineError::SceneNodeNotFoundError(node) => write!(f, "scene node {} not found", node),
+            PineError::SceneCycleError { node, parent } => write!(
+                f,
+                "cannot attach scene node {} to {}, as that would create a cycle",
+                node, parent
+            ),
+            PineError::DuplicateNodeNameError(name) => {
+                write!(f, "a scene node is already named {:?}", name)
+            }
+
+            PineError::SnapshotSizeError { actual, expected } => write!(
+                f,
+                "snapshot is {}x{} but {}x{} was expected",
+                actual.0, actual.1, expected.0, expected.1
+            ),
+            PineError::SnapshotMismatchError {
+                name,
+                mismatched_pixels,
+            } => write!(
+                f,
+                "snapshot {:?} differs in {} pixels",
+                name, mismatched_pixels
+            ),
+            PineError::MissingSnapshotError(path) => write!(
+                f,
+                "no snapshot stored at {:?}, set PINE_UPDATE_SNAPSHOTS to store it",
+                path
+            ),
+
+            PineError::ImageError(_) => write!(f, "failed to load image"),
+            PineError::BadIconError(_) => write!(f, "invalid window icon"),
+            PineError::IoError(_) => write!(f, "IO error"),
+            PineError::ParseActionMapError(_) => write!(f, "failed to parse action map"),
+            PineError::SerializeActionMapError(_) => write!(f, "failed to serialize action map"),
+        }
+    }
+}
+
+impl Error for PineError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PineError::EventLoopError(err) => Some(err),
+            PineError::OsError(err) => Some(err),
+            PineError::CreateSurfaceError(err) => Some(err),
+            PineError::SurfaceError(err) => Some(err),
+            PineError::RequestDeviceError(err) => Some(err),
+            PineError::BufferAsyncError(err) => Some(err),
+            PineError::LoadShaderError { source, .. } => Some(source),
+            PineError::CreatePipelineError(err) => Some(err),
+            PineError::ImageError(err) => Some(err),
+            PineError::BadIconError(err) => Some(err),
+            PineError::IoError(err) => Some(err),
+            PineError::ParseActionMapError(err) => Some(err),
+            PineError::SerializeActionMapError(err) => Some(err),
+            _ => None,
+        }
+    }
 }
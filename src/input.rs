@@ -0,0 +1,642 @@
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+use winit::{
+    event::{ElementState, MouseScrollDelta, WindowEvent},
+    keyboard::PhysicalKey,
+    window::WindowId,
+};
+
+use crate::error::PineError;
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    hash::Hash,
+    path::Path,
+};
+
+pub use winit::{
+    event::MouseButton,
+    keyboard::{Key, KeyCode, ModifiersState, NamedKey},
+};
+
+#[derive(Debug, Clone)]
+/// The state of a set of buttons, e.g. keys or mouse buttons.
+///
+/// Just pressed and just released buttons are only reported for the frame they changed in.
+pub struct ButtonInput<T> {
+    pressed: HashSet<T>,
+    just_pressed: HashSet<T>,
+    just_released: HashSet<T>,
+}
+
+impl<T> Default for ButtonInput<T> {
+    fn default() -> Self {
+        Self {
+            pressed: HashSet::new(),
+            just_pressed: HashSet::new(),
+            just_released: HashSet::new(),
+        }
+    }
+}
+
+impl<T: Clone + Eq + Hash> ButtonInput<T> {
+    /// Returns whether the button is held down.
+    pub fn pressed(&self, button: &T) -> bool {
+        self.pressed.contains(button)
+    }
+
+    /// Returns whether the button was pressed this frame.
+    pub fn just_pressed(&self, button: &T) -> bool {
+        self.just_pressed.contains(button)
+    }
+
+    /// Returns whether the button was released this frame.
+    pub fn just_released(&self, button: &T) -> bool {
+        self.just_released.contains(button)
+    }
+
+    /// Iterates the buttons held down.
+    pub fn iter_pressed(&self) -> impl Iterator<Item = &T> {
+        self.pressed.iter()
+    }
+
+    fn press(&mut self, button: T) {
+        if self.pressed.insert(button.clone()) {
+            self.just_pressed.insert(button);
+        }
+    }
+
+    fn release(&mut self, button: T) {
+        if self.pressed.remove(&button) {
+            self.just_released.insert(button);
+        }
+    }
+
+    /// Releases every button held down.
+    fn release_all(&mut self) {
+        self.just_released.extend(self.pressed.drain());
+    }
+
+    fn end_frame(&mut self) {
+        self.just_pressed.clear();
+        self.just_released.clear();
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// An input an action can be bound to.
+pub enum InputBinding {
+    /// A key by the character or named key it produces, e.g. `Key(Character("a"))` or
+    /// `Key(Named(Space))`. Follows the keyboard layout.
+    Key(Key),
+    /// A key by its position on the keyboard, e.g. `KeyCode(KeyW)`. Ignores the keyboard layout.
+    KeyCode(KeyCode),
+    /// A mouse button, e.g. `Mouse(Left)`.
+    Mouse(MouseButton),
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+/// Named actions bound to one or more inputs each.
+///
+/// Stored as RON, mapping action names to lists of [`InputBinding`]s:
+///
+/// ```ron
+/// {
+///     "jump": [Key(Named(Space)), Mouse(Left)],
+///     "forward": [KeyCode(KeyW), Key(Named(ArrowUp))],
+/// }
+/// ```
+pub struct ActionMap {
+    bindings: BTreeMap<String, Vec<InputBinding>>,
+}
+
+impl ActionMap {
+    /// Constructs an action map without any actions.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Binds an input to an action.
+    pub fn with_binding(mut self, action: &str, binding: InputBinding) -> Self {
+        self.bind(action, binding);
+        self
+    }
+
+    /// Parses an action map from RON.
+    pub fn from_ron(source: &str) -> Result<Self, PineError> {
+        ron::from_str(source).map_err(PineError::ParseActionMapError)
+    }
+
+    /// Loads an action map from a RON file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, PineError> {
+        let source = std::fs::read_to_string(path).map_err(PineError::IoError)?;
+        Self::from_ron(&source)
+    }
+
+    /// Writes the action map as RON.
+    pub fn to_ron(&self) -> Result<String, PineError> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(PineError::SerializeActionMapError)
+    }
+
+    /// Saves the action map to a RON file, e.g. after the player rebound some controls.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), PineError> {
+        std::fs::write(path, self.to_ron()?).map_err(PineError::IoError)
+    }
+
+    /// Binds an input to an action, unless it's already bound to it.
+    pub fn bind(&mut self, action: &str, binding: InputBinding) {
+        let bindings = self.bindings.entry(action.to_string()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    /// Unbinds an input from an action. Returns whether it was bound.
+    pub fn unbind(&mut self, action: &str, binding: &InputBinding) -> bool {
+        let Some(bindings) = self.bindings.get_mut(action) else {
+            return false;
+        };
+        let len = bindings.len();
+        bindings.retain(|b| b != binding);
+        bindings.len() != len
+    }
+
+    /// Removes all bindings of an action.
+    pub fn clear(&mut self, action: &str) {
+        self.bindings.remove(action);
+    }
+
+    /// Returns the inputs bound to an action.
+    pub fn bindings(&self, action: &str) -> &[InputBinding] {
+        self.bindings.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Iterates the names of all actions.
+    pub fn actions(&self) -> impl Iterator<Item = &str> {
+        self.bindings.keys().map(String::as_str)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Keyboard and mouse input, published by the engine as it arrives.
+///
+/// Complements the state kept in [`Input`] for when the order of events matters, e.g. for text
+/// fields or combos.
+pub enum InputEvent {
+    /// A key was pressed or released. Held keys repeat their press.
+    Key {
+        window: WindowId,
+        key: Key,
+        /// The physical key, if the platform could identify it.
+        key_code: Option<KeyCode>,
+        pressed: bool,
+        repeat: bool,
+    },
+    MouseButton {
+        window: WindowId,
+        button: MouseButton,
+        pressed: bool,
+    },
+    /// The cursor moved to the logical position within the window.
+    CursorMoved {
+        window: WindowId,
+        position: Vec2,
+    },
+    CursorLeft {
+        window: WindowId,
+    },
+    /// The mouse wheel or touchpad scrolled, either by lines or by logical pixels.
+    Scrolled {
+        window: WindowId,
+        lines: Vec2,
+        pixels: Vec2,
+    },
+}
+
+impl InputEvent {
+    /// Converts a window event, if it's input.
+    pub(crate) fn from_window_event(
+        window: WindowId,
+        event: &WindowEvent,
+        scale_factor: f64,
+    ) -> Option<Self> {
+        let event = match event {
+            WindowEvent::KeyboardInput { event, .. } => InputEvent::Key {
+                window,
+                key: event.logical_key.clone(),
+                key_code: match event.physical_key {
+                    PhysicalKey::Code(code) => Some(code),
+                    PhysicalKey::Unidentified(_) => None,
+                },
+                pressed: event.state == ElementState::Pressed,
+                repeat: event.repeat,
+            },
+            WindowEvent::MouseInput { state, button, .. } => InputEvent::MouseButton {
+                window,
+                button: *button,
+                pressed: *state == ElementState::Pressed,
+            },
+            WindowEvent::CursorMoved { position, .. } => {
+                let position = position.to_logical::<f32>(scale_factor);
+                InputEvent::CursorMoved {
+                    window,
+                    position: Vec2::new(position.x, position.y),
+                }
+            }
+            WindowEvent::CursorLeft { .. } => InputEvent::CursorLeft { window },
+            WindowEvent::MouseWheel { delta, .. } => match delta {
+                MouseScrollDelta::LineDelta(x, y) => InputEvent::Scrolled {
+                    window,
+                    lines: Vec2::new(*x, *y),
+                    pixels: Vec2::ZERO,
+                },
+                MouseScrollDelta::PixelDelta(position) => {
+                    let position = position.to_logical::<f32>(scale_factor);
+                    InputEvent::Scrolled {
+                        window,
+                        lines: Vec2::ZERO,
+                        pixels: Vec2::new(position.x, position.y),
+                    }
+                }
+            },
+            _ => return None,
+        };
+        Some(event)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// The state of the keyboard and mouse, kept up to date by the engine.
+///
+/// Per-frame state, like just pressed keys and scrolling, is visible to all callbacks of the frame
+/// it happened in. Fixed updates only see it if one runs that frame.
+pub struct Input {
+    keys: ButtonInput<Key>,
+    key_codes: ButtonInput<KeyCode>,
+    mouse_buttons: ButtonInput<MouseButton>,
+    /// The logical key each physical key produced when pressed, so it's released even if the
+    /// modifiers changed in the meantime.
+    held_keys: HashMap<KeyCode, Key>,
+    modifiers: ModifiersState,
+    cursor_positions: HashMap<WindowId, Vec2>,
+    hovered_window: Option<WindowId>,
+    scroll_lines: Vec2,
+    scroll_pixels: Vec2,
+    actions: ActionMap,
+}
+
+impl Input {
+    /// Returns the state of keys by the character or named key they produce.
+    pub fn keys(&self) -> &ButtonInput<Key> {
+        &self.keys
+    }
+
+    /// Returns the state of keys by their position on the keyboard.
+    pub fn key_codes(&self) -> &ButtonInput<KeyCode> {
+        &self.key_codes
+    }
+
+    pub fn mouse_buttons(&self) -> &ButtonInput<MouseButton> {
+        &self.mouse_buttons
+    }
+
+    /// Returns whether the given key is held down.
+    pub fn is_key_pressed(&self, key: &Key) -> bool {
+        self.keys.pressed(key)
+    }
+
+    /// Returns whether the given mouse button is held down.
+    pub fn is_mouse_button_pressed(&self, button: MouseButton) -> bool {
+        self.mouse_buttons.pressed(&button)
+    }
+
+    pub fn modifiers(&self) -> ModifiersState {
+        self.modifiers
+    }
+
+    /// Returns the position of the cursor in logical pixels from the top left corner of the window
+    /// it's over, or None if it isn't over any window.
+    pub fn cursor_position(&self) -> Option<Vec2> {
+        self.cursor_position_in(self.hovered_window?)
+    }
+
+    /// Returns the position of the cursor in the given window, or None if it isn't over it.
+    pub fn cursor_position_in(&self, window: WindowId) -> Option<Vec2> {
+        self.cursor_positions.get(&window).copied()
+    }
+
+    /// Returns the window the cursor is over, if any.
+    pub fn hovered_window(&self) -> Option<WindowId> {
+        self.hovered_window
+    }
+
+    /// Returns the distance scrolled this frame by devices that scroll in lines, like most mouse
+    /// wheels.
+    pub fn scroll_lines(&self) -> Vec2 {
+        self.scroll_lines
+    }
+
+    /// Returns the distance scrolled this frame in logical pixels by devices that scroll smoothly,
+    /// like touchpads.
+    pub fn scroll_pixels(&self) -> Vec2 {
+        self.scroll_pixels
+    }
+
+    pub fn actions(&self) -> &ActionMap {
+        &self.actions
+    }
+
+    /// Returns the action map for rebinding.
+    pub fn actions_mut(&mut self) -> &mut ActionMap {
+        &mut self.actions
+    }
+
+    pub fn set_actions(&mut self, actions: ActionMap) {
+        self.actions = actions;
+    }
+
+    /// Replaces the action map with one loaded from a RON file.
+    ///
+    /// Keeps the current bindings if the file can't be loaded.
+    pub fn load_actions(&mut self, path: impl AsRef<Path>) -> Result<(), PineError> {
+        self.actions = ActionMap::load(path)?;
+        Ok(())
+    }
+
+    /// Returns whether any input bound to the action is held down.
+    pub fn is_action_pressed(&self, action: &str) -> bool {
+        self.actions
+            .bindings(action)
+            .iter()
+            .any(|binding| self.binding_pressed(binding))
+    }
+
+    /// Returns whether an input bound to the action was pressed this frame.
+    pub fn is_action_just_pressed(&self, action: &str) -> bool {
+        self.actions
+            .bindings(action)
+            .iter()
+            .any(|binding| self.binding_just_pressed(binding))
+    }
+
+    /// Returns whether the action stopped being held down this frame.
+    pub fn is_action_just_released(&self, action: &str) -> bool {
+        let bindings = self.actions.bindings(action);
+        bindings
+            .iter()
+            .any(|binding| self.binding_just_released(binding))
+            && !bindings.iter().any(|binding| self.binding_pressed(binding))
+    }
+
+    fn binding_pressed(&self, binding: &InputBinding) -> bool {
+        match binding {
+            InputBinding::Key(key) => self.keys.pressed(key),
+            InputBinding::KeyCode(code) => self.key_codes.pressed(code),
+            InputBinding::Mouse(button) => self.mouse_buttons.pressed(button),
+        }
+    }
+
+    fn binding_just_pressed(&self, binding: &InputBinding) -> bool {
+        match binding {
+            InputBinding::Key(key) => self.keys.just_pressed(key),
+            InputBinding::KeyCode(code) => self.key_codes.just_pressed(code),
+            InputBinding::Mouse(button) => self.mouse_buttons.just_pressed(button),
+        }
+    }
+
+    fn binding_just_released(&self, binding: &InputBinding) -> bool {
+        match binding {
+            InputBinding::Key(key) => self.keys.just_released(key),
+            InputBinding::KeyCode(code) => self.key_codes.just_released(code),
+            InputBinding::Mouse(button) => self.mouse_buttons.just_released(button),
+        }
+    }
+
+    /// Updates the state from an event of the given window.
+    pub(crate) fn handle_event(
+        &mut self,
+        window: WindowId,
+        event: &WindowEvent,
+        scale_factor: f64,
+    ) {
+        match event {
+            WindowEvent::KeyboardInput { event, .. } => {
+                let code = match event.physical_key {
+                    PhysicalKey::Code(code) => Some(code),
+                    PhysicalKey::Unidentified(_) => None,
+                };
+
+                match event.state {
+                    ElementState::Pressed => {
+                        if let Some(code) = code {
+                            self.key_codes.press(code);
+                            self.held_keys.insert(code, event.logical_key.clone());
+                        }
+                        self.keys.press(event.logical_key.clone());
+                    }
+                    ElementState::Released => {
+                        let key = code
+                            .and_then(|code| {
+                                self.key_codes.release(code);
+                                self.held_keys.remove(&code)
+                            })
+                            .unwrap_or_else(|| event.logical_key.clone());
+                        self.keys.release(key);
+                    }
+                }
+            }
+            WindowEvent::ModifiersChanged(modifiers) => self.modifiers = modifiers.state(),
+            WindowEvent::MouseInput { state, button, .. } => match state {
+                ElementState::Pressed => self.mouse_buttons.press(*button),
+                ElementState::Released => self.mouse_buttons.release(*button),
+            },
+            WindowEvent::MouseWheel { delta, .. } => match delta {
+                MouseScrollDelta::LineDelta(x, y) => self.scroll_lines += Vec2::new(*x, *y),
+                MouseScrollDelta::PixelDelta(position) => {
+                    let position = position.to_logical::<f32>(scale_factor);
+                    self.scroll_pixels += Vec2::new(position.x, position.y);
+                }
+            },
+            WindowEvent::CursorMoved { position, .. } => {
+                let position = position.to_logical::<f32>(scale_factor);
+                self.cursor_positions
+                    .insert(window, Vec2::new(position.x, position.y));
+                self.hovered_window = Some(window);
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.cursor_positions.remove(&window);
+                if self.hovered_window == Some(window) {
+                    self.hovered_window = None;
+                }
+            }
+            // Releases aren't reported to unfocused windows, so release everything held down.
+            WindowEvent::Focused(false) => {
+                self.keys.release_all();
+                self.key_codes.release_all();
+                self.mouse_buttons.release_all();
+                self.held_keys.clear();
+                self.modifiers = ModifiersState::empty();
+            }
+            WindowEvent::Destroyed => {
+                self.cursor_positions.remove(&window);
+            }
+            _ => {}
+        }
+    }
+
+    /// Forgets the per-frame state once all callbacks of the frame ran.
+    pub(crate) fn end_frame(&mut self) {
+        self.keys.end_frame();
+        self.key_codes.end_frame();
+        self.mouse_buttons.end_frame();
+        self.scroll_lines = Vec2::ZERO;
+        self.scroll_pixels = Vec2::ZERO;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presses_are_only_just_pressed_for_one_frame() {
+        let mut buttons = ButtonInput::default();
+        buttons.press(MouseButton::Left);
+        assert!(buttons.pressed(&MouseButton::Left));
+        assert!(buttons.just_pressed(&MouseButton::Left));
+
+        buttons.end_frame();
+        assert!(buttons.pressed(&MouseButton::Left));
+        assert!(!buttons.just_pressed(&MouseButton::Left));
+
+        // Repeated presses of a held button aren't new presses.
+        buttons.press(MouseButton::Left);
+        assert!(!buttons.just_pressed(&MouseButton::Left));
+    }
+
+    #[test]
+    fn releases_are_only_just_released_for_one_frame() {
+        let mut buttons = ButtonInput::default();
+        buttons.press(MouseButton::Left);
+        buttons.end_frame();
+
+        buttons.release(MouseButton::Left);
+        assert!(!buttons.pressed(&MouseButton::Left));
+        assert!(buttons.just_released(&MouseButton::Left));
+
+        buttons.end_frame();
+        assert!(!buttons.just_released(&MouseButton::Left));
+
+        // Releasing a button that isn't held does nothing.
+        buttons.release(MouseButton::Right);
+        assert!(!buttons.just_released(&MouseButton::Right));
+    }
+
+    #[test]
+    fn tapping_within_a_frame_is_both_just_pressed_and_just_released() {
+        let mut buttons = ButtonInput::default();
+        buttons.press(KeyCode::Space);
+        buttons.release(KeyCode::Space);
+
+        assert!(!buttons.pressed(&KeyCode::Space));
+        assert!(buttons.just_pressed(&KeyCode::Space));
+        assert!(buttons.just_released(&KeyCode::Space));
+
+        buttons.end_frame();
+        assert!(!buttons.just_pressed(&KeyCode::Space));
+        assert!(!buttons.just_released(&KeyCode::Space));
+    }
+
+    #[test]
+    fn releasing_all_releases_every_held_button() {
+        let mut buttons = ButtonInput::default();
+        buttons.press(KeyCode::KeyA);
+        buttons.press(KeyCode::KeyB);
+        buttons.release_all();
+
+        assert_eq!(buttons.iter_pressed().count(), 0);
+        assert!(buttons.just_released(&KeyCode::KeyA));
+        assert!(buttons.just_released(&KeyCode::KeyB));
+    }
+
+    #[test]
+    fn actions_follow_their_bindings_across_frames() {
+        let mut input = Input::default();
+        input.set_actions(
+            ActionMap::new()
+                .with_binding("jump", InputBinding::KeyCode(KeyCode::Space))
+                .with_binding("jump", InputBinding::Mouse(MouseButton::Left)),
+        );
+
+        input.key_codes.press(KeyCode::Space);
+        assert!(input.is_action_pressed("jump"));
+        assert!(input.is_action_just_pressed("jump"));
+
+        input.end_frame();
+        input.mouse_buttons.press(MouseButton::Left);
+        input.key_codes.release(KeyCode::Space);
+        // Still held through the other binding.
+        assert!(input.is_action_pressed("jump"));
+        assert!(!input.is_action_just_released("jump"));
+
+        input.end_frame();
+        input.mouse_buttons.release(MouseButton::Left);
+        assert!(!input.is_action_pressed("jump"));
+        assert!(input.is_action_just_released("jump"));
+        assert!(!input.is_action_pressed("missing"));
+    }
+
+    #[test]
+    fn action_maps_round_trip_through_ron() {
+        let actions = ActionMap::new()
+            .with_binding("jump", InputBinding::Key(Key::Named(NamedKey::Space)))
+            .with_binding("jump", InputBinding::Mouse(MouseButton::Left))
+            .with_binding("forward", InputBinding::KeyCode(KeyCode::KeyW))
+            .with_binding("chat", InputBinding::Key(Key::Character("t".into())));
+
+        let parsed = ActionMap::from_ron(&actions.to_ron().unwrap()).unwrap();
+
+        assert_eq!(
+            parsed.actions().collect::<Vec<_>>(),
+            actions.actions().collect::<Vec<_>>()
+        );
+        for action in actions.actions() {
+            assert_eq!(parsed.bindings(action), actions.bindings(action));
+        }
+    }
+
+    #[test]
+    fn action_maps_parse_the_documented_format() {
+        let actions = ActionMap::from_ron(
+            r#"{
+                "jump": [Key(Named(Space)), Mouse(Left)],
+                "forward": [KeyCode(KeyW), Key(Named(ArrowUp))],
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(actions.actions().collect::<Vec<_>>(), ["forward", "jump"]);
+        assert_eq!(
+            actions.bindings("jump"),
+            [
+                InputBinding::Key(Key::Named(NamedKey::Space)),
+                InputBinding::Mouse(MouseButton::Left),
+            ]
+        );
+        assert!(ActionMap::from_ron("{ \"jump\": [Gamepad(South)] }").is_err());
+    }
+
+    #[test]
+    fn binding_twice_keeps_one_binding() {
+        let mut actions = ActionMap::new();
+        actions.bind("jump", InputBinding::KeyCode(KeyCode::Space));
+        actions.bind("jump", InputBinding::KeyCode(KeyCode::Space));
+        assert_eq!(actions.bindings("jump").len(), 1);
+
+        assert!(actions.unbind("jump", &InputBinding::KeyCode(KeyCode::Space)));
+        assert!(!actions.unbind("jump", &InputBinding::KeyCode(KeyCode::Space)));
+        assert!(actions.bindings("jump").is_empty());
+    }
+}
@@ -1,8 +1,44 @@
 mod app;
+mod context;
 mod error;
-mod rendering;
+mod input;
+pub mod rendering;
+mod time;
 mod windowing;
+pub mod world;
 
 pub mod prelude {
-    pub use crate::{app::Pine, rendering::color::Color, windowing::WindowConfig};
+    pub use crate::{
+        app::{AppLifecycleEvent, Pine},
+        context::Context,
+        error::PineError,
+        input::{ActionMap, Input, InputBinding, InputEvent, Key, KeyCode, MouseButton, NamedKey},
+        rendering::{
+            color::Color,
+            gpu::GpuContext,
+            offscreen::HeadlessConfig,
+            post::{
+                Bloom, ChromaticAberration, ColorGrading, ColorLut, Crt, PostProcessing, Vignette,
+            },
+            recording::{FrameRecorder, RecordedFrame, RecordingRenderer},
+            surface::{PresentMode, SurfaceConfig},
+            Renderer, Renderer2D,
+        },
+        time::{FixedTime, Time},
+        windowing::{FullscreenMode, Window, WindowConfig, WindowLifecycleEvent, WindowResized},
+        world::{
+            entity::Entity,
+            event::{EventReader, Events},
+            query::{Res, ResMut},
+            scene::SceneLink,
+            schedule::{Schedule, Stage},
+            system::System,
+            World,
+        },
+    };
+    pub use glam::Vec2;
+    pub use winit::{
+        event::WindowEvent,
+        window::{CursorGrabMode, WindowId},
+    };
 }
@@ -0,0 +1,345 @@
+pub mod recording;
+pub mod wgpu_backend;
+
+use super::{
+    color::Color,
+    shaders::ShaderId,
+    texture::{TextureHandle, TextureOptions},
+};
+use crate::error::PineError;
+
+use std::{borrow::Cow, fmt::Debug, ops::Range};
+
+pub use self::{recording::RecordingBackend, wgpu_backend::WgpuBackend};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// A handle to a buffer created by a [`Backend`].
+pub struct BufferId(pub(crate) u64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// A handle to a pipeline created by a [`Backend`].
+pub struct PipelineId(pub(crate) u64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// What a buffer is bound as.
+pub enum BufferUsage {
+    /// Per-vertex or per-instance data.
+    Vertex,
+    /// 16 bit indices into the vertex buffers.
+    Index,
+    /// Uniforms bound to a bind group of their own.
+    Uniform,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Describes a buffer to create. Buffers start out zeroed.
+pub struct BufferDesc {
+    pub label: Cow<'static, str>,
+    /// The size in bytes.
+    pub size: u64,
+    pub usage: BufferUsage,
+}
+
+impl BufferDesc {
+    pub fn new(label: impl Into<Cow<'static, str>>, size: u64, usage: BufferUsage) -> Self {
+        Self {
+            label: label.into(),
+            size,
+            usage,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// The format of a vertex attribute.
+pub enum VertexFormat {
+    Float32x2,
+    Float32x4,
+}
+
+impl VertexFormat {
+    /// Returns the size of an attribute in bytes.
+    pub fn size(self) -> u64 {
+        match self {
+            VertexFormat::Float32x2 => 8,
+            VertexFormat::Float32x4 => 16,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Whether a vertex buffer advances per vertex or per instance.
+pub enum StepMode {
+    Vertex,
+    Instance,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The layout of a vertex buffer a pipeline reads.
+pub struct VertexLayout {
+    /// The number of bytes between consecutive elements.
+    pub stride: u64,
+    pub step_mode: StepMode,
+    /// The shader location and format of each attribute, tightly packed in order.
+    pub attributes: Vec<(u32, VertexFormat)>,
+}
+
+impl VertexLayout {
+    /// Constructs a layout of tightly packed attributes.
+    pub fn new(step_mode: StepMode, attributes: &[(u32, VertexFormat)]) -> Self {
+        Self {
+            stride: attributes.iter().map(|(_, format)| format.size()).sum(),
+            step_mode,
+            attributes: attributes.to_vec(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// What a bind group of a pipeline holds.
+pub enum BindingKind {
+    /// A uniform buffer at binding 0.
+    Uniform,
+    /// A texture at binding 0 and its sampler at binding 1.
+    Texture,
+    /// A 3D texture at binding 0 and its sampler at binding 1.
+    Texture3d,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+/// The format of a texture passes draw into.
+pub enum TextureFormat {
+    /// The format of the backend's output, e.g. that of the window's surface.
+    #[default]
+    Output,
+    /// 8 bit sRGB color channels.
+    Rgba8Srgb,
+    /// 16 bit float channels, which hold colors brighter than white.
+    Rgba16Float,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+/// How a pipeline combines the colors it draws with those already in the target.
+pub enum BlendMode {
+    /// Overwrites the target.
+    Replace,
+    /// Blends by the alpha of the drawn color.
+    #[default]
+    Alpha,
+    /// Adds the drawn color onto the target, replacing its alpha.
+    Additive,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// What a pass draws into.
+pub enum PassTarget {
+    /// The backend's output, e.g. the window.
+    Output,
+    /// A texture created with [`Backend::create_render_texture`].
+    Texture(TextureHandle),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Where the code of a pipeline comes from.
+pub enum ShaderSource {
+    /// WGSL source, compiled when the pipeline is created.
+    Wgsl(Cow<'static, str>),
+    /// A shader loaded through the [`GpuContext`](super::gpu::GpuContext), in its current version.
+    Shader(ShaderId),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Describes a pipeline drawing triangle lists.
+///
+/// By default, the shader's entry points are `vs_main` and `fs_main`, and the pipeline alpha
+/// blends into targets of the backend's output format.
+pub struct PipelineDesc {
+    pub label: Cow<'static, str>,
+    pub shader: ShaderSource,
+    pub vertex_entry: Cow<'static, str>,
+    pub fragment_entry: Cow<'static, str>,
+    pub vertex_buffers: Vec<VertexLayout>,
+    /// The kind of each bind group, in group order.
+    pub bind_groups: Vec<BindingKind>,
+    /// The format of the targets the pipeline draws into.
+    pub format: TextureFormat,
+    pub blend: BlendMode,
+}
+
+impl PipelineDesc {
+    pub fn new(label: impl Into<Cow<'static, str>>, shader: ShaderSource) -> Self {
+        Self {
+            label: label.into(),
+            shader,
+            vertex_entry: "vs_main".into(),
+            fragment_entry: "fs_main".into(),
+            vertex_buffers: vec![],
+            bind_groups: vec![],
+            format: TextureFormat::Output,
+            blend: BlendMode::Alpha,
+        }
+    }
+
+    /// Sets the names of the vertex and fragment entry points of the shader.
+    pub fn with_entry_points(
+        mut self,
+        vertex: impl Into<Cow<'static, str>>,
+        fragment: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        self.vertex_entry = vertex.into();
+        self.fragment_entry = fragment.into();
+        self
+    }
+
+    /// Adds a vertex buffer, read from the next slot.
+    pub fn with_vertex_buffer(mut self, layout: VertexLayout) -> Self {
+        self.vertex_buffers.push(layout);
+        self
+    }
+
+    /// Adds a bind group, bound at the next group index.
+    pub fn with_bind_group(mut self, kind: BindingKind) -> Self {
+        self.bind_groups.push(kind);
+        self
+    }
+
+    /// Sets the format of the targets the pipeline draws into.
+    pub fn with_format(mut self, format: TextureFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn with_blend(mut self, blend: BlendMode) -> Self {
+        self.blend = blend;
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A single step of drawing, recorded into a [`CommandList`].
+pub enum Command {
+    /// Starts drawing into the target, clearing it first if a color is given.
+    BeginPass {
+        label: Cow<'static, str>,
+        target: PassTarget,
+        clear: Option<Color>,
+    },
+    SetPipeline(PipelineId),
+    /// Binds a uniform buffer to a group of the pipeline.
+    SetUniform {
+        group: u32,
+        buffer: BufferId,
+    },
+    /// Binds a texture to a group of the pipeline.
+    SetTexture {
+        group: u32,
+        texture: TextureHandle,
+    },
+    SetVertexBuffer {
+        slot: u32,
+        buffer: BufferId,
+    },
+    SetIndexBuffer(BufferId),
+    Draw {
+        vertices: Range<u32>,
+        instances: Range<u32>,
+    },
+    DrawIndexed {
+        indices: Range<u32>,
+        instances: Range<u32>,
+    },
+    EndPass,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// Commands recorded for a backend to execute in order.
+///
+/// Draws must happen between [`Command::BeginPass`] and [`Command::EndPass`], with a pipeline
+/// set.
+pub struct CommandList {
+    commands: Vec<Command>,
+}
+
+impl CommandList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, command: Command) {
+        self.commands.push(command);
+    }
+
+    pub fn commands(&self) -> &[Command] {
+        &self.commands
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Returns the number of draw calls in the list.
+    pub fn draw_calls(&self) -> usize {
+        self.commands
+            .iter()
+            .filter(|command| matches!(command, Command::Draw { .. } | Command::DrawIndexed { .. }))
+            .count()
+    }
+}
+
+/// Creates GPU resources and executes commands drawing with them.
+///
+/// Decouples rendering from the graphics API underneath, from sprite batches to the passes of
+/// the render graph and post-processing. [`WgpuBackend`] draws with wgpu into a window or an
+/// offscreen texture, while [`RecordingBackend`] only keeps everything in memory, e.g. for tests
+/// without a GPU.
+///
+/// Resources are created through a shared reference, so renderers can create them while
+/// rendering. Textures of every kind are identified by the same [`TextureHandle`]s sprites use.
+pub trait Backend: Debug {
+    fn create_buffer(&self, desc: &BufferDesc) -> BufferId;
+
+    /// Writes the data into the buffer at the given offset in bytes.
+    fn write_buffer(&self, buffer: BufferId, offset: u64, data: &[u8]);
+
+    fn destroy_buffer(&self, buffer: BufferId);
+
+    /// Uploads an image as a texture that can be bound with [`Command::SetTexture`].
+    fn create_texture(&self, image: &image::RgbaImage, options: TextureOptions) -> TextureHandle;
+
+    /// Creates a texture passes can draw into with [`PassTarget::Texture`] and later passes can
+    /// sample with linear filtering. Its contents are undefined until drawn.
+    fn create_render_texture(
+        &self,
+        label: &str,
+        size: (u32, u32),
+        format: TextureFormat,
+    ) -> TextureHandle;
+
+    /// Uploads RGBA texels into a 3D texture bound with [`BindingKind::Texture3d`], e.g. a color
+    /// lookup table. The data holds the slices one after another, each row by row.
+    fn create_texture_3d(&self, size: (u32, u32, u32), data: &[u8]) -> TextureHandle;
+
+    /// Returns whether textures of the format can be drawn into with blending and sampled with
+    /// filtering.
+    fn supports_format(&self, format: TextureFormat) -> bool;
+
+    /// Returns the size of a texture as (width, height), if it exists.
+    fn texture_size(&self, texture: TextureHandle) -> Option<(u32, u32)>;
+
+    /// Frees a texture. Commands submitted afterwards must not use it anymore.
+    ///
+    /// The white texture is never freed.
+    fn destroy_texture(&self, texture: TextureHandle);
+
+    /// Creates a pipeline. Fails if the shader doesn't match the description.
+    fn create_pipeline(&self, desc: &PipelineDesc) -> Result<PipelineId, PineError>;
+
+    fn destroy_pipeline(&self, pipeline: PipelineId);
+
+    /// Executes the commands, drawing into the backend's output and render textures.
+    fn submit(&self, commands: &CommandList) -> Result<(), PineError>;
+}
@@ -0,0 +1,237 @@
+use super::{Backend, BufferDesc, BufferId, CommandList, PipelineDesc, PipelineId, TextureFormat};
+use crate::{
+    error::PineError,
+    rendering::texture::{TextureHandle, TextureOptions},
+};
+
+use std::{
+    cell::{Cell, Ref, RefCell},
+    collections::BTreeMap,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A buffer created through a [`RecordingBackend`], along with what was written into it.
+pub struct RecordedBuffer {
+    pub desc: BufferDesc,
+    pub contents: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// How a texture recorded by a [`RecordingBackend`] was created.
+pub enum RecordedTextureKind {
+    /// Uploaded from an image with [`Backend::create_texture`].
+    Image(TextureOptions),
+    /// Created with [`Backend::create_render_texture`] for passes to draw into.
+    Render(TextureFormat),
+    /// Uploaded with [`Backend::create_texture_3d`], with the given number of slices.
+    Volume(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A texture created through a [`RecordingBackend`]. The texels aren't kept.
+pub struct RecordedTexture {
+    pub size: (u32, u32),
+    pub kind: RecordedTextureKind,
+}
+
+#[derive(Debug)]
+/// A [`Backend`] that keeps resources and submitted commands in memory instead of drawing.
+///
+/// Needs no GPU, so renderers can be tested anywhere by inspecting what they submitted.
+///
+/// # Example
+///
+/// ```
+/// # use pine::rendering::backend::{Backend, Command, CommandList, PassTarget, RecordingBackend};
+/// # use pine::prelude::Color;
+/// let backend = RecordingBackend::new();
+/// let mut commands = CommandList::new();
+/// commands.push(Command::BeginPass {
+///     label: "Clear".into(),
+///     target: PassTarget::Output,
+///     clear: Some(Color::BLUE),
+/// });
+/// commands.push(Command::EndPass);
+/// backend.submit(&commands)?;
+///
+/// assert_eq!(backend.submissions().len(), 1);
+/// # Ok::<(), pine::prelude::PineError>(())
+/// ```
+pub struct RecordingBackend {
+    buffers: RefCell<BTreeMap<BufferId, RecordedBuffer>>,
+    /// Indexed by texture handle, starting with the white texture. Destroyed textures leave a
+    /// gap.
+    textures: RefCell<Vec<Option<RecordedTexture>>>,
+    pipelines: RefCell<BTreeMap<PipelineId, PipelineDesc>>,
+    submissions: RefCell<Vec<CommandList>>,
+    next_id: Cell<u64>,
+}
+
+impl Default for RecordingBackend {
+    fn default() -> Self {
+        let white = RecordedTexture {
+            size: (1, 1),
+            kind: RecordedTextureKind::Image(TextureOptions::pixel_art()),
+        };
+        Self {
+            buffers: RefCell::new(BTreeMap::new()),
+            textures: RefCell::new(vec![Some(white)]),
+            pipelines: RefCell::new(BTreeMap::new()),
+            submissions: RefCell::new(vec![]),
+            next_id: Cell::new(0),
+        }
+    }
+}
+
+impl RecordingBackend {
+    /// Constructs a backend holding only the white texture, like every other backend.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the buffer, unless it was destroyed.
+    pub fn buffer(&self, buffer: BufferId) -> Option<RecordedBuffer> {
+        self.buffers.borrow().get(&buffer).cloned()
+    }
+
+    /// Returns the number of buffers alive.
+    pub fn buffer_count(&self) -> usize {
+        self.buffers.borrow().len()
+    }
+
+    /// Returns the texture, unless it was destroyed.
+    pub fn texture(&self, texture: TextureHandle) -> Option<RecordedTexture> {
+        self.textures.borrow().get(texture.0).copied().flatten()
+    }
+
+    /// Returns the number of textures alive, including the white texture.
+    pub fn texture_count(&self) -> usize {
+        self.textures.borrow().iter().flatten().count()
+    }
+
+    /// Returns the description the pipeline was created with, unless it was destroyed.
+    pub fn pipeline(&self, pipeline: PipelineId) -> Option<PipelineDesc> {
+        self.pipelines.borrow().get(&pipeline).cloned()
+    }
+
+    /// Returns the number of pipelines alive.
+    pub fn pipeline_count(&self) -> usize {
+        self.pipelines.borrow().len()
+    }
+
+    /// Returns the command lists submitted so far, oldest first.
+    pub fn submissions(&self) -> Ref<'_, [CommandList]> {
+        Ref::map(self.submissions.borrow(), Vec::as_slice)
+    }
+
+    /// Returns the command lists submitted so far and forgets them.
+    pub fn take_submissions(&self) -> Vec<CommandList> {
+        self.submissions.take()
+    }
+
+    fn push_texture(&self, texture: RecordedTexture) -> TextureHandle {
+        let mut textures = self.textures.borrow_mut();
+        textures.push(Some(texture));
+        TextureHandle(textures.len() - 1)
+    }
+
+    fn next_id(&self) -> u64 {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        id
+    }
+}
+
+impl Backend for RecordingBackend {
+    fn create_buffer(&self, desc: &BufferDesc) -> BufferId {
+        let id = BufferId(self.next_id());
+        let buffer = RecordedBuffer {
+            desc: desc.clone(),
+            contents: vec![0; desc.size as usize],
+        };
+        self.buffers.borrow_mut().insert(id, buffer);
+        id
+    }
+
+    fn write_buffer(&self, buffer: BufferId, offset: u64, data: &[u8]) {
+        let mut buffers = self.buffers.borrow_mut();
+        let Some(recorded) = buffers.get_mut(&buffer) else {
+            tracing::warn!("Not writing to unknown buffer {:?}", buffer);
+            return;
+        };
+
+        let start = offset as usize;
+        match recorded.contents.get_mut(start..start + data.len()) {
+            Some(contents) => contents.copy_from_slice(data),
+            None => tracing::warn!(
+                "Not writing {} bytes at {} past the end of buffer {:?}",
+                data.len(),
+                offset,
+                buffer
+            ),
+        }
+    }
+
+    fn destroy_buffer(&self, buffer: BufferId) {
+        self.buffers.borrow_mut().remove(&buffer);
+    }
+
+    fn create_texture(&self, image: &image::RgbaImage, options: TextureOptions) -> TextureHandle {
+        self.push_texture(RecordedTexture {
+            size: image.dimensions(),
+            kind: RecordedTextureKind::Image(options),
+        })
+    }
+
+    fn create_render_texture(
+        &self,
+        _label: &str,
+        size: (u32, u32),
+        format: TextureFormat,
+    ) -> TextureHandle {
+        self.push_texture(RecordedTexture {
+            size,
+            kind: RecordedTextureKind::Render(format),
+        })
+    }
+
+    fn create_texture_3d(&self, size: (u32, u32, u32), _data: &[u8]) -> TextureHandle {
+        self.push_texture(RecordedTexture {
+            size: (size.0, size.1),
+            kind: RecordedTextureKind::Volume(size.2),
+        })
+    }
+
+    /// Claims to support every format, as nothing is drawn.
+    fn supports_format(&self, _format: TextureFormat) -> bool {
+        true
+    }
+
+    fn texture_size(&self, texture: TextureHandle) -> Option<(u32, u32)> {
+        self.texture(texture).map(|texture| texture.size)
+    }
+
+    fn destroy_texture(&self, texture: TextureHandle) {
+        if texture == TextureHandle::WHITE {
+            return;
+        }
+        if let Some(slot) = self.textures.borrow_mut().get_mut(texture.0) {
+            *slot = None;
+        }
+    }
+
+    fn create_pipeline(&self, desc: &PipelineDesc) -> Result<PipelineId, PineError> {
+        let id = PipelineId(self.next_id());
+        self.pipelines.borrow_mut().insert(id, desc.clone());
+        Ok(id)
+    }
+
+    fn destroy_pipeline(&self, pipeline: PipelineId) {
+        self.pipelines.borrow_mut().remove(&pipeline);
+    }
+
+    fn submit(&self, commands: &CommandList) -> Result<(), PineError> {
+        self.submissions.borrow_mut().push(commands.clone());
+        Ok(())
+    }
+}
@@ -0,0 +1,665 @@
+use super::{
+    Backend, BindingKind, BlendMode, BufferDesc, BufferId, BufferUsage, Command, CommandList,
+    PassTarget, PipelineDesc, PipelineId, ShaderSource, StepMode, TextureFormat, VertexFormat,
+};
+use crate::{
+    error::PineError,
+    rendering::{
+        gpu::GpuContext,
+        offscreen::{OffscreenTarget, OFFSCREEN_FORMAT},
+        surface::SurfaceConfig,
+        texture::{TextureHandle, TextureOptions},
+        texture_cache::TextureCache,
+    },
+};
+
+use std::{
+    borrow::Cow,
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    sync::Arc,
+};
+
+#[derive(Debug)]
+/// A buffer along with the bind group it's bound with, if it holds uniforms.
+struct GpuBuffer {
+    buffer: wgpu::Buffer,
+    bind_group: Option<wgpu::BindGroup>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Something a pass binds for its draws.
+enum Binding {
+    Pipeline,
+    Group(u32),
+    VertexBuffer(u32),
+    IndexBuffer,
+}
+
+#[derive(Debug)]
+/// Where a [`WgpuBackend`] puts its frames.
+enum Output {
+    /// The surface of a window, which holds on to the window.
+    Window {
+        surface: wgpu::Surface<'static>,
+        config: wgpu::SurfaceConfiguration,
+    },
+    /// A texture that is copied into a buffer after every submission, to be read back.
+    Offscreen(Box<OffscreenTarget>),
+}
+
+#[derive(Debug)]
+/// A [`Backend`] drawing with wgpu through a [`GpuContext`].
+///
+/// Draws its output into the surface of a window or into an offscreen texture. Textures live in
+/// the texture cache of the context, shared with every other renderer. Render textures are owned
+/// by the backend and freed along with it.
+pub struct WgpuBackend {
+    gpu: Arc<GpuContext>,
+    output: Output,
+    uniform_layout: wgpu::BindGroupLayout,
+    buffers: RefCell<HashMap<BufferId, GpuBuffer>>,
+    pipelines: RefCell<HashMap<PipelineId, wgpu::RenderPipeline>>,
+    render_textures: RefCell<HashSet<TextureHandle>>,
+    next_id: Cell<u64>,
+}
+
+impl WgpuBackend {
+    /// Constructs a backend presenting to the given surface of a window.
+    ///
+    /// Picks an sRGB format if the surface supports one, and falls back to supported modes where
+    /// the options can't be met.
+    pub fn with_surface(
+        gpu: Arc<GpuContext>,
+        surface: wgpu::Surface<'static>,
+        size: (u32, u32),
+        options: &SurfaceConfig,
+    ) -> Result<Self, PineError> {
+        let surface_caps = surface.get_capabilities(gpu.adapter());
+        let surface_format = surface_caps
+            .formats
+            .iter()
+            .copied()
+            .find(|f| f.is_srgb())
+            .or_else(|| surface_caps.formats.first().copied())
+            .ok_or(PineError::UnsupportedSurfaceError)?;
+
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: surface_format,
+            width: size.0,
+            height: size.1,
+            present_mode: options.present_mode.select(&surface_caps.present_modes),
+            desired_maximum_frame_latency: 2, // Default
+            alpha_mode: options.select_alpha_mode(&surface_caps.alpha_modes),
+            view_formats: vec![],
+        };
+        surface.configure(gpu.device(), &config);
+
+        Ok(Self::with_output(gpu, Output::Window { surface, config }))
+    }
+
+    /// Constructs a backend drawing into a texture of the given size.
+    ///
+    /// Frames can be read back with [`WgpuBackend::read_pixels`] once submitted.
+    pub fn offscreen(gpu: Arc<GpuContext>, width: u32, height: u32) -> Self {
+        let target = OffscreenTarget::new(gpu.device(), width, height);
+        Self::with_output(gpu, Output::Offscreen(Box::new(target)))
+    }
+
+    fn with_output(gpu: Arc<GpuContext>, output: Output) -> Self {
+        let uniform_layout =
+            gpu.device()
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("Uniform layout"),
+                    entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    }],
+                });
+
+        Self {
+            gpu,
+            output,
+            uniform_layout,
+            buffers: RefCell::new(HashMap::new()),
+            pipelines: RefCell::new(HashMap::new()),
+            render_textures: RefCell::new(HashSet::new()),
+            next_id: Cell::new(0),
+        }
+    }
+
+    pub fn gpu(&self) -> &Arc<GpuContext> {
+        &self.gpu
+    }
+
+    /// Returns the format of the output, which [`TextureFormat::Output`] stands for.
+    pub fn format(&self) -> wgpu::TextureFormat {
+        match &self.output {
+            Output::Window { config, .. } => config.format,
+            Output::Offscreen(_) => OFFSCREEN_FORMAT,
+        }
+    }
+
+    /// Returns the size of the output as (width, height).
+    pub fn size(&self) -> (u32, u32) {
+        match &self.output {
+            Output::Window { config, .. } => (config.width, config.height),
+            Output::Offscreen(target) => target.size(),
+        }
+    }
+
+    /// Resizes the output, reconfiguring the surface of a window. Empty sizes are ignored.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        match &mut self.output {
+            Output::Window { surface, config } => {
+                config.width = width;
+                config.height = height;
+                surface.configure(self.gpu.device(), config);
+            }
+            Output::Offscreen(target) => {
+                **target = OffscreenTarget::new(self.gpu.device(), width, height);
+            }
+        }
+    }
+
+    /// Reads the last submitted frame back as an RGBA image.
+    ///
+    /// Only available when drawing offscreen.
+    pub fn read_pixels(&self) -> Result<image::RgbaImage, PineError> {
+        match &self.output {
+            Output::Offscreen(target) => target.read_pixels(self.gpu.device()),
+            Output::Window { .. } => Err(PineError::ReadbackError),
+        }
+    }
+
+    /// Records the commands into an encoder, with the given view as the output.
+    ///
+    /// Invalid commands, like draws outside of a pass or bindings of destroyed resources, are
+    /// skipped with a warning.
+    fn encode(
+        &self,
+        commands: &CommandList,
+        encoder: &mut wgpu::CommandEncoder,
+        output: &wgpu::TextureView,
+    ) {
+        let buffers = self.buffers.borrow();
+        let pipelines = self.pipelines.borrow();
+        // Locked before the passes, as they borrow the textures they draw with.
+        let textures = self.gpu.texture_cache();
+
+        let mut rest = commands.commands();
+        while let Some(start) = rest
+            .iter()
+            .position(|command| matches!(command, Command::BeginPass { .. }))
+        {
+            warn_outside_of_pass(&rest[..start]);
+            let Command::BeginPass {
+                label,
+                target,
+                clear,
+            } = &rest[start]
+            else {
+                unreachable!("Found the start of the pass above");
+            };
+            let body = &rest[start + 1..];
+            let end = body
+                .iter()
+                .position(|command| matches!(command, Command::BeginPass { .. } | Command::EndPass))
+                .unwrap_or(body.len());
+            rest = &body[end..];
+
+            let view = match target {
+                PassTarget::Output => Some(output),
+                PassTarget::Texture(texture) => self
+                    .render_textures
+                    .borrow()
+                    .contains(texture)
+                    .then(|| textures.get(*texture).map(|texture| &texture.view))
+                    .flatten(),
+            };
+            let Some(view) = view else {
+                tracing::warn!("Skipping pass {:?} into unknown {:?}", label, target);
+                continue;
+            };
+
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(label),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: match clear {
+                            Some(color) => wgpu::LoadOp::Clear((*color).into()),
+                            None => wgpu::LoadOp::Load,
+                        },
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            let pass_commands = &body[..end];
+            Self::encode_pass(
+                &mut pass,
+                pass_commands,
+                *target,
+                &buffers,
+                &pipelines,
+                &textures,
+            );
+        }
+        warn_outside_of_pass(rest);
+    }
+
+    /// Records the commands of a single pass.
+    fn encode_pass<'pass>(
+        pass: &mut wgpu::RenderPass<'pass>,
+        commands: &[Command],
+        target: PassTarget,
+        buffers: &'pass HashMap<BufferId, GpuBuffer>,
+        pipelines: &'pass HashMap<PipelineId, wgpu::RenderPipeline>,
+        textures: &'pass TextureCache,
+    ) {
+        // Draws are skipped while any of these isn't bound, so wgpu doesn't fail validation.
+        let mut unbound = HashSet::from([Binding::Pipeline]);
+        for command in commands {
+            match command {
+                Command::BeginPass { .. } | Command::EndPass => {}
+                Command::SetPipeline(id) => {
+                    let pipeline = pipelines.get(id);
+                    if let Some(pipeline) = pipeline {
+                        pass.set_pipeline(pipeline);
+                    }
+                    bind(&mut unbound, Binding::Pipeline, pipeline.is_some(), id);
+                }
+                Command::SetUniform { group, buffer } => {
+                    let bind_group = buffers.get(buffer).and_then(|b| b.bind_group.as_ref());
+                    if let Some(bind_group) = bind_group {
+                        pass.set_bind_group(*group, bind_group, &[]);
+                    }
+                    bind(
+                        &mut unbound,
+                        Binding::Group(*group),
+                        bind_group.is_some(),
+                        buffer,
+                    );
+                }
+                Command::SetTexture { group, texture } => {
+                    // A texture can't be sampled by the pass drawing into it.
+                    let bound = textures
+                        .get(*texture)
+                        .filter(|_| target != PassTarget::Texture(*texture));
+                    if let Some(bound) = bound {
+                        pass.set_bind_group(*group, &bound.bind_group, &[]);
+                    }
+                    bind(
+                        &mut unbound,
+                        Binding::Group(*group),
+                        bound.is_some(),
+                        texture,
+                    );
+                }
+                Command::SetVertexBuffer { slot, buffer } => {
+                    let bound = buffers.get(buffer);
+                    if let Some(bound) = bound {
+                        pass.set_vertex_buffer(*slot, bound.buffer.slice(..));
+                    }
+                    bind(
+                        &mut unbound,
+                        Binding::VertexBuffer(*slot),
+                        bound.is_some(),
+                        buffer,
+                    );
+                }
+                Command::SetIndexBuffer(buffer) => {
+                    let bound = buffers.get(buffer);
+                    if let Some(bound) = bound {
+                        pass.set_index_buffer(bound.buffer.slice(..), wgpu::IndexFormat::Uint16);
+                    }
+                    bind(&mut unbound, Binding::IndexBuffer, bound.is_some(), buffer);
+                }
+                Command::Draw {
+                    vertices,
+                    instances,
+                } => {
+                    if unbound.is_empty() {
+                        pass.draw(vertices.clone(), instances.clone());
+                    }
+                }
+                Command::DrawIndexed { indices, instances } => {
+                    if unbound.is_empty() {
+                        pass.draw_indexed(indices.clone(), 0, instances.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the wgpu format a texture format stands for.
+    fn texture_format(&self, format: TextureFormat) -> wgpu::TextureFormat {
+        match format {
+            TextureFormat::Output => self.format(),
+            TextureFormat::Rgba8Srgb => wgpu::TextureFormat::Rgba8UnormSrgb,
+            TextureFormat::Rgba16Float => wgpu::TextureFormat::Rgba16Float,
+        }
+    }
+
+    fn next_id(&self) -> u64 {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        id
+    }
+}
+
+impl Backend for WgpuBackend {
+    fn create_buffer(&self, desc: &BufferDesc) -> BufferId {
+        let usage = match desc.usage {
+            BufferUsage::Vertex => wgpu::BufferUsages::VERTEX,
+            BufferUsage::Index => wgpu::BufferUsages::INDEX,
+            BufferUsage::Uniform => wgpu::BufferUsages::UNIFORM,
+        };
+        let device = self.gpu.device();
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&desc.label),
+            size: desc.size,
+            usage: usage | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = (desc.usage == BufferUsage::Uniform).then(|| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(&desc.label),
+                layout: &self.uniform_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                }],
+            })
+        });
+
+        let id = BufferId(self.next_id());
+        self.buffers
+            .borrow_mut()
+            .insert(id, GpuBuffer { buffer, bind_group });
+        id
+    }
+
+    fn write_buffer(&self, buffer: BufferId, offset: u64, data: &[u8]) {
+        match self.buffers.borrow().get(&buffer) {
+            Some(buffer) => self.gpu.queue().write_buffer(&buffer.buffer, offset, data),
+            None => tracing::warn!("Not writing to unknown buffer {:?}", buffer),
+        }
+    }
+
+    fn destroy_buffer(&self, buffer: BufferId) {
+        self.buffers.borrow_mut().remove(&buffer);
+    }
+
+    fn create_texture(&self, image: &image::RgbaImage, options: TextureOptions) -> TextureHandle {
+        self.gpu.create_texture(image, options)
+    }
+
+    fn create_render_texture(
+        &self,
+        label: &str,
+        size: (u32, u32),
+        format: TextureFormat,
+    ) -> TextureHandle {
+        let texture = self.gpu.texture_cache_mut().add_render_target(
+            self.gpu.device(),
+            size,
+            self.texture_format(format),
+            label,
+        );
+        self.render_textures.borrow_mut().insert(texture);
+        texture
+    }
+
+    fn create_texture_3d(&self, size: (u32, u32, u32), data: &[u8]) -> TextureHandle {
+        self.gpu
+            .texture_cache_mut()
+            .add_3d(self.gpu.device(), self.gpu.queue(), size, data)
+    }
+
+    fn supports_format(&self, format: TextureFormat) -> bool {
+        if format == TextureFormat::Output {
+            return true;
+        }
+        let features = self
+            .gpu
+            .adapter()
+            .get_texture_format_features(self.texture_format(format));
+        features
+            .allowed_usages
+            .contains(wgpu::TextureUsages::RENDER_ATTACHMENT)
+            && features.flags.contains(
+                wgpu::TextureFormatFeatureFlags::BLENDABLE
+                    | wgpu::TextureFormatFeatureFlags::FILTERABLE,
+            )
+    }
+
+    fn texture_size(&self, texture: TextureHandle) -> Option<(u32, u32)> {
+        self.gpu
+            .texture_cache()
+            .get(texture)
+            .map(|texture| texture.size)
+    }
+
+    fn destroy_texture(&self, texture: TextureHandle) {
+        self.render_textures.borrow_mut().remove(&texture);
+        self.gpu.texture_cache_mut().remove(texture);
+    }
+
+    fn create_pipeline(&self, desc: &PipelineDesc) -> Result<PipelineId, PineError> {
+        let device = self.gpu.device();
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+
+        let module = match &desc.shader {
+            ShaderSource::Wgsl(source) => {
+                Arc::new(device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                    label: Some(&desc.label),
+                    source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(source)),
+                }))
+            }
+            ShaderSource::Shader(id) => self.gpu.shaders().module(*id),
+        };
+
+        let textures = self.gpu.texture_cache();
+        let bind_group_layouts: Vec<_> = desc
+            .bind_groups
+            .iter()
+            .map(|kind| match kind {
+                BindingKind::Uniform => &self.uniform_layout,
+                BindingKind::Texture => textures.layout(),
+                BindingKind::Texture3d => textures.volume_layout(),
+            })
+            .collect();
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(&desc.label),
+            bind_group_layouts: &bind_group_layouts,
+            push_constant_ranges: &[],
+        });
+
+        let attributes: Vec<Vec<_>> = desc
+            .vertex_buffers
+            .iter()
+            .map(|layout| {
+                let mut offset = 0;
+                layout
+                    .attributes
+                    .iter()
+                    .map(|&(shader_location, format)| {
+                        let attribute = wgpu::VertexAttribute {
+                            format: format.into(),
+                            offset,
+                            shader_location,
+                        };
+                        offset += format.size();
+                        attribute
+                    })
+                    .collect()
+            })
+            .collect();
+        let vertex_buffers: Vec<_> = desc
+            .vertex_buffers
+            .iter()
+            .zip(&attributes)
+            .map(|(layout, attributes)| wgpu::VertexBufferLayout {
+                array_stride: layout.stride,
+                step_mode: match layout.step_mode {
+                    StepMode::Vertex => wgpu::VertexStepMode::Vertex,
+                    StepMode::Instance => wgpu::VertexStepMode::Instance,
+                },
+                attributes,
+            })
+            .collect();
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(&desc.label),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &module,
+                entry_point: &desc.vertex_entry,
+                buffers: &vertex_buffers,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &module,
+                entry_point: &desc.fragment_entry,
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: self.texture_format(desc.format),
+                    blend: blend_state(desc.blend),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                cull_mode: None,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        if let Some(err) = pollster::block_on(device.pop_error_scope()) {
+            return Err(PineError::CreatePipelineError(err));
+        }
+        let id = PipelineId(self.next_id());
+        self.pipelines.borrow_mut().insert(id, pipeline);
+        Ok(id)
+    }
+
+    fn destroy_pipeline(&self, pipeline: PipelineId) {
+        self.pipelines.borrow_mut().remove(&pipeline);
+    }
+
+    /// Executes the commands, presenting the frame if the output is a window.
+    ///
+    /// Frames that can't be drawn right now, e.g. because the surface was just lost, are skipped.
+    /// Only unrecoverable failures are returned as errors.
+    fn submit(&self, commands: &CommandList) -> Result<(), PineError> {
+        let mut encoder = self
+            .gpu
+            .device()
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        match &self.output {
+            Output::Window { surface, config } => {
+                let surface_texture = match surface.get_current_texture() {
+                    Ok(surface_texture) => surface_texture,
+                    Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                        tracing::debug!("Surface lost or outdated, reconfiguring");
+                        surface.configure(self.gpu.device(), config);
+                        return Ok(());
+                    }
+                    Err(wgpu::SurfaceError::Timeout) => {
+                        tracing::warn!("Timed out acquiring the next frame, skipping it");
+                        return Ok(());
+                    }
+                    Err(err @ wgpu::SurfaceError::OutOfMemory) => {
+                        return Err(PineError::SurfaceError(err));
+                    }
+                };
+                let view = surface_texture
+                    .texture
+                    .create_view(&wgpu::TextureViewDescriptor::default());
+
+                self.encode(commands, &mut encoder, &view);
+                self.gpu.queue().submit(std::iter::once(encoder.finish()));
+                surface_texture.present();
+            }
+            Output::Offscreen(target) => {
+                self.encode(commands, &mut encoder, target.view());
+                target.copy_to_buffer(&mut encoder);
+                self.gpu.queue().submit(std::iter::once(encoder.finish()));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for WgpuBackend {
+    /// Frees the render textures, which nothing outside of the backend can draw into.
+    fn drop(&mut self) {
+        let mut textures = self.gpu.texture_cache_mut();
+        for texture in self.render_textures.get_mut().drain() {
+            textures.remove(texture);
+        }
+    }
+}
+
+/// Tracks whether a binding succeeded, warning about the resource it couldn't find otherwise.
+fn bind(unbound: &mut HashSet<Binding>, binding: Binding, bound: bool, what: &dyn Debug) {
+    if bound {
+        unbound.remove(&binding);
+    } else {
+        tracing::warn!("Skipping draws with unknown {:?}", what);
+        unbound.insert(binding);
+    }
+}
+
+/// Warns about commands found between passes, which are skipped.
+fn warn_outside_of_pass(commands: &[Command]) {
+    if commands
+        .iter()
+        .any(|command| !matches!(command, Command::EndPass))
+    {
+        tracing::warn!("Skipping commands recorded outside of a pass");
+    }
+}
+
+/// Returns the blend state of a blend mode, if it blends at all.
+fn blend_state(blend: BlendMode) -> Option<wgpu::BlendState> {
+    match blend {
+        BlendMode::Replace => None,
+        BlendMode::Alpha => Some(wgpu::BlendState::ALPHA_BLENDING),
+        BlendMode::Additive => Some(wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent::REPLACE,
+        }),
+    }
+}
+
+impl From<VertexFormat> for wgpu::VertexFormat {
+    fn from(format: VertexFormat) -> Self {
+        match format {
+            VertexFormat::Float32x2 => wgpu::VertexFormat::Float32x2,
+            VertexFormat::Float32x4 => wgpu::VertexFormat::Float32x4,
+        }
+    }
+}
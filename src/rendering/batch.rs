@@ -0,0 +1,182 @@
+use super::{
+    backend::{Backend, BufferDesc, BufferId, BufferUsage, Command, CommandList},
+    pipeline::{Quad, QuadInstance, QuadPipeline},
+    texture::TextureHandle,
+};
+
+use std::{
+    cell::{Cell, RefCell},
+    ops::Range,
+};
+
+/// The number of instances the instance buffer starts out with room for.
+const INITIAL_CAPACITY: u64 = 256;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+/// Statistics about the last batched frame across all its passes, useful for profiling.
+pub struct BatchStats {
+    /// The number of instanced draw calls issued.
+    pub draw_calls: u32,
+    /// The number of quads drawn across all batches.
+    pub instances: u32,
+    /// The number of instances the instance buffer currently has room for.
+    pub instance_capacity: u64,
+}
+
+#[derive(Debug)]
+/// A run of instances sharing the same texture.
+struct Batch {
+    texture: TextureHandle,
+    instances: Range<u32>,
+}
+
+#[derive(Debug)]
+/// The batches of a single frame, along with the instance buffer they were written into.
+pub struct PreparedBatches {
+    instance_buffer: BufferId,
+    batches: Vec<Batch>,
+}
+
+#[derive(Debug)]
+/// Collects quads into batches that share a texture and draws each batch with a single instanced
+/// draw call.
+///
+/// All instances of a frame are written into one buffer, which grows as needed. Each pass of the
+/// frame gets its own region of the buffer.
+pub struct SpriteBatcher {
+    instance_buffer: Cell<BufferId>,
+    /// Buffers outgrown this frame, which earlier passes still draw from until it's submitted.
+    retired: RefCell<Vec<BufferId>>,
+    capacity: Cell<u64>,
+    /// The number of instances written into the buffer this frame.
+    used: Cell<u64>,
+    stats: Cell<BatchStats>,
+}
+
+impl SpriteBatcher {
+    /// Constructs a new sprite batcher.
+    pub fn new(backend: &dyn Backend) -> Self {
+        Self {
+            instance_buffer: Cell::new(Self::create_instance_buffer(backend, INITIAL_CAPACITY)),
+            retired: RefCell::new(vec![]),
+            capacity: Cell::new(INITIAL_CAPACITY),
+            used: Cell::new(0),
+            stats: Cell::new(BatchStats {
+                instance_capacity: INITIAL_CAPACITY,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Returns statistics about the last prepared frame.
+    pub fn stats(&self) -> BatchStats {
+        self.stats.get()
+    }
+
+    /// Starts a new frame, reusing the whole instance buffer and freeing those outgrown during the
+    /// last frame.
+    pub fn begin_frame(&self, backend: &dyn Backend) {
+        for buffer in self.retired.borrow_mut().drain(..) {
+            backend.destroy_buffer(buffer);
+        }
+        self.used.set(0);
+        self.stats.set(BatchStats {
+            instance_capacity: self.capacity.get(),
+            ..Default::default()
+        });
+    }
+
+    /// Sorts the quads and writes them into the instance buffer, after those of earlier passes of
+    /// the frame.
+    ///
+    /// Quads are sorted by layer first and texture second, so quads on the same layer may be drawn
+    /// in any order. Use layers to order overlapping sprites.
+    pub fn prepare(&self, backend: &dyn Backend, quads: &mut [Quad]) -> PreparedBatches {
+        quads.sort_by(|a, b| {
+            a.layer
+                .total_cmp(&b.layer)
+                .then_with(|| a.texture.0.cmp(&b.texture.0))
+        });
+
+        let count = quads.len() as u64;
+        let mut base = self.used.get();
+        let required = base + count;
+        if required > self.capacity.get() {
+            let capacity = required.next_power_of_two();
+            tracing::debug!("Growing instance buffer to {} instances", capacity);
+
+            // Earlier passes keep drawing from the old buffer, so this pass starts the new one.
+            let buffer = Self::create_instance_buffer(backend, capacity);
+            self.retired
+                .borrow_mut()
+                .push(self.instance_buffer.replace(buffer));
+            self.capacity.set(capacity);
+            base = 0;
+        }
+        self.used.set(base + count);
+
+        let mut instances = Vec::with_capacity(quads.len());
+        let mut batches: Vec<Batch> = vec![];
+        for (i, quad) in quads.iter().enumerate() {
+            instances.push(quad.to_instance());
+
+            let i = (base + i as u64) as u32;
+            match batches.last_mut() {
+                Some(batch) if batch.texture == quad.texture => batch.instances.end = i + 1,
+                _ => batches.push(Batch {
+                    texture: quad.texture,
+                    instances: i..i + 1,
+                }),
+            }
+        }
+
+        let instance_buffer = self.instance_buffer.get();
+        if !instances.is_empty() {
+            let offset = base * std::mem::size_of::<QuadInstance>() as u64;
+            backend.write_buffer(instance_buffer, offset, bytemuck::cast_slice(&instances));
+        }
+
+        let stats = self.stats.get();
+        self.stats.set(BatchStats {
+            draw_calls: stats.draw_calls + batches.len() as u32,
+            instances: stats.instances + count as u32,
+            instance_capacity: self.capacity.get(),
+        });
+
+        PreparedBatches {
+            instance_buffer,
+            batches,
+        }
+    }
+
+    /// Records one instanced draw call per batch, with the globals the pipeline wrote for the pass.
+    pub fn record(
+        commands: &mut CommandList,
+        pipeline: &QuadPipeline,
+        globals: BufferId,
+        prepared: &PreparedBatches,
+    ) {
+        if prepared.batches.is_empty() {
+            return;
+        }
+
+        pipeline.bind(commands, globals);
+        commands.push(Command::SetVertexBuffer {
+            slot: 1,
+            buffer: prepared.instance_buffer,
+        });
+
+        for batch in &prepared.batches {
+            pipeline.draw_instances(commands, batch.texture, batch.instances.clone());
+        }
+    }
+
+    /// Creates an instance buffer with room for the given number of instances.
+    fn create_instance_buffer(backend: &dyn Backend, capacity: u64) -> BufferId {
+        backend.create_buffer(&BufferDesc::new(
+            "Sprite instance buffer",
+            capacity * std::mem::size_of::<QuadInstance>() as u64,
+            BufferUsage::Vertex,
+        ))
+    }
+}
@@ -0,0 +1,364 @@
+use glam::{Mat4, Vec2, Vec3};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+/// How the camera maps world units onto the viewport.
+pub enum ScalingMode {
+    /// One world unit per logical pixel, so more of the world is visible in larger windows.
+    #[default]
+    WindowSize,
+    /// Always show the given number of world units vertically.
+    ///
+    /// Units that aren't positive and finite are clamped when the mode is set.
+    FixedHeight(f32),
+    /// Always show the given number of world units horizontally, clamped like
+    /// [`ScalingMode::FixedHeight`].
+    FixedWidth(f32),
+    /// Scale a reference resolution by the largest whole number that fits the viewport.
+    ///
+    /// Keeps pixel art crisp by never stretching a world unit across a fractional number of
+    /// pixels.
+    PixelPerfect { width: u32, height: u32 },
+}
+
+impl ScalingMode {
+    /// Clamps the units shown to a positive, finite number, so the projection stays finite.
+    fn clamped(self) -> Self {
+        // NaN fails the comparison, so it ends up as the smallest value too.
+        let clamp = |units: f32| {
+            if units > 0.0 {
+                units.min(f32::MAX)
+            } else {
+                f32::EPSILON
+            }
+        };
+        match self {
+            ScalingMode::FixedHeight(units) => ScalingMode::FixedHeight(clamp(units)),
+            ScalingMode::FixedWidth(units) => ScalingMode::FixedWidth(clamp(units)),
+            mode => mode,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// An orthographic 2D camera.
+///
+/// World space has its y axis pointing up. Screen space is measured in logical pixels from the
+/// top left corner of the viewport, with the y axis pointing down.
+pub struct Camera2D {
+    position: Vec2,
+    zoom: f32,
+    rotation: f32,
+    scaling_mode: ScalingMode,
+    viewport: (u32, u32),
+    scale_factor: f64,
+}
+
+impl Default for Camera2D {
+    fn default() -> Self {
+        Self {
+            position: Vec2::ZERO,
+            zoom: 1.0,
+            rotation: 0.0,
+            scaling_mode: ScalingMode::default(),
+            viewport: (1, 1),
+            scale_factor: 1.0,
+        }
+    }
+}
+
+impl Camera2D {
+    /// Constructs a new camera looking at the origin.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the position the camera looks at.
+    pub fn with_position(mut self, position: Vec2) -> Self {
+        self.position = position;
+        self
+    }
+
+    /// Sets the zoom. Values above 1 zoom in.
+    pub fn with_zoom(mut self, zoom: f32) -> Self {
+        self.set_zoom(zoom);
+        self
+    }
+
+    /// Sets the rotation in radians, counter-clockwise.
+    pub fn with_rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Sets how world units are mapped onto the viewport.
+    pub fn with_scaling_mode(mut self, scaling_mode: ScalingMode) -> Self {
+        self.set_scaling_mode(scaling_mode);
+        self
+    }
+
+    pub fn position(&self) -> Vec2 {
+        self.position
+    }
+
+    pub fn set_position(&mut self, position: Vec2) {
+        self.position = position;
+    }
+
+    /// Moves the camera by the given offset in world units.
+    pub fn pan(&mut self, offset: Vec2) {
+        self.position += offset;
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    /// Sets the zoom. Values above 1 zoom in.
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom.max(f32::EPSILON);
+    }
+
+    /// Multiplies the zoom by the given factor.
+    pub fn zoom_by(&mut self, factor: f32) {
+        self.set_zoom(self.zoom * factor);
+    }
+
+    pub fn rotation(&self) -> f32 {
+        self.rotation
+    }
+
+    /// Sets the rotation in radians, counter-clockwise.
+    pub fn set_rotation(&mut self, rotation: f32) {
+        self.rotation = rotation;
+    }
+
+    /// Rotates the camera by the given angle in radians, counter-clockwise.
+    pub fn rotate(&mut self, angle: f32) {
+        self.rotation += angle;
+    }
+
+    pub fn scaling_mode(&self) -> ScalingMode {
+        self.scaling_mode
+    }
+
+    pub fn set_scaling_mode(&mut self, scaling_mode: ScalingMode) {
+        self.scaling_mode = scaling_mode.clamped();
+    }
+
+    /// Sets the size of the viewport in physical pixels.
+    pub fn set_viewport(&mut self, width: u32, height: u32) {
+        self.viewport = (width.max(1), height.max(1));
+    }
+
+    /// Returns the size of the viewport in physical pixels.
+    pub fn viewport(&self) -> (u32, u32) {
+        self.viewport
+    }
+
+    /// Sets the ratio between physical and logical pixels.
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+    }
+
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    /// Returns the number of physical pixels a world unit covers, zoom included.
+    pub fn pixels_per_unit(&self) -> f32 {
+        let (width, height) = (self.viewport.0 as f32, self.viewport.1 as f32);
+        let base = match self.scaling_mode {
+            ScalingMode::WindowSize => self.scale_factor as f32,
+            ScalingMode::FixedHeight(units) => height / units,
+            ScalingMode::FixedWidth(units) => width / units,
+            ScalingMode::PixelPerfect {
+                width: reference_width,
+                height: reference_height,
+            } => (width / reference_width.max(1) as f32)
+                .min(height / reference_height.max(1) as f32)
+                .floor()
+                .max(1.0),
+        };
+        base * self.zoom
+    }
+
+    /// Returns the position actually rendered from.
+    ///
+    /// In pixel-perfect mode the position is snapped to whole pixels to avoid shimmering.
+    fn render_position(&self) -> Vec2 {
+        match self.scaling_mode {
+            ScalingMode::PixelPerfect { .. } => {
+                let pixels_per_unit = self.pixels_per_unit();
+                (self.position * pixels_per_unit).round() / pixels_per_unit
+            }
+            _ => self.position,
+        }
+    }
+
+    /// Returns the view matrix, transforming world space into camera space.
+    pub fn view(&self) -> Mat4 {
+        let camera = Mat4::from_translation(self.render_position().extend(0.0))
+            * Mat4::from_rotation_z(self.rotation);
+        camera.inverse()
+    }
+
+    /// Returns the orthographic projection matrix, transforming camera space into clip space.
+    pub fn projection(&self) -> Mat4 {
+        let pixels_per_unit = self.pixels_per_unit();
+        let half_width = self.viewport.0 as f32 / 2.0 / pixels_per_unit;
+        let half_height = self.viewport.1 as f32 / 2.0 / pixels_per_unit;
+        Mat4::orthographic_rh(
+            -half_width,
+            half_width,
+            -half_height,
+            half_height,
+            -1.0,
+            1.0,
+        )
+    }
+
+    /// Returns the combined view projection matrix uploaded to the GPU.
+    pub fn view_proj(&self) -> Mat4 {
+        self.projection() * self.view()
+    }
+
+    /// Converts a position on the screen in logical pixels into world space.
+    pub fn screen_to_world(&self, screen: Vec2) -> Vec2 {
+        let physical = screen * self.scale_factor as f32;
+        let centered = Vec2::new(
+            physical.x - self.viewport.0 as f32 / 2.0,
+            self.viewport.1 as f32 / 2.0 - physical.y,
+        );
+        let camera_space = centered / self.pixels_per_unit();
+        self.view()
+            .inverse()
+            .transform_point3(camera_space.extend(0.0))
+            .truncate()
+    }
+
+    /// Converts a position in world space into logical pixels on the screen.
+    pub fn world_to_screen(&self, world: Vec2) -> Vec2 {
+        let camera_space = self.view().transform_point3(Vec3::from((world, 0.0)));
+        let centered = camera_space.truncate() * self.pixels_per_unit();
+        let physical = Vec2::new(
+            centered.x + self.viewport.0 as f32 / 2.0,
+            self.viewport.1 as f32 / 2.0 - centered.y,
+        );
+        physical / self.scale_factor as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::f32::consts::FRAC_PI_2;
+
+    fn assert_close(a: Vec2, b: Vec2) {
+        assert!((a - b).length() < 1e-3, "{} != {}", a, b);
+    }
+
+    fn assert_round_trips(camera: &Camera2D) {
+        for screen in [Vec2::ZERO, Vec2::new(12.0, 34.0), Vec2::new(200.0, 150.0)] {
+            let world = camera.screen_to_world(screen);
+            assert_close(camera.world_to_screen(world), screen);
+        }
+        for world in [Vec2::ZERO, Vec2::new(-7.5, 3.25)] {
+            let screen = camera.world_to_screen(world);
+            assert_close(camera.screen_to_world(screen), world);
+        }
+    }
+
+    /// A camera with a 400x300 physical viewport.
+    fn camera() -> Camera2D {
+        let mut camera = Camera2D::new();
+        camera.set_viewport(400, 300);
+        camera
+    }
+
+    #[test]
+    fn the_center_of_the_screen_is_the_camera_position() {
+        let camera = camera().with_position(Vec2::new(5.0, -3.0));
+        assert_close(
+            camera.screen_to_world(Vec2::new(200.0, 150.0)),
+            Vec2::new(5.0, -3.0),
+        );
+        // The y axis points down on the screen and up in the world.
+        assert_close(
+            camera.screen_to_world(Vec2::new(200.0, 140.0)),
+            Vec2::new(5.0, 7.0),
+        );
+    }
+
+    #[test]
+    fn screen_and_world_positions_round_trip() {
+        let mut camera = camera().with_position(Vec2::new(10.0, 20.0));
+        assert_round_trips(&camera);
+
+        camera.set_scale_factor(2.0);
+        assert_round_trips(&camera);
+        // The center moved to (100, 75) logical pixels, which still cover a world unit each.
+        assert_close(
+            camera.screen_to_world(Vec2::new(110.0, 75.0)),
+            Vec2::new(20.0, 20.0),
+        );
+
+        camera.set_zoom(4.0);
+        assert_round_trips(&camera);
+
+        camera.set_rotation(FRAC_PI_2);
+        assert_round_trips(&camera);
+        // Rotated a quarter turn, right on the screen is up in the world.
+        let right = camera.screen_to_world(Vec2::new(101.0, 75.0)) - camera.position();
+        assert_close(right.normalize(), Vec2::Y);
+
+        camera.set_scaling_mode(ScalingMode::PixelPerfect {
+            width: 100,
+            height: 100,
+        });
+        camera.set_position(Vec2::new(0.3, 0.7));
+        assert_round_trips(&camera);
+    }
+
+    #[test]
+    fn pixels_per_unit_follow_the_scaling_mode() {
+        let mut camera = camera();
+        camera.set_scale_factor(1.5);
+        assert_eq!(camera.pixels_per_unit(), 1.5);
+
+        camera.set_scaling_mode(ScalingMode::FixedHeight(10.0));
+        assert_eq!(camera.pixels_per_unit(), 30.0);
+
+        camera.set_scaling_mode(ScalingMode::FixedWidth(10.0));
+        assert_eq!(camera.pixels_per_unit(), 40.0);
+
+        // 400x300 fits the reference twice horizontally but only once and a half vertically.
+        camera.set_scaling_mode(ScalingMode::PixelPerfect {
+            width: 160,
+            height: 200,
+        });
+        assert_eq!(camera.pixels_per_unit(), 1.0);
+        camera.set_scaling_mode(ScalingMode::PixelPerfect {
+            width: 100,
+            height: 100,
+        });
+        assert_eq!(camera.pixels_per_unit(), 3.0);
+
+        camera.set_zoom(2.0);
+        assert_eq!(camera.pixels_per_unit(), 6.0);
+    }
+
+    #[test]
+    fn units_shown_are_clamped() {
+        for units in [0.0, -5.0, f32::NAN, f32::INFINITY] {
+            let camera = camera().with_scaling_mode(ScalingMode::FixedHeight(units));
+            assert!(camera.pixels_per_unit().is_finite());
+            assert!(camera.pixels_per_unit() > 0.0);
+            assert!(camera.view_proj().is_finite());
+            assert!(camera.screen_to_world(Vec2::new(1.0, 2.0)).is_finite());
+
+            let camera = camera.with_scaling_mode(ScalingMode::FixedWidth(units));
+            assert!(camera.view_proj().is_finite());
+        }
+    }
+}
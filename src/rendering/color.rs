@@ -1,4 +1,4 @@
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 /// A custom Color type.
 pub struct Color {
     r: f64,
@@ -8,6 +8,16 @@ pub struct Color {
 }
 
 impl Color {
+    /// Constructs a color from its red, green, blue and alpha components, each from 0 to 1.
+    pub const fn rgba(r: f64, g: f64, b: f64, a: f64) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// Constructs an opaque color from its red, green and blue components, each from 0 to 1.
+    pub const fn rgb(r: f64, g: f64, b: f64) -> Self {
+        Self::rgba(r, g, b, 1.0)
+    }
+
     pub const BLACK: Self = Self {
         r: 0.0,
         g: 0.0,
@@ -54,3 +64,14 @@ impl From<Color> for wgpu::Color {
         }
     }
 }
+
+impl From<Color> for [f32; 4] {
+    fn from(color: Color) -> Self {
+        [
+            color.r as f32,
+            color.g as f32,
+            color.b as f32,
+            color.a as f32,
+        ]
+    }
+}
@@ -1,47 +1,42 @@
+use super::color::Color;
+use crate::error::PineError;
+
 #[derive(Debug)]
 /// Data relevent for the rendering step.
 ///
 /// Produced in the preparation step.
-pub struct FrameData<'surface> {
-    pub clear_color: wgpu::Color,
-    pub surface: wgpu::Surface<'surface>,
+pub struct FrameData {
+    pub clear_color: Color,
 }
 
 #[derive(Debug)]
 /// A builder for the FrameData that allows for gradually setting the different values of the frame
 /// data.
-pub struct FrameDataBuilder<'surface> {
-    pub clear_color: Option<wgpu::Color>,
-    pub surface: Option<wgpu::Surface<'surface>>,
+pub struct FrameDataBuilder {
+    pub clear_color: Option<Color>,
 }
 
-impl<'b> Default for FrameDataBuilder<'b> {
+impl Default for FrameDataBuilder {
     fn default() -> Self {
         Self {
-            clear_color: Some(wgpu::Color::BLACK),
-            surface: None,
+            clear_color: Some(Color::BLACK),
         }
     }
 }
 
-impl<'b> FrameDataBuilder<'b> {
-    /// Sets the surface to render.
-    pub fn with_surface(mut self, surface: wgpu::Surface<'b>) -> Self {
-        self.surface = Some(surface);
-        self
-    }
-
+impl FrameDataBuilder {
     /// Sets the clear color to render with.
-    pub fn with_clear_color(mut self, clear_color: wgpu::Color) -> Self {
+    pub fn with_clear_color(mut self, clear_color: Color) -> Self {
         self.clear_color = Some(clear_color);
         self
     }
 
     /// Constructs the actual FrameData used in the render step.
-    pub fn build(self) -> FrameData<'b> {
-        FrameData {
-            clear_color: self.clear_color.unwrap(),
-            surface: self.surface.expect("No surface found in frame builder"),
-        }
+    pub fn build(self) -> Result<FrameData, PineError> {
+        Ok(FrameData {
+            clear_color: self
+                .clear_color
+                .ok_or(PineError::IncompleteFrameDataError("clear color"))?,
+        })
     }
 }
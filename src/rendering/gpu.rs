@@ -0,0 +1,184 @@
+use super::{
+    preprocessor::ShaderDefs,
+    shaders::{ShaderId, ShaderManager},
+    texture::{TextureHandle, TextureOptions},
+    texture_cache::TextureCache,
+};
+use crate::error::PineError;
+
+use winit::window::Window as WinitWindow;
+
+use std::{
+    path::Path,
+    sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+#[derive(Debug)]
+/// The GPU device and the assets living on it, shared by all renderers.
+///
+/// Textures and shaders are loaded once and can be used by any renderer using the same context.
+pub struct GpuContext {
+    instance: wgpu::Instance,
+    adapter: wgpu::Adapter,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    texture_cache: RwLock<TextureCache>,
+    shaders: RwLock<ShaderManager>,
+}
+
+impl GpuContext {
+    /// Creates a new wgpu instance.
+    pub fn create_instance() -> wgpu::Instance {
+        wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            ..Default::default()
+        })
+    }
+
+    /// Requests an adapter, along with its device and queue, from the given instance.
+    ///
+    /// If a surface is given, the adapter is guaranteed to be able to present to it.
+    pub async fn new(
+        instance: wgpu::Instance,
+        compatible_surface: Option<&wgpu::Surface<'_>>,
+        force_fallback_adapter: bool,
+    ) -> Result<Self, PineError> {
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface,
+                force_fallback_adapter,
+            })
+            .await
+            .ok_or(PineError::RequestAdapterError)?;
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: None,
+                    required_features: wgpu::Features::empty(),
+                    required_limits: wgpu::Limits::downlevel_defaults()
+                        .using_resolution(adapter.limits()),
+                },
+                None,
+            )
+            .await
+            .map_err(PineError::RequestDeviceError)?;
+
+        let texture_cache = TextureCache::new(&device, &queue);
+
+        Ok(Self {
+            instance,
+            adapter,
+            device,
+            queue,
+            texture_cache: RwLock::new(texture_cache),
+            shaders: RwLock::new(ShaderManager::default()),
+        })
+    }
+
+    /// Constructs a context without any surface to present to.
+    pub async fn headless(force_fallback_adapter: bool) -> Result<Self, PineError> {
+        Self::new(Self::create_instance(), None, force_fallback_adapter).await
+    }
+
+    /// Creates a surface for the given window.
+    pub fn create_surface(
+        &self,
+        window: Arc<WinitWindow>,
+    ) -> Result<wgpu::Surface<'static>, PineError> {
+        self.instance
+            .create_surface(window)
+            .map_err(PineError::CreateSurfaceError)
+    }
+
+    pub fn instance(&self) -> &wgpu::Instance {
+        &self.instance
+    }
+
+    pub fn adapter(&self) -> &wgpu::Adapter {
+        &self.adapter
+    }
+
+    pub fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    pub fn queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+
+    /// Uploads an image as a texture that sprites can be drawn with.
+    pub fn create_texture(
+        &self,
+        image: &image::RgbaImage,
+        options: TextureOptions,
+    ) -> TextureHandle {
+        self.texture_cache_mut()
+            .add(&self.device, &self.queue, image, options)
+    }
+
+    /// Loads a PNG or JPEG file as a texture that sprites can be drawn with.
+    ///
+    /// Loading the same file twice with the same options returns the same handle.
+    pub fn load_texture(
+        &self,
+        path: impl AsRef<Path>,
+        options: TextureOptions,
+    ) -> Result<TextureHandle, PineError> {
+        self.texture_cache_mut()
+            .load(&self.device, &self.queue, path, options)
+    }
+
+    /// Returns the textures shared by all renderers.
+    ///
+    /// NB: holding on to the cache blocks loading textures until it's dropped.
+    pub fn texture_cache(&self) -> RwLockReadGuard<'_, TextureCache> {
+        // The cache is never left half-updated, so a panic elsewhere doesn't invalidate it.
+        self.texture_cache
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub(crate) fn texture_cache_mut(&self) -> RwLockWriteGuard<'_, TextureCache> {
+        self.texture_cache
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Loads a WGSL file as a shader, which is watched for changes by [`GpuContext::poll_shaders`].
+    ///
+    /// Loading the same file twice returns the same shader.
+    pub fn load_shader(&self, path: impl AsRef<Path>) -> Result<ShaderId, PineError> {
+        self.shaders_mut().load(&self.device, path)
+    }
+
+    /// Loads a variant of a WGSL file, preprocessed with the given definitions.
+    pub fn load_shader_variant(
+        &self,
+        path: impl AsRef<Path>,
+        defs: &ShaderDefs,
+    ) -> Result<ShaderId, PineError> {
+        self.shaders_mut().load_variant(&self.device, path, defs)
+    }
+
+    /// Returns the shaders shared by all renderers.
+    ///
+    /// NB: holding on to the manager blocks loading and reloading shaders until it's dropped.
+    pub fn shaders(&self) -> RwLockReadGuard<'_, ShaderManager> {
+        self.shaders.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Reloads the shaders whose files changed, returning the ones that were swapped.
+    ///
+    /// Renderers pick up the new modules in [`Renderer::update_shaders`].
+    ///
+    /// [`Renderer::update_shaders`]: super::Renderer::update_shaders
+    pub fn poll_shaders(&self) -> Vec<ShaderId> {
+        self.shaders_mut().poll(&self.device)
+    }
+
+    fn shaders_mut(&self) -> RwLockWriteGuard<'_, ShaderManager> {
+        self.shaders.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
@@ -0,0 +1,638 @@
+use super::{
+    backend::{Backend, CommandList, PassTarget, TextureFormat},
+    color::Color,
+    pipeline::Quad,
+    texture::TextureHandle,
+    FrameCommands, FrameContext,
+};
+use crate::error::PineError;
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Debug,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// Describes a render target the graph allocates for passes to draw into and sample from.
+pub struct RenderTextureDesc {
+    /// The format of the texture. Defaults to the format of the renderer's output.
+    pub(crate) format: TextureFormat,
+    /// The size of the texture relative to the renderer's output.
+    pub(crate) scale: f32,
+}
+
+impl Default for RenderTextureDesc {
+    fn default() -> Self {
+        Self {
+            format: TextureFormat::Output,
+            scale: 1.0,
+        }
+    }
+}
+
+impl RenderTextureDesc {
+    /// Sets the format of the texture.
+    ///
+    /// NB: pipelines drawing into the texture must be created for the same format.
+    pub fn with_format(mut self, format: TextureFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Sets the size of the texture relative to the renderer's target, e.g. 0.5 for half size.
+    pub fn with_scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Declares a pass of the graph along with the textures it reads and writes.
+///
+/// Passes reading a texture run after all passes writing it. Passes writing the same texture run
+/// in the order they were added.
+pub struct PassDesc {
+    name: String,
+    reads: Vec<String>,
+    writes: Vec<String>,
+}
+
+impl PassDesc {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            reads: vec![],
+            writes: vec![],
+        }
+    }
+
+    /// Declares a texture the pass samples from.
+    pub fn with_read(mut self, texture: &str) -> Self {
+        self.reads.push(texture.to_string());
+        self
+    }
+
+    /// Declares a texture the pass draws into.
+    pub fn with_write(mut self, texture: &str) -> Self {
+        self.writes.push(texture.to_string());
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn reads(&self) -> &[String] {
+        &self.reads
+    }
+
+    pub fn writes(&self) -> &[String] {
+        &self.writes
+    }
+}
+
+/// Work recorded by the render graph, such as drawing the scene or a post-processing effect.
+pub trait GraphPass: Debug {
+    /// Records the pass into the commands of the context.
+    fn run(&self, context: &mut PassContext) -> Result<(), PineError>;
+}
+
+/// What a pass gets to record its work with.
+pub struct PassContext<'a> {
+    /// The backend the frame is drawn through, e.g. to create the pipelines of the pass with.
+    pub backend: &'a dyn Backend,
+    /// The commands of the frame so far, which the pass appends its own to.
+    pub commands: &'a mut CommandList,
+    /// The color the frame should be cleared with.
+    pub clear_color: Color,
+    /// The size of the renderer's output as (width, height).
+    pub size: (u32, u32),
+    pub(crate) frame: &'a FrameContext<'a>,
+    /// The quads drawn by each scene pass, by the index of the command beginning the pass.
+    pub(crate) sprites: &'a mut BTreeMap<usize, Vec<Quad>>,
+    pub(crate) desc: &'a PassDesc,
+    pub(crate) textures: &'a HashMap<&'a str, TextureHandle>,
+}
+
+impl PassContext<'_> {
+    /// Returns the texture the pass declared it reads, to be bound with
+    /// [`Command::SetTexture`](super::backend::Command::SetTexture).
+    ///
+    /// NB: [`RenderGraph::OUTPUT`] can't be read.
+    pub fn read(&self, texture: &str) -> Result<TextureHandle, PineError> {
+        let declared = self.desc.reads.iter().any(|read| read == texture);
+        declared
+            .then(|| self.textures.get(texture).copied())
+            .flatten()
+            .ok_or_else(|| self.undeclared(texture))
+    }
+
+    /// Returns the target of a texture the pass declared it writes.
+    pub fn write(&self, texture: &str) -> Result<PassTarget, PineError> {
+        if !self.desc.writes.iter().any(|write| write == texture) {
+            return Err(self.undeclared(texture));
+        }
+        if texture == RenderGraph::OUTPUT {
+            return Ok(PassTarget::Output);
+        }
+        self.textures
+            .get(texture)
+            .map(|&texture| PassTarget::Texture(texture))
+            .ok_or_else(|| self.undeclared(texture))
+    }
+
+    /// Records a pass drawing the renderer's scene graph into the target, cleared with the
+    /// frame's clear color.
+    pub fn draw_scene(&mut self, target: PassTarget) {
+        let start = self.commands.len();
+        let scene = &self.frame.scene;
+        let quads = scene.record(
+            self.commands,
+            self.frame.camera,
+            self.frame.scene_graph,
+            target,
+            self.clear_color,
+        );
+        self.sprites.insert(start, quads);
+    }
+
+    /// Records the renderer's post-processing effects, reading the scene from `source` and
+    /// drawing the result into `output`.
+    ///
+    /// Records nothing if post-processing isn't enabled, so [`PostProcessPass`] should only be
+    /// added through [`Renderer::enable_post_processing`].
+    ///
+    /// [`PostProcessPass`]: super::post::PostProcessPass
+    /// [`Renderer::enable_post_processing`]: super::Renderer::enable_post_processing
+    pub fn post_process(&mut self, source: TextureHandle, output: PassTarget) {
+        if let Some(post) = self.frame.post {
+            post.record(self.backend, self.commands, source, output, self.size);
+        }
+    }
+
+    fn undeclared(&self, texture: &str) -> PineError {
+        PineError::UndeclaredRenderTextureError {
+            pass: self.desc.name.clone(),
+            texture: texture.to_string(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PassEntry {
+    desc: PassDesc,
+    pass: Box<dyn GraphPass>,
+}
+
+#[derive(Debug)]
+/// A texture allocated by the graph.
+struct TransientTexture {
+    texture: TextureHandle,
+    size: (u32, u32),
+    format: TextureFormat,
+}
+
+#[derive(Debug, Default)]
+/// Passes ordered by the textures they read and write, recorded into one command list per frame.
+///
+/// Textures other than [`RenderGraph::OUTPUT`] are allocated through the renderer's backend and
+/// follow the size of its output.
+pub struct RenderGraph {
+    passes: Vec<PassEntry>,
+    textures: HashMap<String, RenderTextureDesc>,
+    /// Indices into `passes` in execution order.
+    order: Vec<usize>,
+    transients: RefCell<HashMap<String, TransientTexture>>,
+}
+
+impl RenderGraph {
+    /// The backend's output presented at the end of the frame, i.e. the window or offscreen
+    /// target.
+    pub const OUTPUT: &'static str = "output";
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declares a texture the graph should allocate for passes to use.
+    pub fn add_texture(&mut self, name: &str, desc: RenderTextureDesc) -> Result<(), PineError> {
+        if self.contains_texture(name) {
+            return Err(PineError::DuplicateRenderTextureError(name.to_string()));
+        }
+        self.textures.insert(name.to_string(), desc);
+        Ok(())
+    }
+
+    /// Removes a texture. Passes still using it must be removed first.
+    ///
+    /// The texture is freed the next time the graph is executed.
+    pub fn remove_texture(&mut self, name: &str) -> Option<RenderTextureDesc> {
+        let in_use = self.passes.iter().any(|entry| {
+            entry
+                .desc
+                .reads
+                .iter()
+                .chain(&entry.desc.writes)
+                .any(|texture| texture == name)
+        });
+        if in_use {
+            return None;
+        }
+        self.textures.remove(name)
+    }
+
+    /// Adds a pass to the graph.
+    ///
+    /// Fails if the name is taken, a texture was not declared, a texture it reads isn't written by
+    /// a pass added before it, or the pass would create a cycle.
+    pub fn add_pass(
+        &mut self,
+        desc: PassDesc,
+        pass: impl GraphPass + 'static,
+    ) -> Result<(), PineError> {
+        if self.passes.iter().any(|entry| entry.desc.name == desc.name) {
+            return Err(PineError::DuplicateRenderPassError(desc.name));
+        }
+        if let Some(texture) = desc
+            .reads
+            .iter()
+            .chain(&desc.writes)
+            .find(|texture| *texture != Self::OUTPUT && !self.textures.contains_key(*texture))
+        {
+            return Err(PineError::UndeclaredRenderTextureError {
+                pass: desc.name.clone(),
+                texture: texture.clone(),
+            });
+        }
+        if let Some(texture) = desc.reads.iter().find(|texture| !self.is_written(texture)) {
+            return Err(PineError::UnwrittenRenderTextureError {
+                pass: desc.name.clone(),
+                texture: texture.clone(),
+            });
+        }
+
+        self.passes.push(PassEntry {
+            desc,
+            pass: Box::new(pass),
+        });
+        match self.sort() {
+            Ok(order) => {
+                self.order = order;
+                Ok(())
+            }
+            Err(err) => {
+                self.passes.pop();
+                Err(err)
+            }
+        }
+    }
+
+    /// Removes a pass from the graph.
+    ///
+    /// Passes reading a texture only this pass writes must be removed first.
+    pub fn remove_pass(&mut self, name: &str) -> Option<Box<dyn GraphPass>> {
+        let i = self
+            .passes
+            .iter()
+            .position(|entry| entry.desc.name == name)?;
+        let entry = self.passes.remove(i);
+        let still_read = self
+            .passes
+            .iter()
+            .flat_map(|other| &other.desc.reads)
+            .any(|texture| !self.is_written(texture));
+        if still_read {
+            self.passes.insert(i, entry);
+            return None;
+        }
+        // Removing a pass can't introduce a cycle.
+        self.order = self.sort().unwrap_or_default();
+        Some(entry.pass)
+    }
+
+    /// Returns whether the texture was declared, [`RenderGraph::OUTPUT`] included.
+    pub fn contains_texture(&self, name: &str) -> bool {
+        name == Self::OUTPUT || self.textures.contains_key(name)
+    }
+
+    pub fn contains_pass(&self, name: &str) -> bool {
+        self.passes.iter().any(|entry| entry.desc.name == name)
+    }
+
+    /// Returns whether any pass writes the texture.
+    fn is_written(&self, texture: &str) -> bool {
+        self.passes
+            .iter()
+            .any(|entry| entry.desc.writes.iter().any(|written| written == texture))
+    }
+
+    /// Returns the passes in the order they run.
+    pub fn passes(&self) -> impl Iterator<Item = &PassDesc> {
+        self.order.iter().map(|&i| &self.passes[i].desc)
+    }
+
+    /// Orders the passes topologically, preferring the order they were added in.
+    fn sort(&self) -> Result<Vec<usize>, PineError> {
+        let count = self.passes.len();
+        let mut dependents = vec![vec![]; count];
+        let mut dependencies = vec![0; count];
+        let mut add_edge = |from: usize, to: usize| {
+            if !dependents[from].contains(&to) {
+                dependents[from].push(to);
+                dependencies[to] += 1;
+            }
+        };
+
+        for (i, writer) in self.passes.iter().enumerate() {
+            for (j, other) in self.passes.iter().enumerate() {
+                if i == j {
+                    continue;
+                }
+                for texture in &writer.desc.writes {
+                    let reads = other.desc.reads.contains(texture);
+                    let writes_later = j > i && other.desc.writes.contains(texture);
+                    if reads || writes_later {
+                        add_edge(i, j);
+                    }
+                }
+            }
+        }
+
+        let mut ready: BTreeSet<usize> = (0..count).filter(|&i| dependencies[i] == 0).collect();
+        let mut order = Vec::with_capacity(count);
+        while let Some(i) = ready.pop_first() {
+            order.push(i);
+            for &j in &dependents[i] {
+                dependencies[j] -= 1;
+                if dependencies[j] == 0 {
+                    ready.insert(j);
+                }
+            }
+        }
+
+        if order.len() < count {
+            let cycle = (0..count)
+                .filter(|i| !order.contains(i))
+                .map(|i| self.passes[i].desc.name.clone())
+                .collect();
+            return Err(PineError::RenderGraphCycleError(cycle));
+        }
+        Ok(order)
+    }
+
+    /// Records all passes into a command list, drawing with the resources of the frame.
+    pub(crate) fn execute(
+        &self,
+        frame: &FrameContext,
+        size: (u32, u32),
+        clear_color: Color,
+    ) -> Result<FrameCommands, PineError> {
+        let backend = frame.scene.backend;
+        self.allocate_transients(backend, size);
+
+        let transients = self.transients.borrow();
+        let textures: HashMap<&str, TextureHandle> = transients
+            .iter()
+            .map(|(name, transient)| (name.as_str(), transient.texture))
+            .collect();
+
+        let mut recorded = FrameCommands::default();
+        for &i in &self.order {
+            let entry = &self.passes[i];
+            let mut context = PassContext {
+                backend,
+                commands: &mut recorded.commands,
+                clear_color,
+                size,
+                frame,
+                sprites: &mut recorded.sprites,
+                desc: &entry.desc,
+                textures: &textures,
+            };
+            entry.pass.run(&mut context)?;
+        }
+        Ok(recorded)
+    }
+
+    /// Makes sure every declared texture exists with the right size and format, freeing those
+    /// that are no longer declared.
+    fn allocate_transients(&self, backend: &dyn Backend, size: (u32, u32)) {
+        let mut transients = self.transients.borrow_mut();
+        transients.retain(|name, transient| {
+            let declared = self.textures.contains_key(name);
+            if !declared {
+                backend.destroy_texture(transient.texture);
+            }
+            declared
+        });
+
+        for (name, desc) in &self.textures {
+            let scaled = |length: u32| ((length as f32 * desc.scale).round() as u32).max(1);
+            let size = (scaled(size.0), scaled(size.1));
+
+            let up_to_date = transients
+                .get(name)
+                .is_some_and(|transient| transient.size == size && transient.format == desc.format);
+            if up_to_date {
+                continue;
+            }
+
+            tracing::debug!("Allocating render texture {:?} at {:?}", name, size);
+            let texture = backend.create_render_texture(name, size, desc.format);
+            let transient = TransientTexture {
+                texture,
+                size,
+                format: desc.format,
+            };
+            if let Some(previous) = transients.insert(name.clone(), transient) {
+                backend.destroy_texture(previous.texture);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Draws the scene graph of the renderer into a texture, clearing it with the frame's clear color.
+pub struct ScenePass {
+    target: String,
+}
+
+impl ScenePass {
+    /// The name of the pass drawing the scene in the default graph.
+    pub const NAME: &'static str = "scene";
+
+    /// Constructs a pass drawing into the given texture.
+    pub fn new(target: &str) -> Self {
+        Self {
+            target: target.to_string(),
+        }
+    }
+
+    /// Returns the description of the pass, writing its target.
+    pub fn desc(&self) -> PassDesc {
+        PassDesc::new(Self::NAME).with_write(&self.target)
+    }
+}
+
+impl GraphPass for ScenePass {
+    fn run(&self, context: &mut PassContext) -> Result<(), PineError> {
+        let target = context.write(&self.target)?;
+        context.draw_scene(target);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct NoopPass;
+
+    impl GraphPass for NoopPass {
+        fn run(&self, _context: &mut PassContext) -> Result<(), PineError> {
+            Ok(())
+        }
+    }
+
+    /// A graph with the given textures declared.
+    fn graph(textures: &[&str]) -> RenderGraph {
+        let mut graph = RenderGraph::new();
+        for texture in textures {
+            graph
+                .add_texture(texture, RenderTextureDesc::default())
+                .unwrap();
+        }
+        graph
+    }
+
+    fn order(graph: &RenderGraph) -> Vec<&str> {
+        graph.passes().map(PassDesc::name).collect()
+    }
+
+    #[test]
+    fn writers_run_before_readers() {
+        let mut graph = graph(&["scene", "bloom"]);
+        graph
+            .add_pass(PassDesc::new("draw").with_write("scene"), NoopPass)
+            .unwrap();
+        graph
+            .add_pass(
+                PassDesc::new("blur").with_read("scene").with_write("bloom"),
+                NoopPass,
+            )
+            .unwrap();
+        graph
+            .add_pass(
+                PassDesc::new("composite")
+                    .with_read("scene")
+                    .with_read("bloom")
+                    .with_write(RenderGraph::OUTPUT),
+                NoopPass,
+            )
+            .unwrap();
+        // Added last, but its readers still see what it writes.
+        graph
+            .add_pass(PassDesc::new("overlay").with_write("scene"), NoopPass)
+            .unwrap();
+
+        assert_eq!(order(&graph), ["draw", "overlay", "blur", "composite"]);
+    }
+
+    #[test]
+    fn later_writers_run_after_earlier_ones() {
+        let mut graph = graph(&[]);
+        graph
+            .add_pass(
+                PassDesc::new("ui").with_write(RenderGraph::OUTPUT),
+                NoopPass,
+            )
+            .unwrap();
+        graph
+            .add_pass(
+                PassDesc::new("scene").with_write(RenderGraph::OUTPUT),
+                NoopPass,
+            )
+            .unwrap();
+        assert_eq!(order(&graph), ["ui", "scene"]);
+    }
+
+    #[test]
+    fn cycles_are_rejected() {
+        let mut graph = graph(&["a", "b"]);
+        graph
+            .add_pass(PassDesc::new("first").with_write("a"), NoopPass)
+            .unwrap();
+        graph
+            .add_pass(
+                PassDesc::new("second").with_read("a").with_write("b"),
+                NoopPass,
+            )
+            .unwrap();
+
+        let err = graph
+            .add_pass(
+                PassDesc::new("third").with_read("b").with_write("a"),
+                NoopPass,
+            )
+            .unwrap_err();
+        assert!(matches!(err, PineError::RenderGraphCycleError(_)));
+        assert!(!graph.contains_pass("third"));
+        assert_eq!(order(&graph), ["first", "second"]);
+    }
+
+    #[test]
+    fn passes_are_validated() {
+        let mut graph = graph(&["scene"]);
+        graph
+            .add_pass(PassDesc::new("draw").with_write("scene"), NoopPass)
+            .unwrap();
+
+        assert!(matches!(
+            graph.add_pass(PassDesc::new("draw").with_write("scene"), NoopPass),
+            Err(PineError::DuplicateRenderPassError(_))
+        ));
+        assert!(matches!(
+            graph.add_pass(PassDesc::new("blur").with_write("bloom"), NoopPass),
+            Err(PineError::UndeclaredRenderTextureError { .. })
+        ));
+        assert!(matches!(
+            graph.add_pass(PassDesc::new("blur").with_read("bloom"), NoopPass),
+            Err(PineError::UndeclaredRenderTextureError { .. })
+        ));
+        assert!(matches!(
+            graph.add_pass(
+                PassDesc::new("copy").with_read(RenderGraph::OUTPUT),
+                NoopPass
+            ),
+            Err(PineError::UnwrittenRenderTextureError { .. })
+        ));
+        assert_eq!(order(&graph), ["draw"]);
+    }
+
+    #[test]
+    fn textures_and_writers_in_use_are_kept() {
+        let mut graph = graph(&["scene"]);
+        graph
+            .add_pass(PassDesc::new("draw").with_write("scene"), NoopPass)
+            .unwrap();
+        graph
+            .add_pass(
+                PassDesc::new("present")
+                    .with_read("scene")
+                    .with_write(RenderGraph::OUTPUT),
+                NoopPass,
+            )
+            .unwrap();
+
+        assert!(graph.remove_texture("scene").is_none());
+        assert!(graph.remove_pass("draw").is_none());
+        assert_eq!(order(&graph), ["draw", "present"]);
+
+        graph.remove_pass("present").unwrap();
+        graph.remove_pass("draw").unwrap();
+        graph.remove_texture("scene").unwrap();
+        assert!(!graph.contains_texture("scene"));
+    }
+}
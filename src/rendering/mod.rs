@@ -1,194 +1,722 @@
+pub mod backend;
+pub mod batch;
+pub mod camera;
 pub mod color;
 pub mod frame_data;
+pub mod gpu;
+pub mod graph;
+pub mod offscreen;
+pub mod pipeline;
+pub mod post;
+pub mod preprocessor;
+pub mod recording;
 pub mod scene;
+pub mod scene_graph;
 pub mod shaders;
+pub mod snapshot;
+pub mod surface;
+pub mod texture;
+pub mod texture_cache;
 
 use self::{
+    backend::{Backend, Command, CommandList, PassTarget, ShaderSource, WgpuBackend},
+    batch::{BatchStats, SpriteBatcher},
+    camera::Camera2D,
+    color::Color,
     frame_data::{FrameData, FrameDataBuilder},
-    scene::{SceneNode2D, Transform},
+    gpu::GpuContext,
+    graph::{RenderGraph, RenderTextureDesc, ScenePass},
+    offscreen::HeadlessConfig,
+    pipeline::{Quad, QuadPipeline, QUAD_SHADER_PATH},
+    post::{
+        PostProcessPass, PostProcessing, PostProcessor, PostShaders, BLOOM_SHADER_PATH,
+        COMPOSITE_SHADER_PATH,
+    },
+    scene_graph::SceneGraph,
+    shaders::ShaderId,
+    surface::SurfaceConfig,
+    texture::{TextureHandle, TextureOptions},
+    texture_cache::TextureCache,
 };
 
 use crate::{error::PineError, windowing::Window};
 
 use winit::window::Window as WinitWindow;
 
-use std::fmt::Debug;
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    path::Path,
+    sync::{Arc, RwLockReadGuard},
+};
 
+/// Renders a window's scene.
+///
+/// Works with winit windows and sizes.
 pub trait Renderer: Debug {
     /// Prepares data for the rendering step.
     ///
     /// Returns either the frame data for the rendering step or a PineError.
-    fn prepare<'window>(&self, window: &'window Window) -> Result<FrameData<'window>, PineError>;
+    fn prepare(&self, window: &Window) -> Result<FrameData, PineError>;
 
     /// Renders with the given frame data.
-    fn render(&self, frame_data: &FrameData);
+    ///
+    /// Frames that can't be rendered right now, e.g. because the surface was just lost, are
+    /// skipped. Only unrecoverable failures are returned as errors.
+    fn render(&self, frame_data: &FrameData) -> Result<(), PineError>;
 
-    /// Sets the new size for the stored Surface config.
+    /// Sets the new size of the render target, reconfiguring the surface if there is one.
     fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>);
+
+    /// Sets the ratio between physical and logical pixels of the window.
+    fn set_scale_factor(&mut self, scale_factor: f64);
+
+    /// Rebuilds pipelines whose shaders were reloaded since the last call.
+    ///
+    /// Renderers that don't use shaders from the [`GpuContext`] can ignore this.
+    fn update_shaders(&mut self) {}
+
+    /// Reads back the last rendered frame as an RGBA image.
+    ///
+    /// Used for snapshot testing. Renderers that cannot read back their frames return a
+    /// PineError.
+    fn capture(&self) -> Result<image::RgbaImage, PineError>;
+
+    /// Returns the camera the scene is drawn through.
+    fn camera(&self) -> &Camera2D;
+
+    /// Returns the camera the scene is drawn through for modification.
+    fn camera_mut(&mut self) -> &mut Camera2D;
+
+    /// Returns the scene graph drawn by the renderer.
+    fn scene_graph(&self) -> &SceneGraph;
+
+    /// Returns the scene graph drawn by the renderer for modification.
+    fn scene_graph_mut(&mut self) -> &mut SceneGraph;
+
+    /// Returns the post-processing settings, if post-processing is enabled.
+    fn post_processing(&self) -> Option<&PostProcessing> {
+        None
+    }
+
+    /// Returns the post-processing settings for modification, if post-processing is enabled.
+    fn post_processing_mut(&mut self) -> Option<&mut PostProcessing> {
+        None
+    }
+
+    /// Sets up post-processing if needed and returns its settings.
+    ///
+    /// Renderers without post-processing return a PineError.
+    fn enable_post_processing(&mut self) -> Result<&mut PostProcessing, PineError> {
+        Err(PineError::UnsupportedPostProcessingError)
+    }
+}
+
+/// Records the scene pass through a backend, shared by all renderers drawing quads.
+pub(crate) struct SceneRecorder<'a> {
+    pub(crate) backend: &'a dyn Backend,
+    pub(crate) pipeline: &'a QuadPipeline,
+    pub(crate) batcher: &'a SpriteBatcher,
+}
+
+impl SceneRecorder<'_> {
+    /// Starts a new frame, after which passes are recorded into the buffers from the start.
+    fn begin_frame(&self) {
+        self.pipeline.begin_frame();
+        self.batcher.begin_frame(self.backend);
+    }
+
+    /// Prepares the sprites of the scene graph and records a pass drawing them through the
+    /// camera into the target.
+    ///
+    /// Every pass of a frame writes its own globals and instances, so passes don't overwrite each
+    /// other before the frame is submitted. Also returns the quads of the pass in draw order.
+    fn record(
+        &self,
+        commands: &mut CommandList,
+        camera: &Camera2D,
+        scene_graph: &SceneGraph,
+        target: PassTarget,
+        clear_color: Color,
+    ) -> Vec<Quad> {
+        let globals = self
+            .pipeline
+            .update_globals(self.backend, camera.view_proj());
+
+        let mut quads = vec![];
+        scene_graph.render(&mut quads);
+        let batches = self.batcher.prepare(self.backend, &mut quads);
+
+        commands.push(Command::BeginPass {
+            label: "Render pass".into(),
+            target,
+            clear: Some(clear_color),
+        });
+        SpriteBatcher::record(commands, self.pipeline, globals, &batches);
+        commands.push(Command::EndPass);
+        quads
+    }
+}
+
+/// Everything the passes of a render graph draw with in a frame.
+pub(crate) struct FrameContext<'a> {
+    pub(crate) scene: SceneRecorder<'a>,
+    pub(crate) camera: &'a Camera2D,
+    pub(crate) scene_graph: &'a SceneGraph,
+    pub(crate) post: Option<&'a PostProcessor>,
+}
+
+impl FrameContext<'_> {
+    /// Records the passes of the graph into a new frame.
+    fn record(
+        &self,
+        graph: &RenderGraph,
+        size: (u32, u32),
+        clear_color: Color,
+    ) -> Result<FrameCommands, PineError> {
+        self.scene.begin_frame();
+        graph.execute(self, size, clear_color)
+    }
+}
+
+#[derive(Debug, Default)]
+/// The commands recorded for a frame, to be submitted to a backend.
+pub(crate) struct FrameCommands {
+    pub(crate) commands: CommandList,
+    /// The quads drawn by each scene pass in draw order, by the index of the command beginning
+    /// the pass.
+    pub(crate) sprites: BTreeMap<usize, Vec<Quad>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+/// A shader of the GPU context, along with the generation its pipelines were built with.
+struct WatchedShader {
+    id: ShaderId,
+    generation: u64,
+}
+
+impl WatchedShader {
+    fn load(gpu: &GpuContext, path: impl AsRef<Path>) -> Result<Self, PineError> {
+        let id = gpu.load_shader(path)?;
+        Ok(Self {
+            id,
+            generation: gpu.shaders().generation(id),
+        })
+    }
+
+    /// Returns whether the shader was reloaded since the last call.
+    ///
+    /// Only returns true once per reload, so a broken shader isn't retried every frame.
+    fn reloaded(&mut self, gpu: &GpuContext) -> bool {
+        let generation = gpu.shaders().generation(self.id);
+        generation != std::mem::replace(&mut self.generation, generation)
+    }
+
+    fn source(&self) -> ShaderSource {
+        ShaderSource::Shader(self.id)
+    }
+}
+
+/// Creates a post-processor and reroutes the scene of the graph through it.
+///
+/// Moves the [`ScenePass`] to draw into [`PostProcessPass::SOURCE`] and adds a
+/// [`PostProcessPass`] from there to [`RenderGraph::OUTPUT`]. Everything that can fail is checked
+/// before the graph is changed.
+fn add_post_processing(
+    graph: &mut RenderGraph,
+    backend: &dyn Backend,
+    shaders: &PostShaders,
+) -> Result<PostProcessor, PineError> {
+    if graph.contains_texture(PostProcessPass::SOURCE) {
+        return Err(PineError::DuplicateRenderTextureError(
+            PostProcessPass::SOURCE.to_string(),
+        ));
+    }
+    if graph.contains_pass(PostProcessPass::NAME) {
+        return Err(PineError::DuplicateRenderPassError(
+            PostProcessPass::NAME.to_string(),
+        ));
+    }
+    let post = PostProcessor::new(backend, shaders, PostProcessing::default())?;
+
+    // Nothing else uses the new texture, so none of these can fail anymore.
+    graph.remove_pass(ScenePass::NAME);
+    graph.add_texture(PostProcessPass::SOURCE, RenderTextureDesc::default())?;
+    let scene_pass = ScenePass::new(PostProcessPass::SOURCE);
+    graph.add_pass(scene_pass.desc(), scene_pass)?;
+    graph.add_pass(PostProcessPass.desc(), PostProcessPass)?;
+    Ok(post)
+}
+
+/// The default render graph, drawing the scene straight into [`RenderGraph::OUTPUT`].
+fn default_render_graph() -> Result<RenderGraph, PineError> {
+    let mut render_graph = RenderGraph::new();
+    let scene_pass = ScenePass::new(RenderGraph::OUTPUT);
+    render_graph.add_pass(scene_pass.desc(), scene_pass)?;
+    Ok(render_graph)
 }
 
 #[derive(Debug)]
 /// State useful for rendering.
 pub struct Renderer2D {
-    instance: wgpu::Instance,
-    adapter: wgpu::Adapter,
-    device: wgpu::Device,
-    queue: wgpu::Queue,
-    surface_config: wgpu::SurfaceConfiguration,
-    scene_graph: SceneNode2D,
+    gpu: Arc<GpuContext>,
+    backend: WgpuBackend,
+    quad_pipeline: QuadPipeline,
+    quad_shader: WatchedShader,
+    /// The bloom and composite shaders, loaded along with the post-processor.
+    post_shaders: Option<[WatchedShader; 2]>,
+    sprite_batcher: SpriteBatcher,
+    camera: Camera2D,
+    scene_graph: SceneGraph,
+    render_graph: RenderGraph,
+    /// Set up the first time post-processing is enabled.
+    post: Option<PostProcessor>,
 }
 
 impl Renderer for Renderer2D {
-    fn prepare<'window>(&self, window: &'window Window) -> Result<FrameData<'window>, PineError> {
-        let surface = self
-            .instance
-            .create_surface(&window.handle)
-            .map_err(|err| PineError::CreateSurfaceError(err))?;
-
-        let frame_data_builder = FrameDataBuilder::default()
-            .with_surface(surface)
-            .with_clear_color(window.clear_color.into());
-
-        let data = frame_data_builder.build();
-        Ok(data)
-    }
-
-    fn render(&self, frame_data: &FrameData) {
-        frame_data
-            .surface
-            .configure(&self.device, &self.surface_config);
-
-        let surface_texture = frame_data.surface.get_current_texture().unwrap();
-        let view = surface_texture
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
-
-        let mut encoder = self.create_encoder();
-
-        {
-            let _render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(frame_data.clear_color),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
-                occlusion_query_set: None,
-                timestamp_writes: None,
-            });
-
-            self.scene_graph.render();
-
-            // render_pass.set_pipeline(&pipeline);
-        }
+    fn prepare(&self, window: &Window) -> Result<FrameData, PineError> {
+        let frame_data_builder = FrameDataBuilder::default().with_clear_color(window.clear_color);
 
-        self.queue.submit(std::iter::once(encoder.finish()));
-        surface_texture.present();
+        frame_data_builder.build()
+    }
+
+    fn render(&self, frame_data: &FrameData) -> Result<(), PineError> {
+        let frame = self.frame_context().record(
+            &self.render_graph,
+            self.backend.size(),
+            frame_data.clear_color,
+        )?;
+        self.backend.submit(&frame.commands)
     }
 
     fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
         if new_size.width > 0 && new_size.height > 0 {
-            self.surface_config.width = new_size.width;
-            self.surface_config.height = new_size.height;
-            // self.camera.aspect_ratio = new_size.width as f32 / new_size.height as f32;
+            self.backend.resize(new_size.width, new_size.height);
+            self.camera.set_viewport(new_size.width, new_size.height);
         }
     }
+
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.camera.set_scale_factor(scale_factor);
+    }
+
+    fn update_shaders(&mut self) {
+        if let (Some(post), Some(shaders)) = (&mut self.post, &mut self.post_shaders) {
+            // Both are checked, so neither reload is picked up again next time.
+            let [bloom, composite] = shaders;
+            let reloaded = [bloom.reloaded(&self.gpu), composite.reloaded(&self.gpu)];
+            if reloaded.contains(&true) {
+                let sources = post_shader_sources(shaders);
+                if let Err(err) = post.set_shaders(&self.backend, &sources) {
+                    tracing::error!("Keeping previous post-processing pipelines: {}", err);
+                }
+            }
+        }
+
+        if self.quad_shader.reloaded(&self.gpu) {
+            let shader = self.quad_shader.source();
+            if let Err(err) = self.quad_pipeline.set_shader(&self.backend, shader) {
+                tracing::error!("Keeping previous quad pipeline: {}", err);
+            }
+        }
+    }
+
+    fn capture(&self) -> Result<image::RgbaImage, PineError> {
+        self.read_pixels()
+    }
+
+    fn camera(&self) -> &Camera2D {
+        &self.camera
+    }
+
+    fn camera_mut(&mut self) -> &mut Camera2D {
+        &mut self.camera
+    }
+
+    fn scene_graph(&self) -> &SceneGraph {
+        &self.scene_graph
+    }
+
+    fn scene_graph_mut(&mut self) -> &mut SceneGraph {
+        &mut self.scene_graph
+    }
+
+    fn post_processing(&self) -> Option<&PostProcessing> {
+        self.post.as_ref().map(PostProcessor::settings)
+    }
+
+    fn post_processing_mut(&mut self) -> Option<&mut PostProcessing> {
+        self.post.as_mut().map(PostProcessor::settings_mut)
+    }
+
+    /// Sets up post-processing and returns its settings, all effects being disabled at first.
+    ///
+    /// Moves the [`ScenePass`] to draw into [`PostProcessPass::SOURCE`] and adds a
+    /// [`PostProcessPass`] from there to [`RenderGraph::OUTPUT`]. Does nothing but return the
+    /// settings if post-processing is already enabled. Leaves the graph untouched on failure.
+    fn enable_post_processing(&mut self) -> Result<&mut PostProcessing, PineError> {
+        let post = match self.post.take() {
+            Some(post) => post,
+            None => {
+                let shaders = [
+                    WatchedShader::load(&self.gpu, BLOOM_SHADER_PATH)?,
+                    WatchedShader::load(&self.gpu, COMPOSITE_SHADER_PATH)?,
+                ];
+                let post = add_post_processing(
+                    &mut self.render_graph,
+                    &self.backend,
+                    &post_shader_sources(&shaders),
+                )?;
+                self.post_shaders = Some(shaders);
+                post
+            }
+        };
+        Ok(self.post.insert(post).settings_mut())
+    }
+}
+
+/// The post-processing shaders drawn with from the GPU context's shaders.
+fn post_shader_sources([bloom, composite]: &[WatchedShader; 2]) -> PostShaders {
+    PostShaders {
+        bloom: bloom.source(),
+        composite: composite.source(),
+    }
 }
 
 impl Renderer2D {
-    /// Constructs a new Renderer.
-    pub async fn new(window: &WinitWindow) -> Result<Self, PineError> {
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::all(),
-            ..Default::default()
-        });
+    /// Constructs a new Renderer drawing into the given window.
+    ///
+    /// The renderer shares ownership of the window, as its surface must not outlive it.
+    pub fn new(
+        gpu: Arc<GpuContext>,
+        window: Arc<WinitWindow>,
+        options: &SurfaceConfig,
+    ) -> Result<Self, PineError> {
+        let surface = gpu.create_surface(window.clone())?;
+        Self::with_surface(gpu, window, surface, options)
+    }
 
-        let surface = instance
-            .create_surface(window)
-            .map_err(|err| PineError::CreateSurfaceError(err))?;
+    /// Constructs a new Renderer drawing into an already created surface of the given window.
+    ///
+    /// Useful for the first window, whose surface is needed to pick a compatible adapter.
+    pub fn with_surface(
+        gpu: Arc<GpuContext>,
+        window: Arc<WinitWindow>,
+        surface: wgpu::Surface<'static>,
+        options: &SurfaceConfig,
+    ) -> Result<Self, PineError> {
+        let size = window.inner_size();
+        let backend =
+            WgpuBackend::with_surface(gpu.clone(), surface, (size.width, size.height), options)?;
 
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
-            .await
-            .ok_or(PineError::RequestAdapterError)?;
-
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    label: None,
-                    required_features: wgpu::Features::empty(),
-                    required_limits: wgpu::Limits::downlevel_defaults()
-                        .using_resolution(adapter.limits()),
-                },
-                None,
-            )
-            .await
-            .map_err(|err| PineError::RequestDeviceError(err))?;
-
-        let surface_config =
-            Self::configure_surface(&adapter, &device, &surface, window.inner_size());
-
-        // NB: DEBUGGING
-        let scene_graph = SceneNode2D::new()
-            .with_transform(Transform::from(1., 1., 1.))
-            .add_node(SceneNode2D::new().with_transform(Transform::from(1., 2., 3.)));
+        let mut renderer = Self::from_backend(gpu, backend)?;
+        renderer.set_scale_factor(window.scale_factor());
+        Ok(renderer)
+    }
+
+    /// Constructs a new Renderer that renders into an offscreen texture instead of a window.
+    ///
+    /// Frames are rendered through the regular [`Renderer::render`] path using frame data from
+    /// [`Renderer2D::prepare_offscreen`], and can be read back with [`Renderer2D::read_pixels`].
+    pub async fn headless(config: &HeadlessConfig) -> Result<Self, PineError> {
+        let gpu = GpuContext::headless(config.force_fallback_adapter).await?;
+        Self::headless_with_gpu(Arc::new(gpu), config)
+    }
+
+    /// Constructs a new offscreen Renderer sharing an existing GPU context.
+    pub fn headless_with_gpu(
+        gpu: Arc<GpuContext>,
+        config: &HeadlessConfig,
+    ) -> Result<Self, PineError> {
+        let backend = WgpuBackend::offscreen(gpu.clone(), config.width, config.height);
+        Self::from_backend(gpu, backend)
+    }
+
+    /// Sets up the pipeline and default resources shared by all kinds of renderers.
+    fn from_backend(gpu: Arc<GpuContext>, backend: WgpuBackend) -> Result<Self, PineError> {
+        let quad_shader = WatchedShader::load(&gpu, QUAD_SHADER_PATH)?;
+        let quad_pipeline = QuadPipeline::new(&backend, quad_shader.source())?;
+        let sprite_batcher = SpriteBatcher::new(&backend);
+
+        let mut camera = Camera2D::new();
+        let (width, height) = backend.size();
+        camera.set_viewport(width, height);
 
         let renderer = Self {
-            instance,
-            adapter,
-            device,
-            queue,
-            surface_config,
-            scene_graph,
+            gpu,
+            backend,
+            quad_pipeline,
+            quad_shader,
+            post_shaders: None,
+            sprite_batcher,
+            camera,
+            scene_graph: SceneGraph::new(),
+            render_graph: default_render_graph()?,
+            post: None,
         };
         Ok(renderer)
     }
 
-    /// Configures the given surface.
-    fn configure_surface(
-        adapter: &wgpu::Adapter,
-        device: &wgpu::Device,
-        surface: &wgpu::Surface,
-        size: winit::dpi::PhysicalSize<u32>,
-    ) -> wgpu::SurfaceConfiguration {
-        let surface_caps = surface.get_capabilities(adapter);
-        if surface_caps.formats.is_empty() {
-            panic!("No texture formats found in surface capabilities")
+    /// Returns the GPU context the renderer draws with.
+    pub fn gpu(&self) -> &Arc<GpuContext> {
+        &self.gpu
+    }
+
+    /// Returns the backend the frames are drawn through.
+    pub fn backend(&self) -> &WgpuBackend {
+        &self.backend
+    }
+
+    /// Uploads an image as a texture that sprites can be drawn with.
+    ///
+    /// The texture lives in the shared GPU context, so other renderers can draw it too.
+    pub fn create_texture(
+        &self,
+        image: &image::RgbaImage,
+        options: TextureOptions,
+    ) -> TextureHandle {
+        self.gpu.create_texture(image, options)
+    }
+
+    /// Loads a PNG or JPEG file as a texture that sprites can be drawn with.
+    ///
+    /// Loading the same file twice with the same options returns the same handle.
+    pub fn load_texture(
+        &self,
+        path: impl AsRef<std::path::Path>,
+        options: TextureOptions,
+    ) -> Result<TextureHandle, PineError> {
+        self.gpu.load_texture(path, options)
+    }
+
+    /// Returns the texture cache shared through the GPU context.
+    pub fn texture_cache(&self) -> RwLockReadGuard<'_, TextureCache> {
+        self.gpu.texture_cache()
+    }
+
+    /// Returns statistics about the batches drawn in the last frame.
+    pub fn batch_stats(&self) -> BatchStats {
+        self.sprite_batcher.stats()
+    }
+
+    /// Replaces the scene graph drawn by the renderer.
+    ///
+    /// Accepts a [`SceneNode2D`](scene::SceneNode2D) as well, which becomes the root of the new
+    /// graph.
+    pub fn set_scene_graph(&mut self, scene_graph: impl Into<SceneGraph>) {
+        self.scene_graph = scene_graph.into();
+    }
+
+    /// Prepares frame data for a headless renderer.
+    ///
+    /// Counterpart to [`Renderer::prepare`] for renderers without a window.
+    pub fn prepare_offscreen(&self, clear_color: Color) -> FrameData {
+        FrameData { clear_color }
+    }
+
+    /// Reads the last rendered frame back as an RGBA image.
+    ///
+    /// Only available for headless renderers.
+    pub fn read_pixels(&self) -> Result<image::RgbaImage, PineError> {
+        self.backend.read_pixels()
+    }
+
+    /// Returns information about the adapter in use.
+    ///
+    /// Handy for checking whether a fallback adapter was picked.
+    pub fn adapter_info(&self) -> wgpu::AdapterInfo {
+        self.gpu.adapter().get_info()
+    }
+
+    /// Returns the passes the renderer records every frame.
+    ///
+    /// By default, a single [`ScenePass`] draws the scene into [`RenderGraph::OUTPUT`].
+    pub fn render_graph(&self) -> &RenderGraph {
+        &self.render_graph
+    }
+
+    /// Returns the render graph for adding or replacing passes.
+    pub fn render_graph_mut(&mut self) -> &mut RenderGraph {
+        &mut self.render_graph
+    }
+
+    /// Replaces the post-processing settings, enabling post-processing if needed.
+    pub fn set_post_processing(&mut self, settings: PostProcessing) -> Result<(), PineError> {
+        *self.enable_post_processing()? = settings;
+        Ok(())
+    }
+
+    fn frame_context(&self) -> FrameContext<'_> {
+        FrameContext {
+            scene: SceneRecorder {
+                backend: &self.backend,
+                pipeline: &self.quad_pipeline,
+                batcher: &self.sprite_batcher,
+            },
+            camera: &self.camera,
+            scene_graph: &self.scene_graph,
+            post: self.post.as_ref(),
+        }
+    }
+}
+
+impl Drop for Renderer2D {
+    /// Frees the textures of the post-processor, which live in the shared texture cache.
+    fn drop(&mut self) {
+        if let Some(post) = &self.post {
+            post.destroy(&self.backend);
         }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        backend::{BufferId, RecordingBackend},
+        pipeline::QuadInstance,
+        scene::{Renderable, SceneNode2D, Transform},
+        *,
+    };
+
+    /// A scene of the given number of rects, one unit apart.
+    fn rects(count: usize) -> SceneGraph {
+        (0..count)
+            .fold(SceneNode2D::new(), |root, i| {
+                root.add_node(
+                    SceneNode2D::new()
+                        .with_transform(Transform::from(i as f32, 0.0, 0.0))
+                        .with_renderable(Renderable::Rect {
+                            width: 1.0,
+                            height: 1.0,
+                            color: Color::RED,
+                        }),
+                )
+            })
+            .into()
+    }
+
+    /// Returns the globals buffer and the instance ranges drawn in a pass as (start, end).
+    fn bindings(commands: &CommandList) -> (BufferId, Vec<(u32, u32)>) {
+        let globals = commands
+            .commands()
+            .iter()
+            .find_map(|command| match command {
+                Command::SetUniform { group: 0, buffer } => Some(*buffer),
+                _ => None,
+            })
+            .unwrap();
+        let instances = commands
+            .commands()
+            .iter()
+            .filter_map(|command| match command {
+                Command::DrawIndexed { instances, .. } => Some((instances.start, instances.end)),
+                _ => None,
+            })
+            .collect();
+        (globals, instances)
+    }
+
+    /// Records a scene pass of the given number of rects into its own command list.
+    fn record(scene: &SceneRecorder, camera: &Camera2D, count: usize) -> (CommandList, Vec<Quad>) {
+        let mut commands = CommandList::new();
+        let quads = scene.record(
+            &mut commands,
+            camera,
+            &rects(count),
+            PassTarget::Output,
+            Color::BLACK,
+        );
+        (commands, quads)
+    }
 
-        let surface_format = surface_caps
-            .formats
+    fn instance_buffer(commands: &CommandList) -> BufferId {
+        commands
+            .commands()
             .iter()
-            .copied()
-            .find(|f| f.is_srgb())
-            .unwrap_or(surface_caps.formats[0]);
-
-        let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface_format,
-            width: size.width,
-            height: size.height,
-            present_mode: wgpu::PresentMode::Fifo, // For V-Sync. Change as needed.
-            desired_maximum_frame_latency: 2,      // Default
-            alpha_mode: surface_caps.alpha_modes[0],
-            view_formats: vec![],
+            .find_map(|command| match command {
+                Command::SetVertexBuffer { slot: 1, buffer } => Some(*buffer),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn scene_passes_of_a_frame_write_their_own_buffers() {
+        let backend = RecordingBackend::new();
+        let pipeline = QuadPipeline::new(&backend, ShaderSource::Wgsl("".into())).unwrap();
+        let batcher = SpriteBatcher::new(&backend);
+        let scene = SceneRecorder {
+            backend: &backend,
+            pipeline: &pipeline,
+            batcher: &batcher,
+        };
+        let near = Camera2D::new();
+        let far = Camera2D::new().with_zoom(0.5);
+
+        scene.begin_frame();
+        let (first, first_quads) = record(&scene, &near, 2);
+        let (second, second_quads) = record(&scene, &far, 3);
+
+        let (first_globals, first_instances) = bindings(&first);
+        let (second_globals, second_instances) = bindings(&second);
+        assert_ne!(first_globals, second_globals);
+        assert_eq!(first_instances, [(0, 2)]);
+        assert_eq!(second_instances, [(2, 5)]);
+        assert_eq!(batcher.stats().instances, 5);
+
+        // Both passes' data survives until the frame is submitted.
+        let view_proj = |buffer| {
+            let contents = backend.buffer(buffer).unwrap().contents;
+            glam::Mat4::from_cols_slice(bytemuck::cast_slice(&contents))
         };
+        assert_eq!(view_proj(first_globals), near.view_proj());
+        assert_eq!(view_proj(second_globals), far.view_proj());
+
+        let buffer = instance_buffer(&first);
+        assert_eq!(buffer, instance_buffer(&second));
+        let contents = backend.buffer(buffer).unwrap().contents;
+        let instances: &[QuadInstance] = bytemuck::cast_slice(&contents);
+        let translations: Vec<_> = first_quads
+            .iter()
+            .chain(&second_quads)
+            .map(|quad| <[f32; 2]>::from(quad.transform.translation))
+            .collect();
+        let written: Vec<_> = instances[..5]
+            .iter()
+            .map(|instance| instance.translation)
+            .collect();
+        assert_eq!(written, translations);
 
-        surface.configure(device, &config);
-        config
+        // The next frame reuses the buffers from the start.
+        scene.begin_frame();
+        let (next, _) = record(&scene, &near, 1);
+        assert_eq!(bindings(&next), (first_globals, vec![(0, 1)]));
+        assert_eq!(batcher.stats().instances, 1);
     }
 
-    /// Creates a new command encoder.
-    pub fn create_encoder(&self) -> wgpu::CommandEncoder {
-        self.device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None })
+    #[test]
+    fn passes_after_the_instance_buffer_grows_start_a_new_buffer() {
+        let backend = RecordingBackend::new();
+        let pipeline = QuadPipeline::new(&backend, ShaderSource::Wgsl("".into())).unwrap();
+        let batcher = SpriteBatcher::new(&backend);
+        let scene = SceneRecorder {
+            backend: &backend,
+            pipeline: &pipeline,
+            batcher: &batcher,
+        };
+        let camera = Camera2D::new();
+
+        scene.begin_frame();
+        let (first, _) = record(&scene, &camera, 200);
+        let (second, _) = record(&scene, &camera, 100);
+
+        assert_ne!(instance_buffer(&first), instance_buffer(&second));
+        assert_eq!(bindings(&second).1.first(), Some(&(0, 100)));
+        assert_eq!(batcher.stats().instance_capacity, 512);
+
+        // The first pass still draws from the old buffer until the frame is submitted.
+        assert!(backend.buffer(instance_buffer(&first)).is_some());
+        scene.begin_frame();
+        assert!(backend.buffer(instance_buffer(&first)).is_none());
     }
 }
@@ -0,0 +1,207 @@
+use crate::error::PineError;
+
+use std::sync::mpsc;
+
+/// The texture format used for offscreen rendering.
+///
+/// Matches the layout of `image::RgbaImage` so the read back pixels can be used as is.
+pub(crate) const OFFSCREEN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+#[derive(Debug, Clone)]
+/// The Headless config defines customizable options for building a renderer without a window.
+pub struct HeadlessConfig {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) force_fallback_adapter: bool,
+}
+
+impl Default for HeadlessConfig {
+    fn default() -> Self {
+        Self {
+            width: 500,
+            height: 500,
+            force_fallback_adapter: false,
+        }
+    }
+}
+
+impl HeadlessConfig {
+    /// Sets the size of the offscreen render target.
+    pub fn with_size(mut self, width: u32, height: u32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    /// Sets whether to force the use of a fallback (software) adapter.
+    ///
+    /// Useful on machines without a GPU, e.g. in CI.
+    pub fn with_force_fallback_adapter(mut self, force: bool) -> Self {
+        self.force_fallback_adapter = force;
+        self
+    }
+}
+
+#[derive(Debug)]
+/// A texture that can be rendered into and read back on the CPU.
+pub(crate) struct OffscreenTarget {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    readback: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+}
+
+impl OffscreenTarget {
+    /// Constructs a new offscreen target of the given size.
+    pub fn new(device: &wgpu::Device, width: u32, height: u32) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: OFFSCREEN_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        // Rows copied into a buffer must be aligned to `COPY_BYTES_PER_ROW_ALIGNMENT`.
+        let unpadded_bytes_per_row = width * 4;
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(align) * align;
+
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Offscreen readback buffer"),
+            size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            texture,
+            view,
+            readback,
+            width,
+            height,
+            padded_bytes_per_row,
+        }
+    }
+
+    /// Returns the view to render into.
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    /// Returns the size of the target as (width, height).
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Records a copy of the texture into the readback buffer.
+    ///
+    /// NB: must be submitted before calling [`OffscreenTarget::read_pixels`].
+    pub fn copy_to_buffer(&self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &self.readback,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(self.padded_bytes_per_row),
+                    rows_per_image: Some(self.height),
+                },
+            },
+            wgpu::Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    /// Reads the last copied frame back from the GPU.
+    ///
+    /// Blocks until the GPU is done with all submitted work.
+    pub fn read_pixels(&self, device: &wgpu::Device) -> Result<image::RgbaImage, PineError> {
+        let slice = self.readback.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            // The receiver only goes away if we bail out below, in which case nobody cares.
+            let _ = sender.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait);
+
+        receiver
+            .recv()
+            .map_err(|_| PineError::ReadbackError)?
+            .map_err(PineError::BufferAsyncError)?;
+
+        let unpadded_bytes_per_row = (self.width * 4) as usize;
+        let mut pixels = Vec::with_capacity(unpadded_bytes_per_row * self.height as usize);
+        {
+            let data = slice.get_mapped_range();
+            for row in data.chunks(self.padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[..unpadded_bytes_per_row]);
+            }
+        }
+        self.readback.unmap();
+
+        image::RgbaImage::from_raw(self.width, self.height, pixels).ok_or(PineError::ReadbackError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rendering::{
+        color::Color,
+        scene::{Renderable, SceneNode2D},
+        Renderer, Renderer2D,
+    };
+
+    #[test]
+    fn rendered_frames_are_read_back_without_row_padding() {
+        // Rows of 100 pixels take 400 bytes, which get padded to 512 for the copy.
+        let config = HeadlessConfig::default().with_size(100, 60);
+        assert_ne!(100 * 4 % wgpu::COPY_BYTES_PER_ROW_ALIGNMENT, 0);
+        let Ok(mut renderer) = pollster::block_on(Renderer2D::headless(&config)) else {
+            eprintln!("Skipping, no adapter available");
+            return;
+        };
+        renderer.set_scene_graph(SceneNode2D::new().with_renderable(Renderable::Rect {
+            width: 20.0,
+            height: 20.0,
+            color: Color::RED,
+        }));
+        renderer
+            .render(&renderer.prepare_offscreen(Color::BLUE))
+            .unwrap();
+
+        let frame = renderer.read_pixels().unwrap();
+        assert_eq!(frame.dimensions(), (100, 60));
+        let red = image::Rgba([255, 0, 0, 255]);
+        let blue = image::Rgba([0, 0, 255, 255]);
+        for (x, y, &pixel) in frame.enumerate_pixels() {
+            // The rect covers the 20 by 20 pixels around the center, give or take its edges.
+            let inside = (42..58).contains(&x) && (22..38).contains(&y);
+            let outside = !(38..62).contains(&x) || !(18..42).contains(&y);
+            if inside {
+                assert_eq!(pixel, red, "pixel ({}, {}) should be red", x, y);
+            } else if outside {
+                assert_eq!(pixel, blue, "pixel ({}, {}) should be blue", x, y);
+            }
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use super::{
+    backend::{
+        Backend, BindingKind, BufferDesc, BufferId, BufferUsage, Command, CommandList,
+        PipelineDesc, PipelineId, ShaderSource, StepMode, VertexFormat, VertexLayout,
+    },
+    texture::TextureHandle,
+};
+use crate::error::PineError;
+
+use std::{
+    cell::{Cell, RefCell},
+    ops::Range,
+};
+
+/// Path to the quad shader source. Read from the embedded copy if the file doesn't exist.
+pub const QUAD_SHADER_PATH: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/src/rendering/shaders/quad.wgsl"
+);
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+/// A single corner of the unit quad as laid out in the vertex buffer.
+struct QuadVertex {
+    position: [f32; 2],
+    uv: [f32; 2],
+}
+
+impl QuadVertex {
+    /// Describes the vertex buffer layout for the pipeline.
+    fn layout() -> VertexLayout {
+        VertexLayout::new(
+            StepMode::Vertex,
+            &[(0, VertexFormat::Float32x2), (1, VertexFormat::Float32x2)],
+        )
+    }
+}
+
+/// The corners of the unit quad, centered on the origin.
+const QUAD_VERTICES: [QuadVertex; 4] = [
+    QuadVertex {
+        position: [-0.5, 0.5],
+        uv: [0.0, 0.0],
+    },
+    QuadVertex {
+        position: [0.5, 0.5],
+        uv: [1.0, 0.0],
+    },
+    QuadVertex {
+        position: [0.5, -0.5],
+        uv: [1.0, 1.0],
+    },
+    QuadVertex {
+        position: [-0.5, -0.5],
+        uv: [0.0, 1.0],
+    },
+];
+
+const QUAD_INDICES: [u16; 6] = [0, 1, 2, 0, 2, 3];
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+/// Per-instance data of a quad as laid out in the instance buffer.
+pub struct QuadInstance {
+    pub x_axis: [f32; 2],
+    pub y_axis: [f32; 2],
+    pub translation: [f32; 2],
+    pub uv_rect: [f32; 4],
+    pub color: [f32; 4],
+}
+
+impl QuadInstance {
+    /// Describes the instance buffer layout for the pipeline.
+    fn layout() -> VertexLayout {
+        VertexLayout::new(
+            StepMode::Instance,
+            &[
+                (2, VertexFormat::Float32x2),
+                (3, VertexFormat::Float32x2),
+                (4, VertexFormat::Float32x2),
+                (5, VertexFormat::Float32x4),
+                (6, VertexFormat::Float32x4),
+            ],
+        )
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+/// Uniforms shared by every quad drawn in a pass.
+struct Globals {
+    view_proj: [[f32; 4]; 4],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// A quad resolved from the scene graph, ready to be drawn.
+pub struct Quad {
+    /// Maps the unit quad into world space, size included.
+    pub transform: glam::Affine2,
+    /// The texture region to sample as (min u, min v, max u, max v).
+    pub uv_rect: [f32; 4],
+    pub color: [f32; 4],
+    pub texture: TextureHandle,
+    /// Quads on lower layers are drawn first.
+    pub layer: f32,
+}
+
+impl Quad {
+    /// Converts the quad into its instance data.
+    pub fn to_instance(&self) -> QuadInstance {
+        QuadInstance {
+            x_axis: self.transform.matrix2.x_axis.into(),
+            y_axis: self.transform.matrix2.y_axis.into(),
+            translation: self.transform.translation.into(),
+            uv_rect: self.uv_rect,
+            color: self.color,
+        }
+    }
+}
+
+#[derive(Debug)]
+/// The render pipeline used for drawing colored rectangles and textured sprites.
+pub struct QuadPipeline {
+    pipeline: PipelineId,
+    vertex_buffer: BufferId,
+    index_buffer: BufferId,
+    /// One globals buffer per pass drawn in a frame, as writes all land before the frame's
+    /// commands run.
+    globals_buffers: RefCell<Vec<BufferId>>,
+    /// The number of globals buffers written this frame.
+    globals_used: Cell<usize>,
+}
+
+impl QuadPipeline {
+    /// Constructs a new quad pipeline drawing with the shader through the backend.
+    pub fn new(backend: &dyn Backend, shader: ShaderSource) -> Result<Self, PineError> {
+        let pipeline = backend.create_pipeline(&Self::desc(shader))?;
+
+        let vertex_buffer = backend.create_buffer(&BufferDesc::new(
+            "Quad vertex buffer",
+            std::mem::size_of_val(&QUAD_VERTICES) as u64,
+            BufferUsage::Vertex,
+        ));
+        backend.write_buffer(vertex_buffer, 0, bytemuck::cast_slice(&QUAD_VERTICES));
+
+        let index_buffer = backend.create_buffer(&BufferDesc::new(
+            "Quad index buffer",
+            std::mem::size_of_val(&QUAD_INDICES) as u64,
+            BufferUsage::Index,
+        ));
+        backend.write_buffer(index_buffer, 0, bytemuck::cast_slice(&QUAD_INDICES));
+
+        Ok(Self {
+            pipeline,
+            vertex_buffer,
+            index_buffer,
+            globals_buffers: RefCell::new(vec![]),
+            globals_used: Cell::new(0),
+        })
+    }
+
+    /// Swaps in a new shader, e.g. after it was reloaded.
+    ///
+    /// If the pipeline can't be created with the new shader, the previous one is kept.
+    pub fn set_shader(
+        &mut self,
+        backend: &dyn Backend,
+        shader: ShaderSource,
+    ) -> Result<(), PineError> {
+        let pipeline = backend.create_pipeline(&Self::desc(shader))?;
+        backend.destroy_pipeline(std::mem::replace(&mut self.pipeline, pipeline));
+        Ok(())
+    }
+
+    /// Describes the pipeline: the unit quad in slot 0, instances in slot 1, the globals in group
+    /// 0 and the texture in group 1.
+    fn desc(shader: ShaderSource) -> PipelineDesc {
+        PipelineDesc::new("Quad pipeline", shader)
+            .with_vertex_buffer(QuadVertex::layout())
+            .with_vertex_buffer(QuadInstance::layout())
+            .with_bind_group(BindingKind::Uniform)
+            .with_bind_group(BindingKind::Texture)
+    }
+
+    /// Starts a new frame, reusing the globals buffers of the previous one.
+    pub fn begin_frame(&self) {
+        self.globals_used.set(0);
+    }
+
+    /// Uploads the view projection matrix for a pass, returning the buffer to bind it from.
+    ///
+    /// Every call within a frame writes a buffer of its own, so passes drawing through different
+    /// cameras don't overwrite each other.
+    pub fn update_globals(&self, backend: &dyn Backend, view_proj: glam::Mat4) -> BufferId {
+        let used = self.globals_used.get();
+        let mut buffers = self.globals_buffers.borrow_mut();
+        if used == buffers.len() {
+            buffers.push(backend.create_buffer(&BufferDesc::new(
+                "Quad globals buffer",
+                std::mem::size_of::<Globals>() as u64,
+                BufferUsage::Uniform,
+            )));
+        }
+        self.globals_used.set(used + 1);
+
+        let globals = Globals {
+            view_proj: view_proj.to_cols_array_2d(),
+        };
+        backend.write_buffer(buffers[used], 0, bytemuck::bytes_of(&globals));
+        buffers[used]
+    }
+
+    /// Binds the pipeline along with the buffers shared by all batches of a pass.
+    ///
+    /// Instance data is expected in vertex buffer slot 1.
+    pub fn bind(&self, commands: &mut CommandList, globals: BufferId) {
+        commands.push(Command::SetPipeline(self.pipeline));
+        commands.push(Command::SetUniform {
+            group: 0,
+            buffer: globals,
+        });
+        commands.push(Command::SetVertexBuffer {
+            slot: 0,
+            buffer: self.vertex_buffer,
+        });
+        commands.push(Command::SetIndexBuffer(self.index_buffer));
+    }
+
+    /// Draws a range of instances from the bound instance buffer with the given texture.
+    pub fn draw_instances(
+        &self,
+        commands: &mut CommandList,
+        texture: TextureHandle,
+        instances: Range<u32>,
+    ) {
+        commands.push(Command::SetTexture { group: 1, texture });
+        commands.push(Command::DrawIndexed {
+            indices: 0..QUAD_INDICES.len() as u32,
+            instances,
+        });
+    }
+}
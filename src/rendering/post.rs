@@ -0,0 +1,789 @@
+use super::{
+    backend::{
+        Backend, BindingKind, BlendMode, BufferDesc, BufferId, BufferUsage, Command, CommandList,
+        PassTarget, PipelineDesc, PipelineId, ShaderSource, TextureFormat,
+    },
+    color::Color,
+    graph::{GraphPass, PassContext, PassDesc, RenderGraph},
+    preprocessor::{preprocess, ShaderDefs},
+    texture::TextureHandle,
+};
+use crate::error::PineError;
+
+use std::{borrow::Cow, cell::RefCell, path::Path};
+
+/// Path to the bloom shader source. Read from the embedded copy if the file doesn't exist.
+pub(crate) const BLOOM_SHADER_PATH: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/src/rendering/shaders/post/bloom.wgsl"
+);
+/// Path to the shader applying all other effects, embedded like the bloom shader.
+pub(crate) const COMPOSITE_SHADER_PATH: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/src/rendering/shaders/post/composite.wgsl"
+);
+
+/// The most textures the bloom blur goes through, each half the size of the previous one.
+const MAX_BLOOM_LEVELS: usize = 6;
+/// Bloom textures smaller than this in either dimension aren't worth blurring into.
+const MIN_BLOOM_SIZE: u32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// Makes bright parts of the image bleed light into their surroundings.
+pub struct Bloom {
+    pub enabled: bool,
+    /// How bright a color must be to bloom, from 0 to 1.
+    pub threshold: f32,
+    /// How softly colors just below the threshold fade in, as a fraction of the threshold.
+    pub knee: f32,
+    /// How strongly the blurred light is added to the image.
+    pub intensity: f32,
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold: 0.8,
+            knee: 0.5,
+            intensity: 0.6,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// Remaps colors through a lookup table, e.g. to give a scene a warmer or a washed out look.
+pub struct ColorGrading {
+    pub enabled: bool,
+    /// How much of the graded color is used, from 0 to 1.
+    pub strength: f32,
+    /// The table to look colors up in. Without one, colors are left as they are.
+    pub lut: Option<ColorLut>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// Darkens the edges of the image.
+pub struct Vignette {
+    pub enabled: bool,
+    /// How dark the edges get, from 0 to 1.
+    pub intensity: f32,
+    /// The distance from the center where darkening ends, with 1 being the corners.
+    pub radius: f32,
+    /// How far inwards from the radius the darkening fades out.
+    pub softness: f32,
+}
+
+impl Default for Vignette {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            intensity: 0.4,
+            radius: 1.0,
+            softness: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// Splits the red and blue channels apart towards the edges of the image, like a cheap lens.
+pub struct ChromaticAberration {
+    pub enabled: bool,
+    /// How far apart the channels are at the edges, in pixels.
+    pub amount: f32,
+}
+
+impl Default for ChromaticAberration {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            amount: 2.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// Imitates an old CRT screen with scanlines, an aperture grille and a curved screen.
+pub struct Crt {
+    pub enabled: bool,
+    /// How dark the gaps between scanlines are, from 0 to 1.
+    pub scanline_intensity: f32,
+    /// The number of scanlines from the top to the bottom of the screen.
+    pub scanline_count: f32,
+    /// How strongly the pixel columns are tinted red, green and blue, from 0 to 1.
+    pub mask_intensity: f32,
+    /// How curved the screen is. Zero keeps it flat.
+    pub curvature: f32,
+}
+
+impl Default for Crt {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            scanline_intensity: 0.3,
+            scanline_count: 240.0,
+            mask_intensity: 0.15,
+            curvature: 0.08,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// The post-processing effects applied to a window after the scene is drawn.
+///
+/// All effects are disabled by default and can be toggled and tweaked between frames.
+pub struct PostProcessing {
+    pub bloom: Bloom,
+    pub color_grading: ColorGrading,
+    pub vignette: Vignette,
+    pub chromatic_aberration: ChromaticAberration,
+    pub crt: Crt,
+}
+
+impl PostProcessing {
+    pub fn with_bloom(mut self, bloom: Bloom) -> Self {
+        self.bloom = bloom;
+        self
+    }
+
+    pub fn with_color_grading(mut self, color_grading: ColorGrading) -> Self {
+        self.color_grading = color_grading;
+        self
+    }
+
+    pub fn with_vignette(mut self, vignette: Vignette) -> Self {
+        self.vignette = vignette;
+        self
+    }
+
+    pub fn with_chromatic_aberration(mut self, chromatic_aberration: ChromaticAberration) -> Self {
+        self.chromatic_aberration = chromatic_aberration;
+        self
+    }
+
+    pub fn with_crt(mut self, crt: Crt) -> Self {
+        self.crt = crt;
+        self
+    }
+
+    /// Returns the parameters of the composite shader, with disabled effects zeroed out.
+    fn params(&self, size: (u32, u32)) -> PostParams {
+        let bloom = self.bloom.enabled;
+        let grading = self.color_grading.enabled && self.color_grading.lut.is_some();
+        let vignette = self.vignette.enabled;
+        let crt = self.crt.enabled;
+        let enabled_or_zero = |enabled: bool, value: f32| if enabled { value } else { 0.0 };
+
+        PostParams {
+            resolution: [size.0 as f32, size.1 as f32],
+            bloom_intensity: enabled_or_zero(bloom, self.bloom.intensity),
+            lut_strength: enabled_or_zero(grading, self.color_grading.strength),
+            vignette_intensity: enabled_or_zero(vignette, self.vignette.intensity),
+            vignette_radius: self.vignette.radius,
+            vignette_softness: self.vignette.softness,
+            aberration: enabled_or_zero(
+                self.chromatic_aberration.enabled,
+                self.chromatic_aberration.amount,
+            ),
+            crt_curvature: enabled_or_zero(crt, self.crt.curvature),
+            scanline_intensity: enabled_or_zero(crt, self.crt.scanline_intensity),
+            scanline_count: self.crt.scanline_count,
+            mask_intensity: enabled_or_zero(crt, self.crt.mask_intensity),
+            _padding: [0.0; 4],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A 3D color lookup table, uploaded as a texture.
+pub struct ColorLut {
+    texture: TextureHandle,
+}
+
+impl ColorLut {
+    /// Loads a LUT from a PNG or JPEG file.
+    ///
+    /// The image must be a horizontal strip of `size` squares of `size` by `size` pixels, red
+    /// increasing to the right and green downwards within a square, and blue from one square to
+    /// the next.
+    pub fn load(backend: &dyn Backend, path: impl AsRef<Path>) -> Result<Self, PineError> {
+        let image = image::open(path)
+            .map_err(PineError::ImageError)?
+            .into_rgba8();
+        Self::from_image(backend, &image)
+    }
+
+    /// Uploads a LUT laid out as described in [`ColorLut::load`].
+    pub fn from_image(backend: &dyn Backend, image: &image::RgbaImage) -> Result<Self, PineError> {
+        let (size, data) = lut_slices(image)?;
+        Ok(Self::from_data(backend, size, &data))
+    }
+
+    /// Creates a LUT mapping every color to itself.
+    pub fn identity(backend: &dyn Backend) -> Self {
+        let data: Vec<u8> = (0..8u8)
+            .flat_map(|i| {
+                let channel = |bit: u8| if i & bit != 0 { 255 } else { 0 };
+                [channel(1), channel(2), channel(4), 255]
+            })
+            .collect();
+        Self::from_data(backend, 2, &data)
+    }
+
+    /// Returns the 3D texture holding the table.
+    pub fn texture(&self) -> TextureHandle {
+        self.texture
+    }
+
+    fn from_data(backend: &dyn Backend, size: u32, data: &[u8]) -> Self {
+        Self {
+            texture: backend.create_texture_3d((size, size, size), data),
+        }
+    }
+}
+
+/// Stacks the squares of a LUT strip into the slices of a 3D texture, returning its size and data.
+fn lut_slices(image: &image::RgbaImage) -> Result<(u32, Vec<u8>), PineError> {
+    let (width, size) = image.dimensions();
+    if size < 2 || width != size * size {
+        return Err(PineError::LutSizeError {
+            width,
+            height: size,
+        });
+    }
+
+    let row_bytes = (size * 4) as usize;
+    let mut data = Vec::with_capacity(row_bytes * (size * size) as usize);
+    for blue in 0..size {
+        for green in 0..size {
+            let start = ((green * width + blue * size) * 4) as usize;
+            data.extend_from_slice(&image.as_raw()[start..start + row_bytes]);
+        }
+    }
+    Ok((size, data))
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+/// Uniforms of the bloom shader.
+struct BloomParams {
+    threshold: f32,
+    knee: f32,
+    _padding: [f32; 2],
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+/// Uniforms of the composite shader.
+struct PostParams {
+    resolution: [f32; 2],
+    bloom_intensity: f32,
+    lut_strength: f32,
+    vignette_intensity: f32,
+    vignette_radius: f32,
+    vignette_softness: f32,
+    aberration: f32,
+    crt_curvature: f32,
+    scanline_intensity: f32,
+    scanline_count: f32,
+    mask_intensity: f32,
+    _padding: [f32; 4],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The shaders post-processing draws with.
+pub(crate) struct PostShaders {
+    pub(crate) bloom: ShaderSource,
+    pub(crate) composite: ShaderSource,
+}
+
+impl PostShaders {
+    /// Preprocesses the built-in shaders into WGSL source.
+    pub(crate) fn preprocessed() -> Result<Self, PineError> {
+        let wgsl = |path| -> Result<ShaderSource, PineError> {
+            let shader = preprocess(path, &ShaderDefs::default())?;
+            Ok(ShaderSource::Wgsl(Cow::Owned(shader.source().to_string())))
+        };
+        Ok(Self {
+            bloom: wgsl(BLOOM_SHADER_PATH)?,
+            composite: wgsl(COMPOSITE_SHADER_PATH)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// The pipelines built from the post-processing shaders.
+struct PostPipelines {
+    threshold: PipelineId,
+    downsample: PipelineId,
+    upsample: PipelineId,
+    composite: PipelineId,
+}
+
+impl PostPipelines {
+    /// Creates the pipelines, cleaning up after itself if any of them fails.
+    fn new(
+        backend: &dyn Backend,
+        shaders: &PostShaders,
+        bloom_format: TextureFormat,
+    ) -> Result<Self, PineError> {
+        let bloom = |label, entry_point, blend| {
+            PipelineDesc::new(label, shaders.bloom.clone())
+                .with_entry_points("vs_fullscreen", entry_point)
+                .with_bind_group(BindingKind::Texture)
+                .with_bind_group(BindingKind::Uniform)
+                .with_format(bloom_format)
+                .with_blend(blend)
+        };
+        let descs = [
+            bloom(
+                "Bloom threshold pipeline",
+                "fs_threshold",
+                BlendMode::Replace,
+            ),
+            bloom(
+                "Bloom downsample pipeline",
+                "fs_downsample",
+                BlendMode::Replace,
+            ),
+            bloom(
+                "Bloom upsample pipeline",
+                "fs_upsample",
+                BlendMode::Additive,
+            ),
+            PipelineDesc::new("Composite pipeline", shaders.composite.clone())
+                .with_entry_points("vs_fullscreen", "fs_composite")
+                .with_bind_group(BindingKind::Texture)
+                .with_bind_group(BindingKind::Uniform)
+                .with_bind_group(BindingKind::Texture)
+                .with_bind_group(BindingKind::Texture3d)
+                .with_blend(BlendMode::Replace),
+        ];
+
+        let mut pipelines = Vec::with_capacity(descs.len());
+        for desc in &descs {
+            match backend.create_pipeline(desc) {
+                Ok(pipeline) => pipelines.push(pipeline),
+                Err(err) => {
+                    for pipeline in pipelines {
+                        backend.destroy_pipeline(pipeline);
+                    }
+                    return Err(err);
+                }
+            }
+        }
+        Ok(Self {
+            threshold: pipelines[0],
+            downsample: pipelines[1],
+            upsample: pipelines[2],
+            composite: pipelines[3],
+        })
+    }
+
+    fn destroy(&self, backend: &dyn Backend) {
+        for pipeline in [
+            self.threshold,
+            self.downsample,
+            self.upsample,
+            self.composite,
+        ] {
+            backend.destroy_pipeline(pipeline);
+        }
+    }
+}
+
+#[derive(Debug)]
+/// A level of the bloom blur chain.
+struct BloomLevel {
+    texture: TextureHandle,
+    size: (u32, u32),
+}
+
+#[derive(Debug)]
+/// Applies [`PostProcessing`] effects to a renderer's scene, drawing through its backend.
+pub(crate) struct PostProcessor {
+    settings: PostProcessing,
+    bloom_format: TextureFormat,
+    bloom_params: BufferId,
+    post_params: BufferId,
+    pipelines: PostPipelines,
+    /// Bound when color grading is off, as the composite shader always samples a LUT.
+    identity_lut: ColorLut,
+    bloom_levels: RefCell<Vec<BloomLevel>>,
+}
+
+impl PostProcessor {
+    /// Sets up the post-processing pipelines, drawing into the backend's output.
+    pub(crate) fn new(
+        backend: &dyn Backend,
+        shaders: &PostShaders,
+        settings: PostProcessing,
+    ) -> Result<Self, PineError> {
+        // Float textures keep bright colors from clipping, if the backend can blend them.
+        let bloom_format = if backend.supports_format(TextureFormat::Rgba16Float) {
+            TextureFormat::Rgba16Float
+        } else {
+            TextureFormat::Output
+        };
+        let pipelines = PostPipelines::new(backend, shaders, bloom_format)?;
+
+        let bloom_params = backend.create_buffer(&BufferDesc::new(
+            "Bloom params",
+            std::mem::size_of::<BloomParams>() as u64,
+            BufferUsage::Uniform,
+        ));
+        let post_params = backend.create_buffer(&BufferDesc::new(
+            "Post-processing params",
+            std::mem::size_of::<PostParams>() as u64,
+            BufferUsage::Uniform,
+        ));
+
+        Ok(Self {
+            settings,
+            bloom_format,
+            bloom_params,
+            post_params,
+            pipelines,
+            identity_lut: ColorLut::identity(backend),
+            bloom_levels: RefCell::new(vec![]),
+        })
+    }
+
+    pub(crate) fn settings(&self) -> &PostProcessing {
+        &self.settings
+    }
+
+    pub(crate) fn settings_mut(&mut self) -> &mut PostProcessing {
+        &mut self.settings
+    }
+
+    /// Rebuilds the pipelines with new shaders, e.g. after they were reloaded.
+    ///
+    /// Should the new shaders not work, the previous pipelines are kept.
+    pub(crate) fn set_shaders(
+        &mut self,
+        backend: &dyn Backend,
+        shaders: &PostShaders,
+    ) -> Result<(), PineError> {
+        let pipelines = PostPipelines::new(backend, shaders, self.bloom_format)?;
+        std::mem::replace(&mut self.pipelines, pipelines).destroy(backend);
+        Ok(())
+    }
+
+    /// Frees everything the post-processor created through the backend.
+    pub(crate) fn destroy(&self, backend: &dyn Backend) {
+        self.pipelines.destroy(backend);
+        backend.destroy_buffer(self.bloom_params);
+        backend.destroy_buffer(self.post_params);
+        backend.destroy_texture(self.identity_lut.texture);
+        for level in self.bloom_levels.borrow_mut().drain(..) {
+            backend.destroy_texture(level.texture);
+        }
+    }
+
+    /// Records the effects, reading the scene from `source` and drawing the result into `output`.
+    pub(crate) fn record(
+        &self,
+        backend: &dyn Backend,
+        commands: &mut CommandList,
+        source: TextureHandle,
+        output: PassTarget,
+        size: (u32, u32),
+    ) {
+        let settings = &self.settings;
+        backend.write_buffer(
+            self.post_params,
+            0,
+            bytemuck::bytes_of(&settings.params(size)),
+        );
+
+        let mut levels = self.bloom_levels.borrow_mut();
+        if settings.bloom.enabled {
+            backend.write_buffer(
+                self.bloom_params,
+                0,
+                bytemuck::bytes_of(&BloomParams {
+                    threshold: settings.bloom.threshold,
+                    knee: settings.bloom.knee,
+                    _padding: [0.0; 2],
+                }),
+            );
+            self.allocate_bloom_levels(backend, &mut levels, size);
+            self.record_bloom(commands, source, &levels);
+        }
+
+        // Without bloom, its intensity is zero, so any texture will do.
+        let bloom = match levels.first() {
+            Some(level) if settings.bloom.enabled => level.texture,
+            _ => TextureHandle::WHITE,
+        };
+        let lut = match &settings.color_grading.lut {
+            Some(lut) if settings.color_grading.enabled => lut,
+            _ => &self.identity_lut,
+        };
+
+        commands.push(Command::BeginPass {
+            label: "Composite pass".into(),
+            target: output,
+            clear: Some(Color::BLACK),
+        });
+        commands.push(Command::SetPipeline(self.pipelines.composite));
+        commands.push(Command::SetTexture {
+            group: 0,
+            texture: source,
+        });
+        commands.push(Command::SetUniform {
+            group: 1,
+            buffer: self.post_params,
+        });
+        commands.push(Command::SetTexture {
+            group: 2,
+            texture: bloom,
+        });
+        commands.push(Command::SetTexture {
+            group: 3,
+            texture: lut.texture,
+        });
+        commands.push(Command::Draw {
+            vertices: 0..3,
+            instances: 0..1,
+        });
+        commands.push(Command::EndPass);
+    }
+
+    /// Records the threshold, downsample and upsample passes of the bloom into its levels.
+    fn record_bloom(
+        &self,
+        commands: &mut CommandList,
+        source: TextureHandle,
+        levels: &[BloomLevel],
+    ) {
+        let mut draw = |pipeline: PipelineId,
+                        label: &'static str,
+                        from: TextureHandle,
+                        to: TextureHandle,
+                        clear: bool| {
+            commands.push(Command::BeginPass {
+                label: label.into(),
+                target: PassTarget::Texture(to),
+                clear: clear.then_some(Color::BLACK),
+            });
+            commands.push(Command::SetPipeline(pipeline));
+            commands.push(Command::SetTexture {
+                group: 0,
+                texture: from,
+            });
+            commands.push(Command::SetUniform {
+                group: 1,
+                buffer: self.bloom_params,
+            });
+            commands.push(Command::Draw {
+                vertices: 0..3,
+                instances: 0..1,
+            });
+            commands.push(Command::EndPass);
+        };
+
+        draw(
+            self.pipelines.threshold,
+            "Bloom threshold pass",
+            source,
+            levels[0].texture,
+            true,
+        );
+        for pair in levels.windows(2) {
+            draw(
+                self.pipelines.downsample,
+                "Bloom downsample pass",
+                pair[0].texture,
+                pair[1].texture,
+                true,
+            );
+        }
+        for pair in levels.windows(2).rev() {
+            draw(
+                self.pipelines.upsample,
+                "Bloom upsample pass",
+                pair[1].texture,
+                pair[0].texture,
+                false,
+            );
+        }
+    }
+
+    /// Makes sure the bloom levels match the size of the target.
+    fn allocate_bloom_levels(
+        &self,
+        backend: &dyn Backend,
+        levels: &mut Vec<BloomLevel>,
+        size: (u32, u32),
+    ) {
+        let first = ((size.0 / 2).max(1), (size.1 / 2).max(1));
+        if levels.first().is_some_and(|level| level.size == first) {
+            return;
+        }
+
+        for level in levels.drain(..) {
+            backend.destroy_texture(level.texture);
+        }
+        let mut level_size = first;
+        while levels.len() < MAX_BLOOM_LEVELS
+            && (levels.is_empty() || level_size.0.min(level_size.1) >= MIN_BLOOM_SIZE)
+        {
+            levels.push(BloomLevel {
+                texture: backend.create_render_texture(
+                    "Bloom texture",
+                    level_size,
+                    self.bloom_format,
+                ),
+                size: level_size,
+            });
+            level_size = ((level_size.0 / 2).max(1), (level_size.1 / 2).max(1));
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+/// Applies the renderer's [`PostProcessing`] to the scene on its way to the output.
+pub struct PostProcessPass;
+
+impl PostProcessPass {
+    /// The name of the pass in the graph.
+    pub const NAME: &'static str = "post_processing";
+    /// The texture the scene is drawn into before being post-processed.
+    pub const SOURCE: &'static str = "scene";
+
+    /// Returns the description of the pass, reading the scene and writing the output.
+    pub fn desc(&self) -> PassDesc {
+        PassDesc::new(Self::NAME)
+            .with_read(Self::SOURCE)
+            .with_write(RenderGraph::OUTPUT)
+    }
+}
+
+impl GraphPass for PostProcessPass {
+    fn run(&self, context: &mut PassContext) -> Result<(), PineError> {
+        let source = context.read(Self::SOURCE)?;
+        let output = context.write(RenderGraph::OUTPUT)?;
+        context.post_process(source, output);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rendering::backend::RecordingBackend;
+
+    /// Post-processing with every effect set up, toggled by the bits of `toggles`.
+    fn post_processing(toggles: u32, lut: Option<ColorLut>) -> PostProcessing {
+        let enabled = |bit: u32| toggles & (1 << bit) != 0;
+        PostProcessing::default()
+            .with_bloom(Bloom {
+                enabled: enabled(0),
+                ..Default::default()
+            })
+            .with_color_grading(ColorGrading {
+                enabled: enabled(1),
+                strength: 0.75,
+                lut,
+            })
+            .with_vignette(Vignette {
+                enabled: enabled(2),
+                ..Default::default()
+            })
+            .with_chromatic_aberration(ChromaticAberration {
+                enabled: enabled(3),
+                ..Default::default()
+            })
+            .with_crt(Crt {
+                enabled: enabled(4),
+                ..Default::default()
+            })
+    }
+
+    #[test]
+    fn disabled_effects_are_zeroed() {
+        for toggles in 0..32 {
+            let post = post_processing(toggles, None);
+            let params = post.params((320, 240));
+            let expect = |enabled: bool, value: f32| if enabled { value } else { 0.0 };
+
+            assert_eq!(params.resolution, [320.0, 240.0]);
+            assert_eq!(
+                params.bloom_intensity,
+                expect(post.bloom.enabled, post.bloom.intensity)
+            );
+            // Without a LUT there's nothing to grade with, enabled or not.
+            assert_eq!(params.lut_strength, 0.0);
+            assert_eq!(
+                params.vignette_intensity,
+                expect(post.vignette.enabled, post.vignette.intensity)
+            );
+            assert_eq!(
+                params.aberration,
+                expect(
+                    post.chromatic_aberration.enabled,
+                    post.chromatic_aberration.amount
+                )
+            );
+            assert_eq!(
+                params.crt_curvature,
+                expect(post.crt.enabled, post.crt.curvature)
+            );
+            assert_eq!(
+                params.scanline_intensity,
+                expect(post.crt.enabled, post.crt.scanline_intensity)
+            );
+            assert_eq!(
+                params.mask_intensity,
+                expect(post.crt.enabled, post.crt.mask_intensity)
+            );
+        }
+    }
+
+    #[test]
+    fn grading_needs_a_lut() {
+        let lut = ColorLut::identity(&RecordingBackend::new());
+
+        let params = post_processing(0b10, Some(lut)).params((1, 1));
+        assert_eq!(params.lut_strength, 0.75);
+        let params = post_processing(0, Some(lut)).params((1, 1));
+        assert_eq!(params.lut_strength, 0.0);
+    }
+
+    #[test]
+    fn lut_strips_are_stacked_into_slices() {
+        // Every pixel of a 4x4x4 strip holds its own coordinates.
+        let strip =
+            image::RgbaImage::from_fn(16, 4, |x, y| image::Rgba([x as u8, y as u8, 0, 255]));
+        let (size, data) = lut_slices(&strip).unwrap();
+        assert_eq!(size, 4);
+        assert_eq!(data.len(), 4 * 4 * 4 * 4);
+
+        for blue in 0..4 {
+            for green in 0..4 {
+                for red in 0..4 {
+                    let texel = (((blue * 4 + green) * 4 + red) * 4) as usize;
+                    assert_eq!(
+                        data[texel..texel + 4],
+                        [(blue * 4 + red) as u8, green as u8, 0, 255],
+                        "texel ({}, {}, {})",
+                        red,
+                        green,
+                        blue
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn luts_must_be_strips_of_squares() {
+        for (width, height) in [(15, 4), (16, 5), (1, 1)] {
+            assert!(matches!(
+                lut_slices(&image::RgbaImage::new(width, height)),
+                Err(PineError::LutSizeError { .. })
+            ));
+        }
+    }
+}
@@ -0,0 +1,548 @@
+use super::shaders::builtin_source;
+use crate::error::PineError;
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+/// Definitions toggling features of a shader, resolved by the preprocessor at load time.
+///
+/// The same file loaded with different definitions compiles into separate shader variants.
+pub struct ShaderDefs(BTreeMap<String, String>);
+
+impl ShaderDefs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Defines a flag that can be checked with `#ifdef`.
+    pub fn with_flag(mut self, name: &str) -> Self {
+        self.define(name, "");
+        self
+    }
+
+    /// Defines a value that replaces the name wherever it appears in the shader.
+    pub fn with_value(mut self, name: &str, value: impl ToString) -> Self {
+        self.define(name, value);
+        self
+    }
+
+    pub fn define(&mut self, name: &str, value: impl ToString) {
+        self.0.insert(name.to_string(), value.to_string());
+    }
+
+    pub fn undefine(&mut self, name: &str) {
+        self.0.remove(name);
+    }
+
+    pub fn is_defined(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+}
+
+#[derive(Debug, Clone)]
+/// WGSL source with all directives resolved, remembering where each line came from.
+pub struct PreprocessedShader {
+    source: String,
+    /// Every file that ended up in the source, the root file first.
+    files: Vec<PathBuf>,
+    /// Where every line of the source came from.
+    lines: Vec<LineOrigin>,
+}
+
+#[derive(Debug, Clone)]
+/// Where a line of the preprocessed source came from.
+struct LineOrigin {
+    /// The index of the file in the shader's files.
+    file: usize,
+    /// The 1-based line in the file.
+    line: usize,
+    /// The line as written, if substituting values changed it.
+    original: Option<String>,
+    substitutions: Vec<Substitution>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A name replaced with its value, with 0-based columns in chars.
+struct Substitution {
+    /// Where the value starts in the preprocessed line.
+    column: usize,
+    value_len: usize,
+    /// Where the name starts in the line as written.
+    original_column: usize,
+    name_len: usize,
+}
+
+impl PreprocessedShader {
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the files the source was assembled from, the root file first.
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    /// Maps a 1-based line of the preprocessed source back to its file and line.
+    pub fn original_location(&self, line: usize) -> Option<(&Path, usize)> {
+        let origin = self.origin(line)?;
+        Some((&self.files[origin.file], origin.line))
+    }
+
+    /// Returns a 1-based line of the preprocessed source as it was written, before values were
+    /// substituted.
+    pub fn original_line(&self, line: usize) -> Option<&str> {
+        match &self.origin(line)?.original {
+            Some(original) => Some(original),
+            None => self.source.lines().nth(line - 1),
+        }
+    }
+
+    /// Maps a 1-based column in chars of a line of the preprocessed source back to the line as
+    /// written. Columns within a substituted value map to the start of its name.
+    pub fn original_column(&self, line: usize, column: usize) -> Option<usize> {
+        let column = column.checked_sub(1)?;
+        let mut original_column = column;
+        for substitution in &self.origin(line)?.substitutions {
+            let value_end = substitution.column + substitution.value_len;
+            if column < substitution.column {
+                break;
+            } else if column < value_end {
+                original_column = substitution.original_column;
+                break;
+            }
+            original_column =
+                substitution.original_column + substitution.name_len + (column - value_end);
+        }
+        Some(original_column + 1)
+    }
+
+    fn origin(&self, line: usize) -> Option<&LineOrigin> {
+        self.lines.get(line.checked_sub(1)?)
+    }
+}
+
+/// Resolves `#include`, `#define`, `#undef`, `#ifdef`, `#ifndef`, `#else` and `#endif` directives.
+///
+/// Includes are relative to the including file and each file is only included once, so shared
+/// helpers can be included from several places. Including a file from itself, directly or not, is
+/// an error. Built-in shaders that can't be read from disk are taken from their embedded copies.
+pub fn preprocess(
+    path: impl AsRef<Path>,
+    defs: &ShaderDefs,
+) -> Result<PreprocessedShader, PineError> {
+    let mut preprocessor = Preprocessor {
+        defs: defs.clone(),
+        output: PreprocessedShader {
+            source: String::new(),
+            files: vec![],
+            lines: vec![],
+        },
+        stack: vec![],
+    };
+    preprocessor.process_file(path.as_ref())?;
+    Ok(preprocessor.output)
+}
+
+/// Returns the canonical form of a path.
+///
+/// Paths that don't exist, like those of embedded built-in shaders off the build machine, only
+/// have `.` and `..` resolved.
+pub(crate) fn canonicalize(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| {
+        let mut normalized = PathBuf::new();
+        for component in path.components() {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir if normalized.pop() => {}
+                component => normalized.push(component),
+            }
+        }
+        normalized
+    })
+}
+
+/// Reads a shader file, falling back to the embedded copy of built-in shaders.
+fn read_source(path: &Path) -> Result<String, PineError> {
+    fs::read_to_string(path).or_else(|source| {
+        builtin_source(path)
+            .map(str::to_string)
+            .ok_or_else(|| PineError::LoadShaderError {
+                path: path.to_path_buf(),
+                source,
+            })
+    })
+}
+
+#[derive(Debug)]
+/// A conditional block the preprocessor is in.
+struct Conditional {
+    /// Whether the lines of the current branch are kept.
+    active: bool,
+    /// Whether the enclosing block was active.
+    parent_active: bool,
+    line: usize,
+    seen_else: bool,
+}
+
+#[derive(Debug)]
+struct Preprocessor {
+    defs: ShaderDefs,
+    output: PreprocessedShader,
+    /// The files currently being processed, used to detect include cycles.
+    stack: Vec<PathBuf>,
+}
+
+impl Preprocessor {
+    fn process_file(&mut self, path: &Path) -> Result<(), PineError> {
+        // Canonical paths make "a/../b.wgsl" and "b.wgsl" the same file.
+        let path = canonicalize(path);
+
+        if self.stack.contains(&path) {
+            let mut chain = self.stack.clone();
+            chain.push(path);
+            return Err(PineError::ShaderIncludeCycleError(chain));
+        }
+        if self.output.files.contains(&path) {
+            return Ok(());
+        }
+
+        let source = read_source(&path)?;
+        let file = self.output.files.len();
+        self.output.files.push(path.clone());
+        self.stack.push(path.clone());
+
+        let mut conditionals: Vec<Conditional> = vec![];
+        for (i, line) in source.lines().enumerate() {
+            let line_number = i + 1;
+            let error = |message: String| PineError::ShaderPreprocessError {
+                path: path.clone(),
+                line: line_number,
+                message,
+            };
+            let active = conditionals.last().is_none_or(|c| c.active);
+
+            let Some(directive) = line.trim_start().strip_prefix('#') else {
+                if active {
+                    let (substituted, substitutions) = self.substitute(line);
+                    let origin = LineOrigin {
+                        file,
+                        line: line_number,
+                        original: (!substitutions.is_empty()).then(|| line.to_string()),
+                        substitutions,
+                    };
+                    self.push_line(&substituted, origin);
+                }
+                continue;
+            };
+
+            let mut parts = directive.split_whitespace();
+            let name = parts.next().unwrap_or_default();
+            let argument = parts.next();
+            let rest = parts.collect::<Vec<_>>().join(" ");
+
+            match (name, argument) {
+                ("ifdef" | "ifndef", Some(def)) => {
+                    let defined = self.defs.is_defined(def);
+                    conditionals.push(Conditional {
+                        active: active && defined == (name == "ifdef"),
+                        parent_active: active,
+                        line: line_number,
+                        seen_else: false,
+                    });
+                }
+                ("else", None) => match conditionals.last_mut() {
+                    Some(conditional) if !conditional.seen_else => {
+                        conditional.seen_else = true;
+                        conditional.active = conditional.parent_active && !conditional.active;
+                    }
+                    Some(_) => return Err(error("#else after #else".to_string())),
+                    None => return Err(error("#else without #ifdef".to_string())),
+                },
+                ("endif", None) => {
+                    if conditionals.pop().is_none() {
+                        return Err(error("#endif without #ifdef".to_string()));
+                    }
+                }
+                _ if !active => {}
+                ("define", Some(def)) => self.defs.define(def, rest),
+                ("undef", Some(def)) => self.defs.undefine(def),
+                ("include", Some(_)) => {
+                    let include = directive.trim_start()["include".len()..].trim();
+                    let include = include
+                        .strip_prefix('"')
+                        .and_then(|include| include.strip_suffix('"'))
+                        .ok_or_else(|| {
+                            error("expected a quoted path after #include".to_string())
+                        })?;
+                    let include_path = path
+                        .parent()
+                        .map_or_else(|| PathBuf::from(include), |dir| dir.join(include));
+                    self.process_file(&include_path).map_err(|err| match err {
+                        PineError::LoadShaderError { path, source } => {
+                            error(format!("failed to include {}: {}", path.display(), source))
+                        }
+                        err => err,
+                    })?;
+                }
+                _ => return Err(error(format!("invalid directive #{}", directive.trim()))),
+            }
+        }
+
+        if let Some(conditional) = conditionals.last() {
+            return Err(PineError::ShaderPreprocessError {
+                path,
+                line: conditional.line,
+                message: "#ifdef without #endif".to_string(),
+            });
+        }
+
+        self.stack.pop();
+        Ok(())
+    }
+
+    fn push_line(&mut self, line: &str, origin: LineOrigin) {
+        self.output.source.push_str(line);
+        self.output.source.push('\n');
+        self.output.lines.push(origin);
+    }
+
+    /// Replaces the names of defined values in the line with their values.
+    ///
+    /// Tokens starting with a digit are literals like `1u`, `2.0f` or `0x1F`, whose suffixes
+    /// aren't names.
+    fn substitute(&self, line: &str) -> (String, Vec<Substitution>) {
+        let is_word = |c: char| c.is_ascii_alphanumeric() || c == '_';
+        let mut out = String::with_capacity(line.len());
+        let mut substitutions = vec![];
+        // Columns in chars, as naga reports them.
+        let (mut column, mut original_column) = (0, 0);
+        let mut rest = line;
+        while let Some(start) = rest.find(is_word) {
+            let gap = &rest[..start];
+            out.push_str(gap);
+            column += gap.chars().count();
+            original_column += gap.chars().count();
+
+            rest = &rest[start..];
+            let end = rest.find(|c: char| !is_word(c)).unwrap_or(rest.len());
+            let word = &rest[..end];
+            let value = match self.defs.get(word) {
+                Some(value)
+                    if !value.is_empty() && !word.starts_with(|c: char| c.is_ascii_digit()) =>
+                {
+                    substitutions.push(Substitution {
+                        column,
+                        value_len: value.chars().count(),
+                        original_column,
+                        name_len: word.len(),
+                    });
+                    value
+                }
+                _ => word,
+            };
+            out.push_str(value);
+            column += value.chars().count();
+            original_column += word.len();
+            rest = &rest[end..];
+        }
+        out.push_str(rest);
+        (out, substitutions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes the files into a fresh temporary directory, returning it.
+    fn write_files(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("pine-preprocessor-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for (path, source) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, source).unwrap();
+        }
+        dir
+    }
+
+    /// Preprocesses the first of the files, then removes them.
+    fn preprocess_files(
+        name: &str,
+        files: &[(&str, &str)],
+        defs: &ShaderDefs,
+    ) -> Result<PreprocessedShader, PineError> {
+        let dir = write_files(name, files);
+        let shader = preprocess(dir.join(files[0].0), defs);
+        fs::remove_dir_all(&dir).unwrap();
+        shader
+    }
+
+    fn lines(shader: &PreprocessedShader) -> Vec<&str> {
+        shader.source().lines().collect()
+    }
+
+    #[test]
+    fn include_cycles_are_errors() {
+        let err = preprocess_files(
+            "cycle",
+            &[
+                ("a.wgsl", "#include \"b.wgsl\"\n"),
+                ("b.wgsl", "#include \"nested/c.wgsl\"\n"),
+                ("nested/c.wgsl", "#include \"../a.wgsl\"\n"),
+            ],
+            &ShaderDefs::default(),
+        )
+        .unwrap_err();
+
+        let PineError::ShaderIncludeCycleError(chain) = err else {
+            panic!("expected an include cycle, got {:?}", err);
+        };
+        let names: Vec<_> = chain
+            .iter()
+            .map(|path| path.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(names, ["a.wgsl", "b.wgsl", "c.wgsl", "a.wgsl"]);
+    }
+
+    #[test]
+    fn files_are_included_once() {
+        let shader = preprocess_files(
+            "once",
+            &[
+                (
+                    "main.wgsl",
+                    "#include \"common.wgsl\"\n#include \"lib/helpers.wgsl\"\nmain\n",
+                ),
+                ("common.wgsl", "common\n"),
+                ("lib/helpers.wgsl", "#include \"../common.wgsl\"\nhelpers\n"),
+            ],
+            &ShaderDefs::default(),
+        )
+        .unwrap();
+
+        assert_eq!(lines(&shader), ["common", "helpers", "main"]);
+        assert_eq!(shader.files().len(), 3);
+    }
+
+    #[test]
+    fn nested_conditionals_keep_the_active_branches() {
+        let source = "\
+#ifdef OUTER
+#ifdef INNER
+outer and inner
+#else
+outer only
+#endif
+#else
+#ifndef INNER
+neither
+#else
+inner only
+#endif
+#endif
+";
+        let files = [("main.wgsl", source)];
+        let run = |defs: ShaderDefs| {
+            let shader = preprocess_files("conditionals", &files, &defs).unwrap();
+            lines(&shader).join("\n")
+        };
+
+        let outer = ShaderDefs::new().with_flag("OUTER");
+        let inner = ShaderDefs::new().with_flag("INNER");
+        assert_eq!(run(outer.clone().with_flag("INNER")), "outer and inner");
+        assert_eq!(run(outer), "outer only");
+        assert_eq!(run(inner), "inner only");
+        assert_eq!(run(ShaderDefs::new()), "neither");
+    }
+
+    #[test]
+    fn unbalanced_conditionals_are_errors() {
+        for (source, line) in [
+            ("#ifdef A\n", 1),
+            ("#endif\n", 1),
+            ("#ifdef A\n#else\n#else\n#endif\n", 3),
+        ] {
+            let err = preprocess_files(
+                "unbalanced",
+                &[("main.wgsl", source)],
+                &ShaderDefs::default(),
+            )
+            .unwrap_err();
+            assert!(
+                matches!(err, PineError::ShaderPreprocessError { line: l, .. } if l == line),
+                "{:?} for {:?}",
+                err,
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn lines_map_back_to_their_files() {
+        let shader = preprocess_files(
+            "lines",
+            &[
+                (
+                    "main.wgsl",
+                    "first\n#include \"inc.wgsl\"\n#ifdef MISSING\nskipped\n#endif\nlast\n",
+                ),
+                ("inc.wgsl", "// comment\nincluded\n"),
+            ],
+            &ShaderDefs::default(),
+        )
+        .unwrap();
+
+        assert_eq!(lines(&shader), ["first", "// comment", "included", "last"]);
+        let location = |line| {
+            let (path, line) = shader.original_location(line).unwrap();
+            (path.file_name().unwrap().to_str().unwrap(), line)
+        };
+        assert_eq!(location(1), ("main.wgsl", 1));
+        assert_eq!(location(2), ("inc.wgsl", 1));
+        assert_eq!(location(3), ("inc.wgsl", 2));
+        assert_eq!(location(4), ("main.wgsl", 6));
+        assert!(shader.original_location(5).is_none());
+        assert!(shader.original_location(0).is_none());
+    }
+
+    #[test]
+    fn values_are_not_substituted_into_literals() {
+        let defs = ShaderDefs::new()
+            .with_value("u", "bad")
+            .with_value("f", "bad")
+            .with_value("x1F", "bad")
+            .with_value("COUNT", 4);
+        let shader = preprocess_files(
+            "literals",
+            &[("main.wgsl", "let a = 1u + 2.0f + 0x1F + COUNT + u;\n")],
+            &defs,
+        )
+        .unwrap();
+
+        assert_eq!(lines(&shader), ["let a = 1u + 2.0f + 0x1F + 4 + bad;"]);
+    }
+
+    #[test]
+    fn columns_map_back_across_substitutions() {
+        let defs = ShaderDefs::new().with_value("N", "1024u");
+        let shader = preprocess_files("columns", &[("main.wgsl", "a(N, N) + b\n")], &defs).unwrap();
+
+        assert_eq!(lines(&shader), ["a(1024u, 1024u) + b"]);
+        assert_eq!(shader.original_line(1), Some("a(N, N) + b"));
+        // Before, within and after substituted values.
+        assert_eq!(shader.original_column(1, 2), Some(2));
+        assert_eq!(shader.original_column(1, 5), Some(3));
+        assert_eq!(shader.original_column(1, 10), Some(6));
+        assert_eq!(shader.original_column(1, 19), Some(11));
+    }
+}
@@ -0,0 +1,641 @@
+use super::{
+    add_post_processing,
+    backend::{Backend, Command, PassTarget, RecordingBackend, ShaderSource},
+    batch::SpriteBatcher,
+    camera::Camera2D,
+    color::Color,
+    default_render_graph,
+    frame_data::{FrameData, FrameDataBuilder},
+    graph::RenderGraph,
+    pipeline::{Quad, QuadPipeline, QUAD_SHADER_PATH},
+    post::{PostProcessing, PostProcessor, PostShaders},
+    preprocessor::{preprocess, ShaderDefs},
+    scene_graph::SceneGraph,
+    FrameCommands, FrameContext, Renderer, SceneRecorder,
+};
+use crate::{error::PineError, windowing::Window};
+
+use std::{
+    borrow::Cow,
+    collections::VecDeque,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+/// A pass recorded by a [`RecordingRenderer`].
+pub struct RecordedPass {
+    pub label: Cow<'static, str>,
+    /// What the pass draws into.
+    pub target: PassTarget,
+    /// The color the target was cleared with, if it was.
+    pub clear: Option<Color>,
+    /// The commands issued between beginning and ending the pass.
+    pub commands: Vec<Command>,
+    /// The quads drawn in the pass in draw order, with their transforms resolved to world space.
+    pub sprites: Vec<Quad>,
+}
+
+impl RecordedPass {
+    /// Returns the number of draw calls issued in the pass.
+    pub fn draw_calls(&self) -> usize {
+        self.commands
+            .iter()
+            .filter(|command| matches!(command, Command::Draw { .. } | Command::DrawIndexed { .. }))
+            .count()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Everything a [`RecordingRenderer`] drew in one frame.
+pub struct RecordedFrame {
+    /// The number of frames recorded before this one.
+    pub index: u64,
+    pub clear_color: Color,
+    /// The size of the target as (width, height).
+    pub size: (u32, u32),
+    /// The view projection matrix of the camera the frame was drawn through.
+    pub view_proj: glam::Mat4,
+    pub passes: Vec<RecordedPass>,
+}
+
+impl RecordedFrame {
+    /// Returns the first pass with the given label.
+    pub fn pass(&self, label: &str) -> Option<&RecordedPass> {
+        self.passes.iter().find(|pass| pass.label == label)
+    }
+
+    /// Iterates over the quads drawn across all passes, in draw order.
+    pub fn sprites(&self) -> impl Iterator<Item = &Quad> {
+        self.passes.iter().flat_map(|pass| &pass.sprites)
+    }
+
+    /// Returns the number of draw calls issued across all passes.
+    pub fn draw_calls(&self) -> usize {
+        self.passes.iter().map(RecordedPass::draw_calls).sum()
+    }
+
+    /// Splits the commands of a frame into passes, along with the quads drawn by its scene passes.
+    ///
+    /// The index is assigned by the recorder.
+    fn new(
+        clear_color: Color,
+        size: (u32, u32),
+        view_proj: glam::Mat4,
+        frame: &FrameCommands,
+    ) -> Self {
+        let mut passes = vec![];
+        let mut current: Option<RecordedPass> = None;
+        for (i, command) in frame.commands.commands().iter().enumerate() {
+            match command {
+                Command::BeginPass {
+                    label,
+                    target,
+                    clear,
+                } => {
+                    passes.extend(current.take());
+                    current = Some(RecordedPass {
+                        label: label.clone(),
+                        target: *target,
+                        clear: *clear,
+                        commands: vec![],
+                        sprites: frame.sprites.get(&i).cloned().unwrap_or_default(),
+                    });
+                }
+                Command::EndPass => passes.extend(current.take()),
+                command => {
+                    let Some(pass) = &mut current else {
+                        tracing::warn!("Not recording {:?} outside of a pass", command);
+                        continue;
+                    };
+                    pass.commands.push(command.clone());
+                }
+            }
+        }
+        passes.extend(current);
+
+        Self {
+            index: 0,
+            clear_color,
+            size,
+            view_proj,
+            passes,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct RecorderState {
+    frames: VecDeque<RecordedFrame>,
+    max_frames: Option<usize>,
+    /// The number of frames recorded so far, including those dropped or taken.
+    recorded: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+/// The frames recorded by a [`RecordingRenderer`].
+///
+/// Clones share the same frames, so a test can keep a recorder while the renderer is moved into
+/// a window with [`WindowConfig::with_recorder`](crate::windowing::WindowConfig::with_recorder).
+pub struct FrameRecorder {
+    state: Arc<Mutex<RecorderState>>,
+}
+
+impl FrameRecorder {
+    /// Constructs a recorder keeping every frame until they're taken.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only keeps the given number of most recent frames, e.g. for recorders left running.
+    pub fn with_max_frames(self, max_frames: usize) -> Self {
+        {
+            let mut state = self.state();
+            state.max_frames = Some(max_frames);
+            Self::truncate(&mut state);
+        }
+        self
+    }
+
+    /// Returns the frames kept, oldest first.
+    pub fn frames(&self) -> Vec<RecordedFrame> {
+        self.state().frames.iter().cloned().collect()
+    }
+
+    /// Returns the most recent frame.
+    pub fn last_frame(&self) -> Option<RecordedFrame> {
+        self.state().frames.back().cloned()
+    }
+
+    /// Returns the frames kept, oldest first, and forgets them.
+    pub fn take_frames(&self) -> Vec<RecordedFrame> {
+        self.state().frames.drain(..).collect()
+    }
+
+    /// Returns the number of frames kept.
+    pub fn len(&self) -> usize {
+        self.state().frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of frames recorded so far, including those no longer kept.
+    pub fn recorded(&self) -> u64 {
+        self.state().recorded
+    }
+
+    /// Forgets all frames kept.
+    pub fn clear(&self) {
+        self.state().frames.clear();
+    }
+
+    /// Numbers and keeps a frame, dropping the oldest ones if there are too many.
+    fn push(&self, mut frame: RecordedFrame) -> RecordedFrame {
+        let mut state = self.state();
+        frame.index = state.recorded;
+        state.recorded += 1;
+        state.frames.push_back(frame.clone());
+        Self::truncate(&mut state);
+        frame
+    }
+
+    fn truncate(state: &mut RecorderState) {
+        if let Some(max_frames) = state.max_frames {
+            let excess = state.frames.len().saturating_sub(max_frames);
+            state.frames.drain(..excess);
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, RecorderState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[derive(Debug)]
+/// A renderer that records what it would draw instead of drawing it.
+///
+/// Every rendered frame is kept in a [`FrameRecorder`]: its clear color, the passes of its render
+/// graph and their commands, and the quads drawn resolved from the scene graph. Post-processing
+/// is recorded like any other pass. Needs no GPU, so tests can assert on exactly what a frame
+/// drew.
+///
+/// # Example
+///
+/// ```
+/// # use pine::prelude::*;
+/// # use pine::rendering::scene::{Renderable, SceneNode2D, Transform};
+/// let recorder = FrameRecorder::new();
+/// let mut renderer = RecordingRenderer::new(200, 200, recorder.clone())?;
+/// renderer.set_scene_graph(
+///     SceneNode2D::new()
+///         .with_transform(Transform::from(10.0, 20.0, 0.0))
+///         .with_renderable(Renderable::Rect {
+///             width: 4.0,
+///             height: 4.0,
+///             color: Color::RED,
+///         }),
+/// );
+/// renderer.record(Color::BLACK)?;
+///
+/// let frame = recorder.last_frame().unwrap();
+/// let sprites: Vec<_> = frame.sprites().collect();
+/// assert_eq!(sprites.len(), 1);
+/// assert_eq!(sprites[0].transform.translation, glam::Vec2::new(10.0, 20.0));
+/// # Ok::<(), PineError>(())
+/// ```
+pub struct RecordingRenderer {
+    backend: RecordingBackend,
+    quad_pipeline: QuadPipeline,
+    sprite_batcher: SpriteBatcher,
+    camera: Camera2D,
+    scene_graph: SceneGraph,
+    render_graph: RenderGraph,
+    post: Option<PostProcessor>,
+    recorder: FrameRecorder,
+    size: (u32, u32),
+}
+
+impl Renderer for RecordingRenderer {
+    fn prepare(&self, window: &Window) -> Result<FrameData, PineError> {
+        FrameDataBuilder::default()
+            .with_clear_color(window.clear_color)
+            .build()
+    }
+
+    fn render(&self, frame_data: &FrameData) -> Result<(), PineError> {
+        self.record(frame_data.clear_color)?;
+        Ok(())
+    }
+
+    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        if new_size.width > 0 && new_size.height > 0 {
+            self.size = (new_size.width, new_size.height);
+            self.camera.set_viewport(new_size.width, new_size.height);
+        }
+    }
+
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.camera.set_scale_factor(scale_factor);
+    }
+
+    fn capture(&self) -> Result<image::RgbaImage, PineError> {
+        Err(PineError::ReadbackError)
+    }
+
+    fn camera(&self) -> &Camera2D {
+        &self.camera
+    }
+
+    fn camera_mut(&mut self) -> &mut Camera2D {
+        &mut self.camera
+    }
+
+    fn scene_graph(&self) -> &SceneGraph {
+        &self.scene_graph
+    }
+
+    fn scene_graph_mut(&mut self) -> &mut SceneGraph {
+        &mut self.scene_graph
+    }
+
+    fn post_processing(&self) -> Option<&PostProcessing> {
+        self.post.as_ref().map(PostProcessor::settings)
+    }
+
+    fn post_processing_mut(&mut self) -> Option<&mut PostProcessing> {
+        self.post.as_mut().map(PostProcessor::settings_mut)
+    }
+
+    /// Sets up post-processing like [`Renderer2D`](super::Renderer2D) does, recording its
+    /// passes with the preprocessed shaders.
+    fn enable_post_processing(&mut self) -> Result<&mut PostProcessing, PineError> {
+        let post = match self.post.take() {
+            Some(post) => post,
+            None => add_post_processing(
+                &mut self.render_graph,
+                &self.backend,
+                &PostShaders::preprocessed()?,
+            )?,
+        };
+        Ok(self.post.insert(post).settings_mut())
+    }
+}
+
+impl RecordingRenderer {
+    /// Constructs a renderer recording frames of the given size into the recorder.
+    pub fn new(width: u32, height: u32, recorder: FrameRecorder) -> Result<Self, PineError> {
+        let backend = RecordingBackend::new();
+        // Only kept in the pipeline description, as nothing is compiled.
+        let source = preprocess(QUAD_SHADER_PATH, &ShaderDefs::default())?;
+        let shader = ShaderSource::Wgsl(Cow::Owned(source.source().to_string()));
+        let quad_pipeline = QuadPipeline::new(&backend, shader)?;
+        let sprite_batcher = SpriteBatcher::new(&backend);
+
+        let mut camera = Camera2D::new();
+        camera.set_viewport(width, height);
+
+        Ok(Self {
+            backend,
+            quad_pipeline,
+            sprite_batcher,
+            camera,
+            scene_graph: SceneGraph::new(),
+            render_graph: default_render_graph()?,
+            post: None,
+            recorder,
+            size: (width, height),
+        })
+    }
+
+    /// Returns the recorder frames are recorded into.
+    pub fn recorder(&self) -> &FrameRecorder {
+        &self.recorder
+    }
+
+    /// Returns the backend the commands are submitted to.
+    pub fn backend(&self) -> &RecordingBackend {
+        &self.backend
+    }
+
+    /// Replaces the scene graph drawn by the renderer.
+    pub fn set_scene_graph(&mut self, scene_graph: impl Into<SceneGraph>) {
+        self.scene_graph = scene_graph.into();
+    }
+
+    /// Returns the passes the renderer records every frame.
+    ///
+    /// By default, a single [`ScenePass`](super::graph::ScenePass) draws the scene into
+    /// [`RenderGraph::OUTPUT`].
+    pub fn render_graph(&self) -> &RenderGraph {
+        &self.render_graph
+    }
+
+    /// Returns the render graph for adding or replacing passes.
+    pub fn render_graph_mut(&mut self) -> &mut RenderGraph {
+        &mut self.render_graph
+    }
+
+    /// Replaces the post-processing settings, enabling post-processing if needed.
+    pub fn set_post_processing(&mut self, settings: PostProcessing) -> Result<(), PineError> {
+        *self.enable_post_processing()? = settings;
+        Ok(())
+    }
+
+    /// Records a frame of the render graph cleared with the given color.
+    ///
+    /// Counterpart to [`Renderer::render`] for renderers without a window. The frame is kept in
+    /// the recorder and returned.
+    pub fn record(&self, clear_color: Color) -> Result<RecordedFrame, PineError> {
+        let context = FrameContext {
+            scene: SceneRecorder {
+                backend: &self.backend,
+                pipeline: &self.quad_pipeline,
+                batcher: &self.sprite_batcher,
+            },
+            camera: &self.camera,
+            scene_graph: &self.scene_graph,
+            post: self.post.as_ref(),
+        };
+        let commands = context.record(&self.render_graph, self.size, clear_color)?;
+        self.backend.submit(&commands.commands)?;
+        // The frame keeps the commands, the backend doesn't need to.
+        self.backend.take_submissions();
+
+        let frame = RecordedFrame::new(clear_color, self.size, self.camera.view_proj(), &commands);
+        Ok(self.recorder.push(frame))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{
+            graph::{GraphPass, PassContext, PassDesc, RenderTextureDesc, ScenePass},
+            post::Bloom,
+            scene::{Renderable, SceneNode2D, Transform},
+            texture::TextureHandle,
+        },
+        *,
+    };
+
+    use glam::Vec2;
+
+    fn rect(x: f32, y: f32) -> SceneNode2D {
+        SceneNode2D::new()
+            .with_transform(Transform::from(x, y, 0.0))
+            .with_renderable(Renderable::Rect {
+                width: 4.0,
+                height: 2.0,
+                color: Color::RED,
+            })
+    }
+
+    fn sprite(texture: usize) -> SceneNode2D {
+        SceneNode2D::new().with_renderable(Renderable::sprite(1.0, 1.0, TextureHandle(texture)))
+    }
+
+    fn renderer(scene: SceneNode2D) -> RecordingRenderer {
+        let mut renderer = RecordingRenderer::new(200, 100, FrameRecorder::new()).unwrap();
+        renderer.set_scene_graph(scene);
+        renderer
+    }
+
+    #[test]
+    fn frames_keep_every_sprite_drawn() {
+        let renderer = renderer(
+            rect(0.0, 0.0)
+                .add_node(rect(1.0, 0.0))
+                .add_node(rect(2.0, 0.0)),
+        );
+        let frame = renderer.record(Color::BLACK).unwrap();
+
+        assert_eq!(frame.sprites().count(), 3);
+        assert_eq!(frame.size, (200, 100));
+        let pass = frame.pass("Render pass").unwrap();
+        assert_eq!(pass.sprites.len(), 3);
+        assert_eq!(pass.clear, Some(Color::BLACK));
+    }
+
+    #[test]
+    fn sprites_have_their_transforms_resolved() {
+        let renderer = renderer(
+            SceneNode2D::new()
+                .with_transform(Transform::from(10.0, 20.0, 0.0))
+                .add_node(rect(5.0, -5.0)),
+        );
+        let frame = renderer.record(Color::BLACK).unwrap();
+
+        let sprites: Vec<_> = frame.sprites().collect();
+        assert_eq!(sprites.len(), 1);
+        assert_eq!(sprites[0].transform.translation, Vec2::new(15.0, 15.0));
+        assert_eq!(sprites[0].transform.matrix2.x_axis, Vec2::new(4.0, 0.0));
+        assert_eq!(sprites[0].transform.matrix2.y_axis, Vec2::new(0.0, 2.0));
+    }
+
+    #[test]
+    fn sprites_are_batched_by_texture() {
+        let renderer = renderer(
+            SceneNode2D::new()
+                .add_node(sprite(1))
+                .add_node(sprite(2))
+                .add_node(sprite(1))
+                .add_node(sprite(2))
+                .add_node(sprite(1)),
+        );
+        let frame = renderer.record(Color::BLACK).unwrap();
+
+        assert_eq!(frame.draw_calls(), 2);
+        let textures: Vec<_> = frame.sprites().map(|sprite| sprite.texture).collect();
+        assert_eq!(
+            textures,
+            [1, 1, 1, 2, 2].map(TextureHandle),
+            "sprites sharing a texture are drawn together"
+        );
+    }
+
+    #[test]
+    fn frames_keep_their_clear_color() {
+        let renderer = renderer(rect(0.0, 0.0));
+        renderer.record(Color::RED).unwrap();
+        renderer.record(Color::BLUE).unwrap();
+
+        let colors: Vec<_> = renderer
+            .recorder()
+            .frames()
+            .iter()
+            .map(|frame| frame.clear_color)
+            .collect();
+        assert_eq!(colors, [Color::RED, Color::BLUE]);
+    }
+
+    #[test]
+    fn recorders_only_keep_the_most_recent_frames() {
+        let recorder = FrameRecorder::new();
+        let renderer = RecordingRenderer::new(200, 100, recorder.clone()).unwrap();
+        for _ in 0..3 {
+            renderer.record(Color::BLACK).unwrap();
+        }
+
+        let recorder = recorder.with_max_frames(2);
+        let indices: Vec<_> = recorder.frames().iter().map(|frame| frame.index).collect();
+        assert_eq!(indices, [1, 2]);
+
+        renderer.record(Color::BLACK).unwrap();
+        let indices: Vec<_> = recorder.frames().iter().map(|frame| frame.index).collect();
+        assert_eq!(indices, [2, 3]);
+        assert_eq!(recorder.recorded(), 4);
+        assert_eq!(recorder.take_frames().len(), 2);
+        assert!(recorder.is_empty());
+    }
+
+    #[test]
+    fn pipelines_keep_the_preprocessed_shader() {
+        let renderer = renderer(rect(0.0, 0.0));
+        let frame = renderer.record(Color::BLACK).unwrap();
+        let pipeline = frame.passes[0]
+            .commands
+            .iter()
+            .find_map(|command| match command {
+                Command::SetPipeline(pipeline) => Some(*pipeline),
+                _ => None,
+            })
+            .unwrap();
+
+        let ShaderSource::Wgsl(source) = renderer.backend().pipeline(pipeline).unwrap().shader
+        else {
+            panic!("expected the shader source");
+        };
+        assert!(!source.contains("#include"));
+        assert!(source.contains("struct Globals"));
+    }
+
+    #[test]
+    fn post_processing_is_recorded_through_the_graph() {
+        let mut renderer = renderer(rect(0.0, 0.0).add_node(rect(1.0, 0.0)));
+        renderer
+            .set_post_processing(PostProcessing::default().with_bloom(Bloom {
+                enabled: true,
+                ..Default::default()
+            }))
+            .unwrap();
+        let frame = renderer.record(Color::BLACK).unwrap();
+
+        let scene = &frame.passes[0];
+        assert_eq!(scene.label, "Render pass");
+        assert_eq!(scene.sprites.len(), 2);
+        let PassTarget::Texture(source) = scene.target else {
+            panic!("the scene should be drawn into a texture");
+        };
+        assert!(frame
+            .passes
+            .iter()
+            .any(|pass| pass.label == "Bloom threshold pass"));
+
+        let composite = frame.passes.last().unwrap();
+        assert_eq!(composite.label, "Composite pass");
+        assert_eq!(composite.target, PassTarget::Output);
+        assert!(composite.commands.contains(&Command::SetTexture {
+            group: 0,
+            texture: source,
+        }));
+        assert_eq!(frame.sprites().count(), 2, "only the scene draws sprites");
+    }
+
+    #[derive(Debug)]
+    /// Draws a fullscreen triangle sampling the scene.
+    struct BlitPass;
+
+    impl GraphPass for BlitPass {
+        fn run(&self, context: &mut PassContext) -> Result<(), PineError> {
+            let source = context.read("scene color")?;
+            let target = context.write(RenderGraph::OUTPUT)?;
+            context.commands.push(Command::BeginPass {
+                label: "Blit pass".into(),
+                target,
+                clear: None,
+            });
+            context.commands.push(Command::SetTexture {
+                group: 0,
+                texture: source,
+            });
+            context.commands.push(Command::Draw {
+                vertices: 0..3,
+                instances: 0..1,
+            });
+            context.commands.push(Command::EndPass);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn custom_graph_passes_are_recorded() {
+        let mut renderer = renderer(rect(0.0, 0.0));
+        let graph = renderer.render_graph_mut();
+        graph.remove_pass(ScenePass::NAME);
+        graph
+            .add_texture("scene color", RenderTextureDesc::default().with_scale(0.5))
+            .unwrap();
+        let scene_pass = ScenePass::new("scene color");
+        graph.add_pass(scene_pass.desc(), scene_pass).unwrap();
+        graph
+            .add_pass(
+                PassDesc::new("blit")
+                    .with_read("scene color")
+                    .with_write(RenderGraph::OUTPUT),
+                BlitPass,
+            )
+            .unwrap();
+        let frame = renderer.record(Color::BLACK).unwrap();
+
+        let labels: Vec<_> = frame.passes.iter().map(|pass| &pass.label).collect();
+        assert_eq!(labels, ["Render pass", "Blit pass"]);
+        let PassTarget::Texture(scene) = frame.passes[0].target else {
+            panic!("the scene should be drawn into a texture");
+        };
+        assert_eq!(renderer.backend().texture_size(scene), Some((100, 50)));
+        assert_eq!(frame.passes[1].target, PassTarget::Output);
+        assert_eq!(frame.passes[1].sprites, []);
+        assert_eq!(frame.draw_calls(), 2);
+    }
+}
@@ -1,22 +1,231 @@
-#[derive(Debug, Default, Clone, Copy)]
+use glam::{Affine2, Mat4, Vec2, Vec4};
+
+use super::{
+    color::Color,
+    pipeline::Quad,
+    texture::{TextureAtlas, TextureHandle, TextureRegion},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// The position, rotation and scale of a node relative to its parent.
 pub struct Transform {
-    x: f64,
-    y: f64,
-    z: f64,
+    pub translation: Vec2,
+    /// Rotation in radians, counter-clockwise.
+    pub rotation: f32,
+    pub scale: Vec2,
+    /// The draw order of the node. Nodes with a higher z are drawn on top.
+    pub z: f32,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
 }
 
 impl Transform {
-    pub fn from(x: f64, y: f64, z: f64) -> Self {
-        Self { x, y, z }
+    pub const IDENTITY: Self = Self {
+        translation: Vec2::ZERO,
+        rotation: 0.0,
+        scale: Vec2::ONE,
+        z: 0.0,
+    };
+
+    pub fn from(x: f32, y: f32, z: f32) -> Self {
+        Self {
+            translation: Vec2::new(x, y),
+            z,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Constructs a transform that only translates.
+    pub fn from_translation(translation: Vec2) -> Self {
+        Self {
+            translation,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Sets the rotation in radians, counter-clockwise.
+    pub fn with_rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Sets the scale along each axis.
+    pub fn with_scale(mut self, scale: Vec2) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Sets the draw order.
+    pub fn with_z(mut self, z: f32) -> Self {
+        self.z = z;
+        self
+    }
+
+    /// Returns the matrix mapping the local space of the node into the space of its parent.
+    ///
+    /// Scale is applied first, then rotation, then translation.
+    pub fn local_matrix(&self) -> Affine2 {
+        Affine2::from_scale_angle_translation(self.scale, self.rotation, self.translation)
+    }
+
+    /// Blends linearly between this transform and another, component by component.
+    ///
+    /// NB: rotations aren't wrapped, so blending from just below pi to just above -pi spins the
+    /// long way around.
+    pub fn lerp(&self, other: &Transform, t: f32) -> Self {
+        Self {
+            translation: self.translation.lerp(other.translation, t),
+            rotation: self.rotation + (other.rotation - self.rotation) * t,
+            scale: self.scale.lerp(other.scale, t),
+            z: self.z + (other.z - self.z) * t,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// The transform of a node relative to the world, resolved from its ancestors.
+pub struct GlobalTransform {
+    pub matrix: Affine2,
+    /// The accumulated draw order of the node and its ancestors.
+    pub z: f32,
+}
+
+impl Default for GlobalTransform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl GlobalTransform {
+    pub const IDENTITY: Self = Self {
+        matrix: Affine2::IDENTITY,
+        z: 0.0,
+    };
+
+    /// Returns the global transform of a child with the given local transform.
+    pub fn mul_transform(&self, transform: &Transform) -> Self {
+        Self {
+            matrix: self.matrix * transform.local_matrix(),
+            z: self.z + transform.z,
+        }
+    }
+
+    /// Returns the position of the node in the world.
+    pub fn translation(&self) -> Vec2 {
+        self.matrix.translation
+    }
+
+    /// Returns the transform as a 4x4 matrix, with the draw order as the z translation.
+    pub fn to_mat4(&self) -> Mat4 {
+        let Affine2 {
+            matrix2,
+            translation,
+        } = self.matrix;
+        Mat4::from_cols(
+            matrix2.x_axis.extend(0.0).extend(0.0),
+            matrix2.y_axis.extend(0.0).extend(0.0),
+            Vec4::Z,
+            translation.extend(self.z).extend(1.0),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Something a scene node can draw.
+pub enum Renderable {
+    /// A rectangle filled with a single color.
+    Rect {
+        width: f32,
+        height: f32,
+        color: Color,
+    },
+    /// A textured rectangle, tinted by the given color.
+    Sprite {
+        width: f32,
+        height: f32,
+        texture: TextureHandle,
+        region: TextureRegion,
+        tint: Color,
+    },
+}
+
+impl Renderable {
+    /// Constructs a sprite drawing the entire texture.
+    pub fn sprite(width: f32, height: f32, texture: TextureHandle) -> Self {
+        Renderable::Sprite {
+            width,
+            height,
+            texture,
+            region: TextureRegion::FULL,
+            tint: Color::WHITE,
+        }
+    }
+
+    /// Constructs a sprite drawing the region at the given index of an atlas.
+    ///
+    /// Returns None if the atlas has no such region.
+    pub fn atlas_sprite(
+        width: f32,
+        height: f32,
+        atlas: &TextureAtlas,
+        index: usize,
+    ) -> Option<Self> {
+        let region = atlas.region(index)?;
+        Some(Renderable::Sprite {
+            width,
+            height,
+            texture: atlas.texture(),
+            region,
+            tint: Color::WHITE,
+        })
+    }
+
+    /// Resolves the renderable into a quad drawn with the given transform.
+    pub(crate) fn to_quad(self, global: &GlobalTransform) -> Quad {
+        let (size, color, texture, region) = match self {
+            Renderable::Rect {
+                width,
+                height,
+                color,
+            } => (
+                [width, height],
+                color,
+                TextureHandle::WHITE,
+                TextureRegion::FULL,
+            ),
+            Renderable::Sprite {
+                width,
+                height,
+                texture,
+                region,
+                tint,
+            } => ([width, height], tint, texture, region),
+        };
+
+        Quad {
+            transform: global.matrix * Affine2::from_scale(Vec2::from(size)),
+            uv_rect: region.into(),
+            color: color.into(),
+            texture,
+            layer: global.z,
+        }
     }
 }
 
 #[derive(Debug, Default)]
+/// A description of a node and its children, used for building scene graphs.
+///
+/// Nodes only get an ID once they're inserted into a [`SceneGraph`](super::scene_graph::SceneGraph).
 pub struct SceneNode2D {
-    /// A node ID of 0 denotes that it's the root.
-    id: u64,
-    transform: Transform,
-    children: Vec<Box<SceneNode2D>>,
+    pub(crate) name: Option<String>,
+    pub(crate) tags: Vec<String>,
+    pub(crate) transform: Transform,
+    pub(crate) renderable: Option<Renderable>,
+    pub(crate) children: Vec<SceneNode2D>,
 }
 
 impl SceneNode2D {
@@ -29,57 +238,67 @@ impl SceneNode2D {
         self
     }
 
+    /// Sets what the node draws.
+    pub fn with_renderable(mut self, renderable: Renderable) -> Self {
+        self.renderable = Some(renderable);
+        self
+    }
+
+    /// Sets the name of the node. Names must be unique within a scene graph.
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// Adds a tag to the node. Any number of nodes can share a tag.
+    pub fn with_tag(mut self, tag: &str) -> Self {
+        self.tags.push(tag.to_string());
+        self
+    }
+
     /// Adds a child node to the scene.
     ///
     /// Allows for composable scene graphs.
-    pub fn add_node(mut self, mut node: SceneNode2D) -> Self {
-        // Set the appropriate ID of the node. It's +1 as we need to account for the root node's
-        // ID.
-        node.id = self.id + self.children.len() as u64 + 1;
-        self.children.push(Box::new(node));
+    pub fn add_node(mut self, node: SceneNode2D) -> Self {
+        self.children.push(node);
         self
     }
+}
 
-    /// Renders the scene and all its children.
-    ///
-    /// NB: this method renders with this scene as the origin and should probably only be used for
-    /// the root node.
-    pub fn render(&self) {
-        let position = self.transform;
-
-        tracing::info!(
-            "Rendering scene node {} at position x: {}, y: {}, z: {}",
-            self.id,
-            position.x,
-            position.y,
-            position.z
-        );
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        for child in &self.children {
-            child.render_with_offset(position);
-        }
-    }
+    use std::f32::consts::{FRAC_PI_2, FRAC_PI_4};
 
-    /// Renders the scene and all its children.
-    ///
-    /// This method takes an offset into account.
-    pub fn render_with_offset(&self, offset: Transform) {
-        let position = Transform::from(
-            self.transform.x + offset.x,
-            self.transform.y + offset.y,
-            self.transform.z + offset.z,
+    #[test]
+    fn children_are_placed_in_their_parents_space() {
+        let parent = GlobalTransform::IDENTITY.mul_transform(
+            &Transform::from(10.0, 0.0, 1.0)
+                .with_rotation(FRAC_PI_2)
+                .with_scale(Vec2::splat(2.0)),
         );
-
-        tracing::info!(
-            "Rendering scene node {} at position x: {}, y: {}, z: {}",
-            self.id,
-            position.x,
-            position.y,
-            position.z
+        let child = parent.mul_transform(
+            &Transform::from(1.0, 0.0, 2.0)
+                .with_rotation(FRAC_PI_4)
+                .with_scale(Vec2::new(0.5, 3.0)),
         );
 
-        for child in &self.children {
-            child.render_with_offset(position);
-        }
+        // One unit right of a parent turned a quarter and scaled twice is two units up.
+        assert!(child.translation().abs_diff_eq(Vec2::new(10.0, 2.0), 1e-5));
+        let (scale, rotation, _) = child.matrix.to_scale_angle_translation();
+        assert!(scale.abs_diff_eq(Vec2::new(1.0, 6.0), 1e-5));
+        assert!((rotation - (FRAC_PI_2 + FRAC_PI_4)).abs() < 1e-5);
+        assert_eq!(child.z, 3.0);
+    }
+
+    #[test]
+    fn global_matrices_carry_the_draw_order() {
+        let global = GlobalTransform::IDENTITY.mul_transform(&Transform::from(3.0, 4.0, 5.0));
+        let matrix = global.to_mat4();
+        assert_eq!(
+            matrix.transform_point3(glam::Vec3::new(1.0, 1.0, 0.0)),
+            glam::Vec3::new(4.0, 5.0, 5.0)
+        );
     }
 }
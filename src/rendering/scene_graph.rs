@@ -0,0 +1,836 @@
+use super::{
+    pipeline::Quad,
+    scene::{GlobalTransform, Renderable, SceneNode2D, Transform},
+};
+use crate::error::PineError;
+
+use std::{
+    cell::Cell,
+    collections::{HashMap, VecDeque},
+    fmt,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/// The next ID handed out to a node, shared by all scene graphs.
+static NEXT_NODE_ID: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// A stable handle to a node in a scene graph.
+///
+/// IDs are unique across all scene graphs and are never reused, so a handle to a removed node
+/// simply stops resolving.
+pub struct NodeId(u64);
+
+impl NodeId {
+    fn next() -> Self {
+        Self(NEXT_NODE_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+impl fmt::Display for NodeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+#[derive(Debug)]
+/// A node living in a scene graph.
+pub struct Node {
+    id: NodeId,
+    name: Option<String>,
+    tags: Vec<String>,
+    transform: Transform,
+    /// The transform as of the last snapshot, blended from when interpolating.
+    previous_transform: Transform,
+    /// Cached global transform, only valid while `dirty` is false.
+    global_transform: Cell<GlobalTransform>,
+    dirty: Cell<bool>,
+    renderable: Option<Renderable>,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+}
+
+impl Node {
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    /// Adds a tag to the node, unless it already has it.
+    pub fn add_tag(&mut self, tag: &str) {
+        if !self.has_tag(tag) {
+            self.tags.push(tag.to_string());
+        }
+    }
+
+    /// Removes a tag from the node. Returns whether the node had the tag.
+    pub fn remove_tag(&mut self, tag: &str) -> bool {
+        let len = self.tags.len();
+        self.tags.retain(|t| t != tag);
+        self.tags.len() != len
+    }
+
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    /// Returns the local transform for modification.
+    ///
+    /// Marks the node dirty, so the global transforms of it and its descendants are recomputed.
+    pub fn transform_mut(&mut self) -> &mut Transform {
+        self.dirty.set(true);
+        &mut self.transform
+    }
+
+    pub fn set_transform(&mut self, transform: Transform) {
+        *self.transform_mut() = transform;
+    }
+
+    /// Sets the local transform without interpolating from the old one.
+    pub fn teleport(&mut self, transform: Transform) {
+        self.set_transform(transform);
+        self.previous_transform = transform;
+    }
+
+    /// Returns the global transform as of the last propagation.
+    ///
+    /// See [`SceneGraph::propagate_transforms`].
+    pub fn global_transform(&self) -> GlobalTransform {
+        self.global_transform.get()
+    }
+
+    pub fn renderable(&self) -> Option<&Renderable> {
+        self.renderable.as_ref()
+    }
+
+    pub fn set_renderable(&mut self, renderable: Option<Renderable>) {
+        self.renderable = renderable;
+    }
+
+    /// Returns the parent of the node. Only the root has none.
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+}
+
+#[derive(Debug)]
+/// A tree of nodes addressed by stable [`NodeId`] handles.
+///
+/// The graph always has a root node, which can't be removed. Nodes can be added, found, changed,
+/// moved and removed at any time.
+pub struct SceneGraph {
+    root: NodeId,
+    nodes: HashMap<NodeId, Node>,
+    names: HashMap<String, NodeId>,
+    interpolation: bool,
+    alpha: f32,
+}
+
+impl Default for SceneGraph {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SceneGraph {
+    /// Constructs a scene graph holding only an empty root node.
+    pub fn new() -> Self {
+        let root = NodeId::next();
+        let mut nodes = HashMap::new();
+        nodes.insert(
+            root,
+            Node {
+                id: root,
+                name: None,
+                tags: vec![],
+                transform: Transform::IDENTITY,
+                previous_transform: Transform::IDENTITY,
+                global_transform: Cell::new(GlobalTransform::IDENTITY),
+                dirty: Cell::new(true),
+                renderable: None,
+                parent: None,
+                children: vec![],
+            },
+        );
+
+        Self {
+            root,
+            nodes,
+            names: HashMap::new(),
+            interpolation: false,
+            alpha: 1.0,
+        }
+    }
+
+    pub fn root(&self) -> NodeId {
+        self.root
+    }
+
+    /// Returns the number of nodes in the graph, including the root.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns whether the graph holds nothing but the root.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.len() == 1
+    }
+
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.nodes.contains_key(&id)
+    }
+
+    pub fn get(&self, id: NodeId) -> Option<&Node> {
+        self.nodes.get(&id)
+    }
+
+    pub fn get_mut(&mut self, id: NodeId) -> Option<&mut Node> {
+        self.nodes.get_mut(&id)
+    }
+
+    /// Inserts a node and its children below the given parent, returning the ID of the node.
+    ///
+    /// Fails without changing the graph if the parent doesn't exist or a name is already taken.
+    pub fn insert(&mut self, parent: NodeId, node: SceneNode2D) -> Result<NodeId, PineError> {
+        if !self.contains(parent) {
+            return Err(PineError::SceneNodeNotFoundError(parent));
+        }
+        self.check_names(&node, &mut vec![])?;
+
+        Ok(self.insert_unchecked(parent, node))
+    }
+
+    /// Inserts a node and its children below the root, returning the ID of the node.
+    pub fn add(&mut self, node: SceneNode2D) -> Result<NodeId, PineError> {
+        self.insert(self.root, node)
+    }
+
+    /// Removes a node and all its descendants.
+    ///
+    /// Returns the removed subtree, which can be inserted again, or None if there's no such node.
+    /// The root can't be removed.
+    pub fn remove(&mut self, id: NodeId) -> Option<SceneNode2D> {
+        if id == self.root {
+            return None;
+        }
+
+        let parent = self.nodes.get(&id)?.parent?;
+        if let Some(parent) = self.nodes.get_mut(&parent) {
+            parent.children.retain(|child| *child != id);
+        }
+
+        Some(self.take_subtree(id))
+    }
+
+    /// Moves a node, along with its descendants, below a new parent.
+    ///
+    /// The local transform is kept, so the node moves along with its new parent.
+    pub fn reparent(&mut self, id: NodeId, new_parent: NodeId) -> Result<(), PineError> {
+        if !self.contains(id) {
+            return Err(PineError::SceneNodeNotFoundError(id));
+        }
+        if !self.contains(new_parent) {
+            return Err(PineError::SceneNodeNotFoundError(new_parent));
+        }
+        if id == self.root || self.is_ancestor_or_self(id, new_parent) {
+            return Err(PineError::SceneCycleError {
+                node: id,
+                parent: new_parent,
+            });
+        }
+
+        let node = &self.nodes[&id];
+        let old_parent = node.parent.expect("Only the root has no parent");
+        node.dirty.set(true);
+        if old_parent == new_parent {
+            return Ok(());
+        }
+
+        if let Some(old_parent) = self.nodes.get_mut(&old_parent) {
+            old_parent.children.retain(|child| *child != id);
+        }
+        if let Some(new_parent) = self.nodes.get_mut(&new_parent) {
+            new_parent.children.push(id);
+        }
+        if let Some(node) = self.nodes.get_mut(&id) {
+            node.parent = Some(new_parent);
+        }
+
+        Ok(())
+    }
+
+    /// Names a node, or removes its name if None is given.
+    pub fn set_name(&mut self, id: NodeId, name: Option<&str>) -> Result<(), PineError> {
+        if !self.contains(id) {
+            return Err(PineError::SceneNodeNotFoundError(id));
+        }
+        if let Some(name) = name {
+            match self.names.get(name) {
+                Some(owner) if *owner == id => return Ok(()),
+                Some(_) => return Err(PineError::DuplicateNodeNameError(name.to_string())),
+                None => {}
+            }
+        }
+
+        let node = self.nodes.get_mut(&id).expect("Node exists");
+        if let Some(old_name) = node.name.take() {
+            self.names.remove(&old_name);
+        }
+        if let Some(name) = name {
+            node.name = Some(name.to_string());
+            self.names.insert(name.to_string(), id);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the ID of the node with the given name, if any.
+    pub fn find_by_name(&self, name: &str) -> Option<NodeId> {
+        self.names.get(name).copied()
+    }
+
+    /// Returns the IDs of all nodes with the given tag, in depth-first order.
+    pub fn find_by_tag<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = NodeId> + 'a {
+        self.iter_depth_first(self.root)
+            .filter(move |node| node.has_tag(tag))
+            .map(Node::id)
+    }
+
+    /// Iterates the given node and its descendants depth-first, parents before children.
+    ///
+    /// Yields nothing if there's no such node.
+    pub fn iter_depth_first(&self, from: NodeId) -> DepthFirst<'_> {
+        DepthFirst {
+            graph: self,
+            stack: self.contains(from).then_some(from).into_iter().collect(),
+        }
+    }
+
+    /// Iterates the given node and its descendants breadth-first, level by level.
+    ///
+    /// Yields nothing if there's no such node.
+    pub fn iter_breadth_first(&self, from: NodeId) -> BreadthFirst<'_> {
+        BreadthFirst {
+            graph: self,
+            queue: self.contains(from).then_some(from).into_iter().collect(),
+        }
+    }
+
+    /// Recomputes the global transforms of all dirty nodes and their descendants.
+    pub fn propagate_transforms(&self) {
+        self.propagate_with_parent(self.root, &GlobalTransform::IDENTITY, false);
+    }
+
+    /// Collects the quads of all nodes in the graph.
+    ///
+    /// Global transforms are propagated first.
+    pub fn render(&self, quads: &mut Vec<Quad>) {
+        self.propagate_transforms();
+
+        if self.interpolation && self.alpha < 1.0 {
+            self.collect_interpolated(self.root, &GlobalTransform::IDENTITY, quads);
+            return;
+        }
+
+        for node in self.iter_depth_first(self.root) {
+            Self::collect_quad(node, &node.global_transform.get(), quads);
+        }
+    }
+
+    /// Sets whether nodes are drawn blended between their last snapshot and their current
+    /// transform.
+    ///
+    /// Smooths out movement done in fixed updates when the frame rate is higher than the fixed
+    /// rate. Nodes moved outside of fixed updates should be moved with [`Node::teleport`], or
+    /// they'll trail behind.
+    pub fn set_interpolation(&mut self, interpolation: bool) {
+        self.interpolation = interpolation;
+    }
+
+    pub fn interpolation(&self) -> bool {
+        self.interpolation
+    }
+
+    /// Sets how far to blend from the snapshot towards the current transforms, from 0 to 1.
+    pub fn set_interpolation_alpha(&mut self, alpha: f32) {
+        self.alpha = alpha.clamp(0.0, 1.0);
+    }
+
+    /// Remembers the current transforms as the ones to interpolate from.
+    ///
+    /// Called by the engine before every fixed update.
+    pub fn snapshot_transforms(&mut self) {
+        for node in self.nodes.values_mut() {
+            node.previous_transform = node.transform;
+        }
+    }
+
+    /// Recomputes global transforms below the given parent transform.
+    ///
+    /// Nodes are only recomputed if they, or one of their ancestors, changed.
+    fn propagate_with_parent(&self, id: NodeId, parent: &GlobalTransform, parent_changed: bool) {
+        let node = &self.nodes[&id];
+        let changed = parent_changed || node.dirty.get();
+        if changed {
+            node.global_transform
+                .set(parent.mul_transform(&node.transform));
+            node.dirty.set(false);
+        }
+
+        let global_transform = node.global_transform.get();
+        for child in &node.children {
+            self.propagate_with_parent(*child, &global_transform, changed);
+        }
+    }
+
+    /// Collects quads depth-first, blending each local transform by the interpolation alpha.
+    fn collect_interpolated(&self, id: NodeId, parent: &GlobalTransform, quads: &mut Vec<Quad>) {
+        let node = &self.nodes[&id];
+        let transform = node.previous_transform.lerp(&node.transform, self.alpha);
+        let global_transform = parent.mul_transform(&transform);
+
+        Self::collect_quad(node, &global_transform, quads);
+        for child in &node.children {
+            self.collect_interpolated(*child, &global_transform, quads);
+        }
+    }
+
+    fn collect_quad(node: &Node, global_transform: &GlobalTransform, quads: &mut Vec<Quad>) {
+        tracing::trace!(
+            "Rendering scene node {} at position {} with z {}",
+            node.id,
+            global_transform.translation(),
+            global_transform.z
+        );
+
+        if let Some(renderable) = node.renderable {
+            quads.push(renderable.to_quad(global_transform));
+        }
+    }
+
+    /// Returns whether `ancestor` is `id` or one of its ancestors.
+    fn is_ancestor_or_self(&self, ancestor: NodeId, id: NodeId) -> bool {
+        let mut current = Some(id);
+        while let Some(node) = current {
+            if node == ancestor {
+                return true;
+            }
+            current = self.nodes.get(&node).and_then(|node| node.parent);
+        }
+        false
+    }
+
+    /// Makes sure none of the names in the subtree are taken, nor repeated within it.
+    fn check_names<'a>(
+        &self,
+        node: &'a SceneNode2D,
+        seen: &mut Vec<&'a str>,
+    ) -> Result<(), PineError> {
+        if let Some(name) = node.name.as_deref() {
+            if self.names.contains_key(name) || seen.contains(&name) {
+                return Err(PineError::DuplicateNodeNameError(name.to_string()));
+            }
+            seen.push(name);
+        }
+
+        for child in &node.children {
+            self.check_names(child, seen)?;
+        }
+        Ok(())
+    }
+
+    /// Flattens the subtree into the graph, assuming its names have been checked.
+    fn insert_unchecked(&mut self, parent: NodeId, node: SceneNode2D) -> NodeId {
+        let id = NodeId::next();
+        let SceneNode2D {
+            name,
+            tags,
+            transform,
+            renderable,
+            children,
+        } = node;
+
+        if let Some(name) = &name {
+            self.names.insert(name.clone(), id);
+        }
+        self.nodes.insert(
+            id,
+            Node {
+                id,
+                name,
+                tags,
+                transform,
+                previous_transform: transform,
+                global_transform: Cell::new(GlobalTransform::IDENTITY),
+                dirty: Cell::new(true),
+                renderable,
+                parent: Some(parent),
+                children: vec![],
+            },
+        );
+        if let Some(parent) = self.nodes.get_mut(&parent) {
+            parent.children.push(id);
+        }
+
+        for child in children {
+            self.insert_unchecked(id, child);
+        }
+
+        id
+    }
+
+    /// Removes the node and its descendants from the map, turning them back into a subtree.
+    fn take_subtree(&mut self, id: NodeId) -> SceneNode2D {
+        let node = self.nodes.remove(&id).expect("Node exists");
+        if let Some(name) = &node.name {
+            self.names.remove(name);
+        }
+
+        SceneNode2D {
+            name: node.name,
+            tags: node.tags,
+            transform: node.transform,
+            renderable: node.renderable,
+            children: node
+                .children
+                .into_iter()
+                .map(|child| self.take_subtree(child))
+                .collect(),
+        }
+    }
+}
+
+impl From<SceneNode2D> for SceneGraph {
+    /// Builds a scene graph with the given node as the root.
+    ///
+    /// Names repeated within the node are only kept for the first node in depth-first order.
+    fn from(node: SceneNode2D) -> Self {
+        let mut graph = SceneGraph::new();
+        let SceneNode2D {
+            name,
+            tags,
+            transform,
+            renderable,
+            children,
+        } = node;
+
+        let root = graph.nodes.get_mut(&graph.root).expect("Root exists");
+        root.tags = tags;
+        root.transform = transform;
+        root.previous_transform = transform;
+        root.renderable = renderable;
+        if let Err(err) = graph.set_name(graph.root, name.as_deref()) {
+            tracing::warn!("Failed to name root node: {}", err);
+        }
+
+        for child in children {
+            if let Err(err) = graph.check_names(&child, &mut vec![]) {
+                tracing::warn!("Dropping names of scene node subtree: {}", err);
+                graph.insert_unchecked(graph.root, strip_names(child));
+            } else {
+                graph.insert_unchecked(graph.root, child);
+            }
+        }
+
+        graph
+    }
+}
+
+/// Removes the names of a node and all its descendants.
+fn strip_names(mut node: SceneNode2D) -> SceneNode2D {
+    node.name = None;
+    node.children = node.children.into_iter().map(strip_names).collect();
+    node
+}
+
+#[derive(Debug)]
+/// A depth-first iterator over a subtree of a scene graph.
+pub struct DepthFirst<'graph> {
+    graph: &'graph SceneGraph,
+    stack: Vec<NodeId>,
+}
+
+impl<'graph> Iterator for DepthFirst<'graph> {
+    type Item = &'graph Node;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.graph.get(self.stack.pop()?)?;
+        // Pushed in reverse so the first child is visited first.
+        self.stack.extend(node.children.iter().rev());
+        Some(node)
+    }
+}
+
+#[derive(Debug)]
+/// A breadth-first iterator over a subtree of a scene graph.
+pub struct BreadthFirst<'graph> {
+    graph: &'graph SceneGraph,
+    queue: VecDeque<NodeId>,
+}
+
+impl<'graph> Iterator for BreadthFirst<'graph> {
+    type Item = &'graph Node;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.graph.get(self.queue.pop_front()?)?;
+        self.queue.extend(node.children.iter());
+        Some(node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec2;
+
+    fn named(name: &str) -> SceneNode2D {
+        SceneNode2D::new().with_name(name)
+    }
+
+    /// Builds root -> a -> (b -> d, c) and returns the IDs of a, b, c and d.
+    fn tree(graph: &mut SceneGraph) -> [NodeId; 4] {
+        let a = graph.add(named("a")).unwrap();
+        let b = graph.insert(a, named("b").add_node(named("d"))).unwrap();
+        let c = graph.insert(a, named("c")).unwrap();
+        let d = graph.find_by_name("d").unwrap();
+        [a, b, c, d]
+    }
+
+    #[test]
+    fn reparenting_below_a_descendant_is_a_cycle() {
+        let mut graph = SceneGraph::new();
+        let [a, b, _, d] = tree(&mut graph);
+
+        for (node, parent) in [(a, d), (a, a), (b, d)] {
+            assert!(matches!(
+                graph.reparent(node, parent),
+                Err(PineError::SceneCycleError { .. })
+            ));
+        }
+        assert!(matches!(
+            graph.reparent(graph.root(), a),
+            Err(PineError::SceneCycleError { .. })
+        ));
+        assert_eq!(graph.get(b).unwrap().parent(), Some(a));
+    }
+
+    #[test]
+    fn reparenting_moves_the_subtree() {
+        let mut graph = SceneGraph::new();
+        let [a, b, c, d] = tree(&mut graph);
+
+        graph.reparent(b, c).unwrap();
+
+        assert_eq!(graph.get(a).unwrap().children(), [c]);
+        assert_eq!(graph.get(c).unwrap().children(), [b]);
+        assert_eq!(graph.get(b).unwrap().parent(), Some(c));
+        assert_eq!(graph.get(d).unwrap().parent(), Some(b));
+    }
+
+    #[test]
+    fn reparenting_moves_along_with_the_new_parent() {
+        let mut graph = SceneGraph::new();
+        let parent = graph
+            .add(SceneNode2D::new().with_transform(Transform::from(10.0, 0.0, 0.0)))
+            .unwrap();
+        let child = graph
+            .add(SceneNode2D::new().with_transform(Transform::from(1.0, 2.0, 0.0)))
+            .unwrap();
+
+        graph.propagate_transforms();
+        assert_eq!(
+            graph.get(child).unwrap().global_transform().translation(),
+            Vec2::new(1.0, 2.0)
+        );
+
+        graph.reparent(child, parent).unwrap();
+        graph.propagate_transforms();
+        assert_eq!(
+            graph.get(child).unwrap().global_transform().translation(),
+            Vec2::new(11.0, 2.0)
+        );
+    }
+
+    #[test]
+    fn global_transforms_accumulate_down_the_hierarchy() {
+        use std::f32::consts::FRAC_PI_2;
+
+        let mut graph = SceneGraph::new();
+        let parent = graph
+            .add(
+                SceneNode2D::new().with_transform(
+                    Transform::from(10.0, 0.0, 1.0)
+                        .with_rotation(FRAC_PI_2)
+                        .with_scale(Vec2::splat(2.0)),
+                ),
+            )
+            .unwrap();
+        let child = graph
+            .insert(
+                parent,
+                SceneNode2D::new().with_transform(Transform::from(1.0, 0.0, 1.0)),
+            )
+            .unwrap();
+        let grandchild = graph
+            .insert(
+                child,
+                SceneNode2D::new()
+                    .with_transform(Transform::from(0.0, 1.0, 1.0).with_scale(Vec2::splat(0.25))),
+            )
+            .unwrap();
+        graph.propagate_transforms();
+
+        let global = graph.get(grandchild).unwrap().global_transform();
+        // Up in the child is left in the world, and twice as far.
+        assert!(global.translation().abs_diff_eq(Vec2::new(8.0, 2.0), 1e-5));
+        let (scale, rotation, _) = global.matrix.to_scale_angle_translation();
+        assert!(scale.abs_diff_eq(Vec2::splat(0.5), 1e-5));
+        assert!((rotation - FRAC_PI_2).abs() < 1e-5);
+        assert_eq!(global.z, 3.0);
+    }
+
+    #[test]
+    fn moving_a_parent_updates_its_descendants() {
+        let mut graph = SceneGraph::new();
+        let [a, b, c, d] = tree(&mut graph);
+        graph
+            .get_mut(d)
+            .unwrap()
+            .set_transform(Transform::from(1.0, 1.0, 0.0));
+        graph.propagate_transforms();
+        assert!(!graph.get(a).unwrap().dirty.get());
+
+        graph
+            .get_mut(a)
+            .unwrap()
+            .set_transform(Transform::from(5.0, 0.0, 2.0));
+        assert!(graph.get(a).unwrap().dirty.get());
+        // Global transforms are stale until the next propagation.
+        assert_eq!(
+            graph.get(d).unwrap().global_transform().translation(),
+            Vec2::new(1.0, 1.0)
+        );
+
+        graph.propagate_transforms();
+        for node in [a, b, c, d] {
+            assert!(!graph.get(node).unwrap().dirty.get());
+        }
+        assert_eq!(
+            graph.get(b).unwrap().global_transform().translation(),
+            Vec2::new(5.0, 0.0)
+        );
+        assert_eq!(graph.get(c).unwrap().global_transform().z, 2.0);
+        assert_eq!(
+            graph.get(d).unwrap().global_transform().translation(),
+            Vec2::new(6.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn removing_takes_the_whole_subtree() {
+        let mut graph = SceneGraph::new();
+        let [a, b, c, d] = tree(&mut graph);
+        assert_eq!(graph.len(), 5);
+
+        let removed = graph.remove(b).unwrap();
+
+        assert_eq!(removed.children.len(), 1);
+        assert_eq!(graph.len(), 3);
+        assert!(!graph.contains(b) && !graph.contains(d));
+        assert_eq!(graph.get(a).unwrap().children(), [c]);
+        assert_eq!(graph.find_by_name("b"), None);
+        assert_eq!(graph.find_by_name("d"), None);
+        assert!(graph.remove(b).is_none());
+        assert!(graph.remove(graph.root()).is_none());
+
+        // The names are free again, so the subtree can go right back in.
+        let b = graph.insert(c, removed).unwrap();
+        assert_eq!(graph.find_by_name("b"), Some(b));
+        assert_eq!(graph.len(), 5);
+    }
+
+    #[test]
+    fn names_are_unique() {
+        let mut graph = SceneGraph::new();
+        let [a, b, ..] = tree(&mut graph);
+
+        assert!(matches!(
+            graph.add(named("a")),
+            Err(PineError::DuplicateNodeNameError(_))
+        ));
+        assert!(matches!(
+            graph.add(named("e").add_node(named("e"))),
+            Err(PineError::DuplicateNodeNameError(_))
+        ));
+        assert!(matches!(
+            graph.set_name(b, Some("a")),
+            Err(PineError::DuplicateNodeNameError(_))
+        ));
+        assert_eq!(graph.len(), 5);
+
+        graph.set_name(a, Some("a")).unwrap();
+        graph.set_name(a, None).unwrap();
+        graph.set_name(b, Some("a")).unwrap();
+        assert_eq!(graph.find_by_name("a"), Some(b));
+        assert_eq!(graph.find_by_name("b"), None);
+    }
+
+    #[test]
+    fn depth_first_visits_parents_before_children() {
+        let mut graph = SceneGraph::new();
+        let [a, b, c, d] = tree(&mut graph);
+
+        let order: Vec<_> = graph.iter_depth_first(a).map(Node::id).collect();
+        assert_eq!(order, [a, b, d, c]);
+    }
+
+    #[test]
+    fn breadth_first_visits_level_by_level() {
+        let mut graph = SceneGraph::new();
+        let [a, b, c, d] = tree(&mut graph);
+
+        let order: Vec<_> = graph
+            .iter_breadth_first(graph.root())
+            .map(Node::id)
+            .collect();
+        assert_eq!(order, [graph.root(), a, b, c, d]);
+    }
+
+    #[test]
+    fn iterating_a_missing_node_yields_nothing() {
+        let mut graph = SceneGraph::new();
+        let [_, b, ..] = tree(&mut graph);
+        graph.remove(b);
+
+        assert_eq!(graph.iter_depth_first(b).count(), 0);
+        assert_eq!(graph.iter_breadth_first(b).count(), 0);
+    }
+
+    #[test]
+    fn tags_are_found_in_depth_first_order() {
+        let mut graph = SceneGraph::new();
+        let [a, b, c, d] = tree(&mut graph);
+        for id in [c, d, b] {
+            graph.get_mut(id).unwrap().add_tag("enemy");
+        }
+        graph.get_mut(a).unwrap().add_tag("player");
+
+        let enemies: Vec<_> = graph.find_by_tag("enemy").collect();
+        assert_eq!(enemies, [b, d, c]);
+    }
+}
@@ -1,16 +1,421 @@
-use std::fs;
+use super::preprocessor::{canonicalize, preprocess, PreprocessedShader, ShaderDefs};
+use crate::error::PineError;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+
+/// How often the shader manager checks files for changes by default.
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The directory the built-in shaders are loaded from while it exists, e.g. during development.
+pub const BUILTIN_SHADER_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/rendering/shaders");
+
+/// The built-in shaders and their includes, by path relative to [`BUILTIN_SHADER_DIR`].
+///
+/// Embedded so renderers work on machines without the source tree, where the files can't be
+/// read and therefore aren't hot-reloaded either.
+const BUILTIN_SHADERS: &[(&str, &str)] = &[
+    ("color.wgsl", include_str!("shaders/color.wgsl")),
+    ("fullscreen.wgsl", include_str!("shaders/fullscreen.wgsl")),
+    ("globals.wgsl", include_str!("shaders/globals.wgsl")),
+    ("quad.wgsl", include_str!("shaders/quad.wgsl")),
+    ("post/bloom.wgsl", include_str!("shaders/post/bloom.wgsl")),
+    (
+        "post/composite.wgsl",
+        include_str!("shaders/post/composite.wgsl"),
+    ),
+];
+
+/// Returns the embedded source of a built-in shader, given its path in [`BUILTIN_SHADER_DIR`].
+pub fn builtin_source(path: &Path) -> Option<&'static str> {
+    let relative = path.strip_prefix(BUILTIN_SHADER_DIR).ok()?;
+    BUILTIN_SHADERS
+        .iter()
+        .find(|(name, _)| Path::new(name) == relative)
+        .map(|(_, source)| *source)
+}
 
 /// Creates a shader module from the given shader source using the given device.
-pub fn load_shader(device: &wgpu::Device, path: &str) -> Result<wgpu::ShaderModule, ()> {
-    let shader_module = match fs::read_to_string(path) {
-        Ok(shader_src) => device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some(path),
-            source: wgpu::ShaderSource::Wgsl(shader_src.into()),
+///
+/// The source is preprocessed and validated with naga first, so broken shaders are reported as
+/// errors rather than bringing down the device.
+pub fn load_shader(device: &wgpu::Device, path: &str) -> Result<wgpu::ShaderModule, PineError> {
+    load_shader_variant(device, path, &ShaderDefs::default())
+}
+
+/// Creates a shader module from the given shader source, preprocessed with the given definitions.
+pub fn load_shader_variant(
+    device: &wgpu::Device,
+    path: impl AsRef<Path>,
+    defs: &ShaderDefs,
+) -> Result<wgpu::ShaderModule, PineError> {
+    create_shader(device, &preprocess(path, defs)?)
+}
+
+/// Validates preprocessed WGSL and creates a shader module from it.
+///
+/// Validation only knows about the device's features, so anything else wgpu rejects is caught in
+/// an error scope instead of reaching the device's error handler, which panics.
+fn create_shader(
+    device: &wgpu::Device,
+    shader: &PreprocessedShader,
+) -> Result<wgpu::ShaderModule, PineError> {
+    validate_wgsl(shader, shader_capabilities(device.features()))?;
+
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: shader.files()[0].to_str(),
+        source: wgpu::ShaderSource::Wgsl(shader.source().into()),
+    });
+    match pollster::block_on(device.pop_error_scope()) {
+        Some(err) => Err(PineError::ShaderValidationError {
+            path: shader.files()[0].clone(),
+            message: err.to_string(),
         }),
-        Err(err) => {
-            tracing::error!("failed to load shader: {}", err);
-            return Err(());
-        }
+        None => Ok(module),
+    }
+}
+
+/// Returns the shader capabilities a device with the given features supports.
+///
+/// Mirrors the capabilities wgpu validates shaders against, except for those that depend on
+/// downlevel flags, which are left out.
+pub fn shader_capabilities(features: wgpu::Features) -> naga::valid::Capabilities {
+    use naga::valid::Capabilities;
+
+    let mut capabilities = Capabilities::empty();
+    for (capability, feature) in [
+        (Capabilities::PUSH_CONSTANT, wgpu::Features::PUSH_CONSTANTS),
+        (Capabilities::FLOAT64, wgpu::Features::SHADER_F64),
+        (
+            Capabilities::PRIMITIVE_INDEX,
+            wgpu::Features::SHADER_PRIMITIVE_INDEX,
+        ),
+        (
+            Capabilities::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING,
+            wgpu::Features::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING,
+        ),
+        (
+            Capabilities::UNIFORM_BUFFER_AND_STORAGE_TEXTURE_ARRAY_NON_UNIFORM_INDEXING,
+            wgpu::Features::UNIFORM_BUFFER_AND_STORAGE_TEXTURE_ARRAY_NON_UNIFORM_INDEXING,
+        ),
+        (
+            Capabilities::SAMPLER_NON_UNIFORM_INDEXING,
+            wgpu::Features::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING,
+        ),
+        (
+            Capabilities::STORAGE_TEXTURE_16BIT_NORM_FORMATS,
+            wgpu::Features::TEXTURE_FORMAT_16BIT_NORM,
+        ),
+        (Capabilities::MULTIVIEW, wgpu::Features::MULTIVIEW),
+        (
+            Capabilities::EARLY_DEPTH_TEST,
+            wgpu::Features::SHADER_EARLY_DEPTH_TEST,
+        ),
+        (
+            Capabilities::DUAL_SOURCE_BLENDING,
+            wgpu::Features::DUAL_SOURCE_BLENDING,
+        ),
+    ] {
+        capabilities.set(capability, features.contains(feature));
+    }
+    capabilities
+}
+
+/// Parses and validates preprocessed WGSL against the given capabilities, e.g. those from
+/// [`shader_capabilities`].
+///
+/// Errors point at the file and line the offending code was written in, not at the assembled
+/// source.
+pub fn validate_wgsl(
+    shader: &PreprocessedShader,
+    capabilities: naga::valid::Capabilities,
+) -> Result<(), PineError> {
+    let source = shader.source();
+    let module = naga::front::wgsl::parse_str(source)
+        .map_err(|err| validation_error(shader, err.location(source), err.message()))?;
+    naga::valid::Validator::new(naga::valid::ValidationFlags::all(), capabilities)
+        .validate(&module)
+        .map_err(|err| validation_error(shader, err.location(source), err.as_inner()))?;
+    Ok(())
+}
+
+/// Builds a validation error for a location in the preprocessed source.
+fn validation_error(
+    shader: &PreprocessedShader,
+    location: Option<naga::SourceLocation>,
+    message: impl std::fmt::Display,
+) -> PineError {
+    let mapped = location.and_then(|location| {
+        let line = location.line_number as usize;
+        let (path, original_line) = shader.original_location(line)?;
+        Some((path, original_line, location))
+    });
+
+    let Some((path, original_line, location)) = mapped else {
+        return PineError::ShaderValidationError {
+            path: shader.files()[0].clone(),
+            message: message.to_string(),
+        };
     };
-    Ok(shader_module)
+
+    // Show the line as written, with the caret under the same code despite substituted values.
+    let line = location.line_number as usize;
+    let text = shader.original_line(line).unwrap_or_default();
+    let column = shader
+        .original_column(line, location.line_position as usize)
+        .unwrap_or(1);
+    let caret = " ".repeat(column - 1);
+    PineError::ShaderValidationError {
+        path: path.to_path_buf(),
+        message: format!(
+            "{}:{}:{}: {}\n    {}\n    {}^",
+            path.display(),
+            original_line,
+            column,
+            message,
+            text,
+            caret
+        ),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Identifies a shader loaded through the [`ShaderManager`].
+pub struct ShaderId(usize);
+
+#[derive(Debug)]
+/// A loaded shader along with what's needed to notice changes to its files.
+struct ShaderEntry {
+    path: PathBuf,
+    defs: ShaderDefs,
+    /// The files the shader was assembled from, with their modification times.
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+    module: Arc<wgpu::ShaderModule>,
+    /// Bumped every time the module is swapped, so users can tell when to rebuild pipelines.
+    generation: u64,
+}
+
+#[derive(Debug)]
+/// Keeps track of the WGSL files loaded as shaders and reloads them when they change on disk.
+///
+/// Shaders that fail to compile after a change are logged and the previous module is kept.
+pub struct ShaderManager {
+    shaders: Vec<ShaderEntry>,
+    poll_interval: Duration,
+    last_poll: Option<Instant>,
+}
+
+impl Default for ShaderManager {
+    fn default() -> Self {
+        Self {
+            shaders: vec![],
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            last_poll: None,
+        }
+    }
+}
+
+impl ShaderManager {
+    /// Sets how often [`ShaderManager::poll`] actually checks the files.
+    pub fn set_poll_interval(&mut self, interval: Duration) {
+        self.poll_interval = interval;
+    }
+
+    /// Loads a shader, or returns the existing one if the file was loaded before.
+    pub fn load(
+        &mut self,
+        device: &wgpu::Device,
+        path: impl AsRef<Path>,
+    ) -> Result<ShaderId, PineError> {
+        self.load_variant(device, path, &ShaderDefs::default())
+    }
+
+    /// Loads a variant of a shader, preprocessed with the given definitions.
+    ///
+    /// Each combination of file and definitions is compiled once; loading it again returns the
+    /// existing shader.
+    pub fn load_variant(
+        &mut self,
+        device: &wgpu::Device,
+        path: impl AsRef<Path>,
+        defs: &ShaderDefs,
+    ) -> Result<ShaderId, PineError> {
+        // Canonical paths make "a/../b.wgsl" and "b.wgsl" the same shader, as in the preprocessor.
+        let path = canonicalize(path.as_ref());
+        if let Some(i) = self
+            .shaders
+            .iter()
+            .position(|entry| entry.path == path && entry.defs == *defs)
+        {
+            return Ok(ShaderId(i));
+        }
+
+        let shader = preprocess(&path, defs)?;
+        let module = create_shader(device, &shader)?;
+
+        self.shaders.push(ShaderEntry {
+            path,
+            defs: defs.clone(),
+            files: modified_times(shader.files()),
+            module: Arc::new(module),
+            generation: 0,
+        });
+        Ok(ShaderId(self.shaders.len() - 1))
+    }
+
+    /// Returns the current module of the shader.
+    ///
+    /// The module is shared, so pipelines built from an older version keep working.
+    pub fn module(&self, id: ShaderId) -> Arc<wgpu::ShaderModule> {
+        self.shaders[id.0].module.clone()
+    }
+
+    /// Returns how many times the shader has been reloaded.
+    pub fn generation(&self, id: ShaderId) -> u64 {
+        self.shaders[id.0].generation
+    }
+
+    pub fn path(&self, id: ShaderId) -> &Path {
+        &self.shaders[id.0].path
+    }
+
+    pub fn defs(&self, id: ShaderId) -> &ShaderDefs {
+        &self.shaders[id.0].defs
+    }
+
+    /// Reloads shaders whose files, includes included, changed since they were last loaded.
+    ///
+    /// Does nothing if called again within the poll interval. Returns the shaders that were
+    /// swapped for a new module.
+    pub fn poll(&mut self, device: &wgpu::Device) -> Vec<ShaderId> {
+        let now = Instant::now();
+        if self
+            .last_poll
+            .is_some_and(|last_poll| now.duration_since(last_poll) < self.poll_interval)
+        {
+            return vec![];
+        }
+        self.last_poll = Some(now);
+
+        let mut reloaded = vec![];
+        for (i, entry) in self.shaders.iter_mut().enumerate() {
+            let changed = entry.files.iter().any(|(path, modified)| {
+                let current = modified_time(path);
+                current.is_some() && current != *modified
+            });
+            if !changed {
+                continue;
+            }
+            // Remember the times even if the reload fails, so a broken file is only reported once.
+            let paths: Vec<_> = entry.files.iter().map(|(path, _)| path.clone()).collect();
+            entry.files = modified_times(&paths);
+
+            let result = preprocess(&entry.path, &entry.defs).and_then(|shader| {
+                let module = create_shader(device, &shader)?;
+                Ok((module, shader))
+            });
+
+            match result {
+                Ok((module, shader)) => {
+                    tracing::info!("Reloaded shader {}", entry.path.display());
+                    // Includes may have been added or removed.
+                    entry.files = modified_times(shader.files());
+                    entry.module = Arc::new(module);
+                    entry.generation += 1;
+                    reloaded.push(ShaderId(i));
+                }
+                Err(err) => {
+                    tracing::error!("Keeping previous shader: {}", err);
+                    if let PineError::ShaderValidationError { message, .. } = &err {
+                        tracing::error!("\n{}", message);
+                    }
+                }
+            }
+        }
+        reloaded
+    }
+}
+
+/// Pairs each file with its modification time.
+fn modified_times(paths: &[PathBuf]) -> Vec<(PathBuf, Option<SystemTime>)> {
+    paths
+        .iter()
+        .map(|path| (path.clone(), modified_time(path)))
+        .collect()
+}
+
+/// Returns when the file was last modified, if it can be told.
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capabilities_follow_the_device_features() {
+        use naga::valid::Capabilities;
+
+        assert_eq!(
+            shader_capabilities(wgpu::Features::empty()),
+            Capabilities::empty()
+        );
+        let capabilities =
+            shader_capabilities(wgpu::Features::PUSH_CONSTANTS | wgpu::Features::SHADER_F64);
+        assert_eq!(
+            capabilities,
+            Capabilities::PUSH_CONSTANT | Capabilities::FLOAT64
+        );
+    }
+
+    #[test]
+    fn shaders_are_validated_against_the_given_capabilities() {
+        let path =
+            std::env::temp_dir().join(format!("pine-push-constants-{}.wgsl", std::process::id()));
+        fs::write(
+            &path,
+            "var<push_constant> offset: vec4<f32>;\n\
+             @vertex fn main() -> @builtin(position) vec4<f32> { return offset; }\n",
+        )
+        .unwrap();
+        let shader = preprocess(&path, &ShaderDefs::default());
+        fs::remove_file(&path).unwrap();
+        let shader = shader.unwrap();
+
+        assert!(matches!(
+            validate_wgsl(&shader, shader_capabilities(wgpu::Features::empty())),
+            Err(PineError::ShaderValidationError { .. })
+        ));
+        validate_wgsl(&shader, shader_capabilities(wgpu::Features::PUSH_CONSTANTS)).unwrap();
+    }
+
+    #[test]
+    fn errors_point_at_the_code_as_written() {
+        let path =
+            std::env::temp_dir().join(format!("pine-error-caret-{}.wgsl", std::process::id()));
+        fs::write(&path, "fn f() -> f32 { return SCALE + missing; }\n").unwrap();
+        let defs = ShaderDefs::new().with_value("SCALE", "1024.0");
+        let shader = preprocess(&path, &defs);
+        fs::remove_file(&path).unwrap();
+        let shader = shader.unwrap();
+
+        let Err(PineError::ShaderValidationError { message, .. }) =
+            validate_wgsl(&shader, naga::valid::Capabilities::empty())
+        else {
+            panic!("expected a validation error");
+        };
+        let lines: Vec<_> = message.lines().collect();
+        assert!(lines[0].contains(":1:32:"), "{}", message);
+        assert_eq!(lines[1].trim(), "fn f() -> f32 { return SCALE + missing; }");
+        let caret = lines[2].find('^').unwrap();
+        assert_eq!(&lines[1][caret..caret + 7], "missing");
+    }
 }
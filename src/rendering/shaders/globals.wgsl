@@ -0,0 +1,8 @@
+// Uniforms shared by everything drawn in a frame.
+
+struct Globals {
+    view_proj: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> globals: Globals;
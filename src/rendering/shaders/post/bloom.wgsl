@@ -0,0 +1,59 @@
+// Extracts the bright parts of the scene and blurs them through a chain of ever smaller textures.
+
+#include "../fullscreen.wgsl"
+#include "../color.wgsl"
+
+struct BloomParams {
+    threshold: f32,
+    // How softly colors below the threshold fade in, as a fraction of the threshold.
+    knee: f32,
+};
+
+@group(0) @binding(0)
+var source: texture_2d<f32>;
+@group(0) @binding(1)
+var source_sampler: sampler;
+
+@group(1) @binding(0)
+var<uniform> params: BloomParams;
+
+@fragment
+fn fs_threshold(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(source, source_sampler, in.uv).rgb;
+    let brightness = max(color.r, max(color.g, color.b));
+
+    let knee = params.threshold * params.knee + 0.0001;
+    let soft = clamp(brightness - params.threshold + knee, 0.0, 2.0 * knee);
+    let contribution = max(soft * soft / (4.0 * knee), brightness - params.threshold)
+        / max(brightness, 0.0001);
+    return vec4<f32>(color * contribution, 1.0);
+}
+
+@fragment
+fn fs_downsample(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(source));
+
+    // Four bilinear taps around the center, each averaging four texels.
+    var color = textureSample(source, source_sampler, in.uv + texel * vec2<f32>(-1.0, -1.0)).rgb;
+    color += textureSample(source, source_sampler, in.uv + texel * vec2<f32>(1.0, -1.0)).rgb;
+    color += textureSample(source, source_sampler, in.uv + texel * vec2<f32>(-1.0, 1.0)).rgb;
+    color += textureSample(source, source_sampler, in.uv + texel * vec2<f32>(1.0, 1.0)).rgb;
+    return vec4<f32>(color * 0.25, 1.0);
+}
+
+@fragment
+fn fs_upsample(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(source));
+
+    // A 3x3 tent filter, added onto the larger texture by blending.
+    var color = textureSample(source, source_sampler, in.uv).rgb * 4.0;
+    color += textureSample(source, source_sampler, in.uv + texel * vec2<f32>(-1.0, 0.0)).rgb * 2.0;
+    color += textureSample(source, source_sampler, in.uv + texel * vec2<f32>(1.0, 0.0)).rgb * 2.0;
+    color += textureSample(source, source_sampler, in.uv + texel * vec2<f32>(0.0, -1.0)).rgb * 2.0;
+    color += textureSample(source, source_sampler, in.uv + texel * vec2<f32>(0.0, 1.0)).rgb * 2.0;
+    color += textureSample(source, source_sampler, in.uv + texel * vec2<f32>(-1.0, -1.0)).rgb;
+    color += textureSample(source, source_sampler, in.uv + texel * vec2<f32>(1.0, -1.0)).rgb;
+    color += textureSample(source, source_sampler, in.uv + texel * vec2<f32>(-1.0, 1.0)).rgb;
+    color += textureSample(source, source_sampler, in.uv + texel * vec2<f32>(1.0, 1.0)).rgb;
+    return vec4<f32>(color / 16.0, 1.0);
+}
@@ -0,0 +1,117 @@
+// Applies the post-processing effects to the scene in a single pass.
+//
+// Effects are disabled by setting their strength to zero.
+
+#include "../fullscreen.wgsl"
+#include "../color.wgsl"
+
+struct PostParams {
+    // The size of the target in pixels.
+    resolution: vec2<f32>,
+    bloom_intensity: f32,
+    lut_strength: f32,
+    vignette_intensity: f32,
+    vignette_radius: f32,
+    vignette_softness: f32,
+    // The distance between the red and blue channels at the edges, in pixels.
+    aberration: f32,
+    crt_curvature: f32,
+    scanline_intensity: f32,
+    scanline_count: f32,
+    mask_intensity: f32,
+};
+
+@group(0) @binding(0)
+var scene: texture_2d<f32>;
+@group(0) @binding(1)
+var scene_sampler: sampler;
+
+@group(1) @binding(0)
+var<uniform> params: PostParams;
+
+@group(2) @binding(0)
+var bloom: texture_2d<f32>;
+@group(2) @binding(1)
+var bloom_sampler: sampler;
+
+@group(3) @binding(0)
+var lut: texture_3d<f32>;
+@group(3) @binding(1)
+var lut_sampler: sampler;
+
+const PI: f32 = 3.14159265;
+
+// Bends the image as if shown on a curved screen.
+fn curve(uv: vec2<f32>) -> vec2<f32> {
+    let centered = uv * 2.0 - 1.0;
+    let offset = centered.yx * centered.yx * params.crt_curvature;
+    return (centered + centered * offset) * 0.5 + 0.5;
+}
+
+fn sample_scene(uv: vec2<f32>) -> vec3<f32> {
+    if params.aberration <= 0.0 {
+        return textureSample(scene, scene_sampler, uv).rgb;
+    }
+
+    // Split the channels further apart towards the edges.
+    let direction = (uv - 0.5) * 2.0;
+    let offset = direction * params.aberration / params.resolution;
+    let r = textureSample(scene, scene_sampler, uv + offset).r;
+    let g = textureSample(scene, scene_sampler, uv).g;
+    let b = textureSample(scene, scene_sampler, uv - offset).b;
+    return vec3<f32>(r, g, b);
+}
+
+// Looks up the color in the LUT, which maps sRGB colors to graded sRGB colors.
+fn grade(color: vec3<f32>) -> vec3<f32> {
+    let size = f32(textureDimensions(lut).x);
+    let srgb = clamp(linear_to_srgb(color), vec3<f32>(0.0), vec3<f32>(1.0));
+    // Sample texel centers, so the corners of the LUT map to black and white.
+    let coords = srgb * ((size - 1.0) / size) + 0.5 / size;
+    let graded = srgb_to_linear(textureSample(lut, lut_sampler, coords).rgb);
+    return mix(color, graded, params.lut_strength);
+}
+
+@fragment
+fn fs_composite(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    var uv = in.uv;
+    if params.crt_curvature > 0.0 {
+        uv = curve(uv);
+    }
+
+    // Sampled unconditionally, as sampling must happen in uniform control flow.
+    var color = sample_scene(uv);
+    color += textureSample(bloom, bloom_sampler, uv).rgb * params.bloom_intensity;
+    let graded = grade(color);
+    if params.lut_strength > 0.0 {
+        color = graded;
+    }
+
+    if params.vignette_intensity > 0.0 {
+        let distance = length(in.uv - 0.5) * 1.41421356;
+        let vignette = smoothstep(
+            params.vignette_radius,
+            params.vignette_radius - params.vignette_softness,
+            distance,
+        );
+        color *= mix(1.0, vignette, params.vignette_intensity);
+    }
+
+    if params.scanline_intensity > 0.0 {
+        let scanline = sin(uv.y * params.scanline_count * PI) * 0.5 + 0.5;
+        color *= 1.0 - params.scanline_intensity * (1.0 - scanline);
+    }
+
+    if params.mask_intensity > 0.0 {
+        // An aperture grille, tinting each column of pixels red, green or blue.
+        let column = u32(in.clip_position.x) % 3u;
+        var mask = vec3<f32>(1.0 - params.mask_intensity);
+        mask[column] = 1.0;
+        color *= mask;
+    }
+
+    if any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) {
+        color = vec3<f32>(0.0);
+    }
+    return vec4<f32>(color, 1.0);
+}
@@ -0,0 +1,49 @@
+// Draws instanced, textured and tinted quads. Colored rectangles use a plain white texture.
+
+#include "globals.wgsl"
+
+@group(1) @binding(0)
+var quad_texture: texture_2d<f32>;
+@group(1) @binding(1)
+var quad_sampler: sampler;
+
+struct VertexInput {
+    // A corner of the unit quad, from -0.5 to 0.5.
+    @location(0) position: vec2<f32>,
+    // The same corner in texture space, from 0 to 1.
+    @location(1) uv: vec2<f32>,
+};
+
+struct InstanceInput {
+    // The affine transform of the quad, mapping the unit quad into world space.
+    @location(2) x_axis: vec2<f32>,
+    @location(3) y_axis: vec2<f32>,
+    @location(4) translation: vec2<f32>,
+    // The texture region to sample as (min u, min v, max u, max v).
+    @location(5) uv_rect: vec4<f32>,
+    @location(6) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(vertex: VertexInput, instance: InstanceInput) -> VertexOutput {
+    let world = instance.x_axis * vertex.position.x
+        + instance.y_axis * vertex.position.y
+        + instance.translation;
+
+    var out: VertexOutput;
+    out.clip_position = globals.view_proj * vec4<f32>(world, 0.0, 1.0);
+    out.uv = mix(instance.uv_rect.xy, instance.uv_rect.zw, vertex.uv);
+    out.color = instance.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(quad_texture, quad_sampler, in.uv) * in.color;
+}
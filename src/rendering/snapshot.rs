@@ -0,0 +1,350 @@
+use super::{frame_data::FrameData, Renderer};
+use crate::error::PineError;
+
+use image::{Rgba, RgbaImage};
+
+use std::path::{Path, PathBuf};
+
+/// Set this environment variable to overwrite stored snapshots with the rendered frames.
+pub const UPDATE_SNAPSHOTS_ENV: &str = "PINE_UPDATE_SNAPSHOTS";
+
+/// The largest possible YIQ distance between two colors, used for normalizing.
+const MAX_YIQ_DELTA: f32 = 35215.0;
+
+#[derive(Debug, Clone)]
+/// The Snapshot config defines how strictly rendered frames are compared against stored ones.
+pub struct SnapshotConfig {
+    dir: PathBuf,
+    tolerance: u8,
+    perceptual_threshold: f32,
+    max_mismatch_ratio: f32,
+}
+
+impl Default for SnapshotConfig {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("snapshots"),
+            tolerance: 2,
+            perceptual_threshold: 0.1,
+            max_mismatch_ratio: 0.0,
+        }
+    }
+}
+
+impl SnapshotConfig {
+    /// Sets the directory holding the stored snapshots.
+    pub fn with_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dir = dir.into();
+        self
+    }
+
+    /// Sets the largest per-channel difference for which two pixels are considered identical.
+    pub fn with_tolerance(mut self, tolerance: u8) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Sets the perceptual difference (0 to 1) above which a pixel counts as mismatched.
+    pub fn with_perceptual_threshold(mut self, threshold: f32) -> Self {
+        self.perceptual_threshold = threshold.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sets the ratio (0 to 1) of mismatched pixels still accepted as a match.
+    pub fn with_max_mismatch_ratio(mut self, ratio: f32) -> Self {
+        self.max_mismatch_ratio = ratio.clamp(0.0, 1.0);
+        self
+    }
+}
+
+#[derive(Debug)]
+/// The result of comparing two frames.
+pub struct SnapshotDiff {
+    /// Pixels that differ perceptually.
+    pub mismatched_pixels: usize,
+    /// Pixels that differ, but only within the perceptual threshold.
+    pub similar_pixels: usize,
+    pub total_pixels: usize,
+    /// The largest perceptual difference found, from 0 to 1.
+    pub max_delta: f32,
+    /// A faded copy of the expected frame with similar pixels in yellow and mismatches in red.
+    pub image: RgbaImage,
+}
+
+impl SnapshotDiff {
+    /// Returns the ratio of mismatched pixels.
+    pub fn mismatch_ratio(&self) -> f32 {
+        if self.total_pixels == 0 {
+            return 0.0;
+        }
+        self.mismatched_pixels as f32 / self.total_pixels as f32
+    }
+}
+
+/// Compares two frames pixel by pixel.
+///
+/// Pixels within the per-channel tolerance are identical. Other pixels are compared by their
+/// distance in YIQ color space, which follows human perception more closely than RGB.
+pub fn compare(
+    actual: &RgbaImage,
+    expected: &RgbaImage,
+    config: &SnapshotConfig,
+) -> Result<SnapshotDiff, PineError> {
+    if actual.dimensions() != expected.dimensions() {
+        return Err(PineError::SnapshotSizeError {
+            actual: actual.dimensions(),
+            expected: expected.dimensions(),
+        });
+    }
+
+    let mut image = RgbaImage::new(expected.width(), expected.height());
+    let mut mismatched_pixels = 0;
+    let mut similar_pixels = 0;
+    let mut max_delta: f32 = 0.0;
+
+    for (x, y, expected_pixel) in expected.enumerate_pixels() {
+        let actual_pixel = actual.get_pixel(x, y);
+
+        let within_tolerance = actual_pixel
+            .0
+            .iter()
+            .zip(expected_pixel.0.iter())
+            .all(|(a, e)| a.abs_diff(*e) <= config.tolerance);
+
+        let diff_pixel = if within_tolerance {
+            fade(expected_pixel)
+        } else {
+            let delta = perceptual_delta(actual_pixel, expected_pixel);
+            max_delta = max_delta.max(delta);
+
+            if delta > config.perceptual_threshold {
+                mismatched_pixels += 1;
+                Rgba([255, 0, 0, 255])
+            } else {
+                similar_pixels += 1;
+                Rgba([255, 255, 0, 255])
+            }
+        };
+        image.put_pixel(x, y, diff_pixel);
+    }
+
+    Ok(SnapshotDiff {
+        mismatched_pixels,
+        similar_pixels,
+        total_pixels: (expected.width() * expected.height()) as usize,
+        max_delta,
+        image,
+    })
+}
+
+/// Renders a frame with the given renderer and compares it against the stored snapshot.
+///
+/// The snapshot is stored as `<dir>/<name>.png`. If the `PINE_UPDATE_SNAPSHOTS` environment
+/// variable is set, the rendered frame is stored instead. A missing snapshot is an error
+/// otherwise, so checks can't pass without one checked in. On a mismatch the rendered frame and a
+/// diff image are written next to the snapshot as `<name>.actual.png` and `<name>.diff.png`.
+pub fn assert_snapshot(
+    renderer: &dyn Renderer,
+    frame_data: &FrameData,
+    name: &str,
+    config: &SnapshotConfig,
+) -> Result<SnapshotDiff, PineError> {
+    renderer.render(frame_data)?;
+    let actual = renderer.capture()?;
+    assert_image_snapshot(&actual, name, config)
+}
+
+/// Compares an already captured frame against the stored snapshot.
+///
+/// See [`assert_snapshot`] for how snapshots are stored.
+pub fn assert_image_snapshot(
+    actual: &RgbaImage,
+    name: &str,
+    config: &SnapshotConfig,
+) -> Result<SnapshotDiff, PineError> {
+    let expected_path = snapshot_path(&config.dir, name, "png");
+
+    if std::env::var_os(UPDATE_SNAPSHOTS_ENV).is_some() {
+        std::fs::create_dir_all(&config.dir).map_err(PineError::IoError)?;
+        actual.save(&expected_path).map_err(PineError::ImageError)?;
+        tracing::info!("Stored snapshot {:?}", expected_path);
+
+        return compare(actual, actual, config);
+    }
+    if !expected_path.exists() {
+        return Err(PineError::MissingSnapshotError(expected_path));
+    }
+
+    let expected = image::open(&expected_path)
+        .map_err(PineError::ImageError)?
+        .into_rgba8();
+    let diff = compare(actual, &expected, config)?;
+
+    if diff.mismatch_ratio() > config.max_mismatch_ratio {
+        let actual_path = snapshot_path(&config.dir, name, "actual.png");
+        let diff_path = snapshot_path(&config.dir, name, "diff.png");
+        actual.save(&actual_path).map_err(PineError::ImageError)?;
+        diff.image.save(&diff_path).map_err(PineError::ImageError)?;
+
+        tracing::error!(
+            "Snapshot {} mismatched in {} of {} pixels, see {:?}",
+            name,
+            diff.mismatched_pixels,
+            diff.total_pixels,
+            diff_path
+        );
+        return Err(PineError::SnapshotMismatchError {
+            name: name.to_string(),
+            mismatched_pixels: diff.mismatched_pixels,
+        });
+    }
+
+    Ok(diff)
+}
+
+/// Returns the path of a snapshot file with the given extension.
+fn snapshot_path(dir: &Path, name: &str, extension: &str) -> PathBuf {
+    dir.join(format!("{}.{}", name, extension))
+}
+
+/// Returns the perceptual difference between two pixels, from 0 to 1.
+///
+/// Pixels are blended onto white before being compared, so differences in fully transparent
+/// pixels don't count.
+fn perceptual_delta(a: &Rgba<u8>, b: &Rgba<u8>) -> f32 {
+    let (ay, ai, aq) = to_yiq(a);
+    let (by, bi, bq) = to_yiq(b);
+    let (dy, di, dq) = (ay - by, ai - bi, aq - bq);
+
+    (0.5053 * dy * dy + 0.299 * di * di + 0.1957 * dq * dq) / MAX_YIQ_DELTA
+}
+
+/// Converts a pixel blended onto white into YIQ color space.
+fn to_yiq(pixel: &Rgba<u8>) -> (f32, f32, f32) {
+    let alpha = pixel[3] as f32 / 255.0;
+    let blend = |channel: u8| 255.0 + (channel as f32 - 255.0) * alpha;
+    let (r, g, b) = (blend(pixel[0]), blend(pixel[1]), blend(pixel[2]));
+
+    let y = r * 0.298_895 + g * 0.586_622 + b * 0.114_482;
+    let i = r * 0.595_978 - g * 0.274_176 - b * 0.321_802;
+    let q = r * 0.211_470 - g * 0.522_617 + b * 0.311_147;
+    (y, i, q)
+}
+
+/// Returns a faded grayscale version of the pixel, used as the diff image background.
+fn fade(pixel: &Rgba<u8>) -> Rgba<u8> {
+    let (y, _, _) = to_yiq(pixel);
+    let faded = (255.0 + (y - 255.0) * 0.1) as u8;
+    Rgba([faded, faded, faded, 255])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid(width: u32, height: u32, pixel: [u8; 4]) -> RgbaImage {
+        RgbaImage::from_pixel(width, height, Rgba(pixel))
+    }
+
+    #[test]
+    fn identical_images_match() {
+        let image = solid(4, 4, [10, 20, 30, 255]);
+        let diff = compare(&image, &image, &SnapshotConfig::default()).unwrap();
+
+        assert_eq!(diff.mismatched_pixels, 0);
+        assert_eq!(diff.similar_pixels, 0);
+        assert_eq!(diff.total_pixels, 16);
+        assert_eq!(diff.max_delta, 0.0);
+    }
+
+    #[test]
+    fn differences_within_tolerance_are_identical() {
+        let expected = solid(2, 2, [100, 100, 100, 255]);
+        let actual = solid(2, 2, [102, 98, 100, 255]);
+        let config = SnapshotConfig::default().with_tolerance(2);
+
+        let diff = compare(&actual, &expected, &config).unwrap();
+        assert_eq!(diff.mismatched_pixels + diff.similar_pixels, 0);
+
+        let diff = compare(&actual, &expected, &config.with_tolerance(1)).unwrap();
+        assert_eq!(diff.similar_pixels, 4);
+    }
+
+    #[test]
+    fn perceptual_threshold_separates_similar_from_mismatched() {
+        let expected = solid(1, 1, [100, 100, 100, 255]);
+        let slightly_off = solid(1, 1, [110, 100, 100, 255]);
+        let far_off = solid(1, 1, [255, 0, 0, 255]);
+        let config = SnapshotConfig::default()
+            .with_tolerance(0)
+            .with_perceptual_threshold(0.1);
+
+        let diff = compare(&slightly_off, &expected, &config).unwrap();
+        assert_eq!((diff.similar_pixels, diff.mismatched_pixels), (1, 0));
+        assert!(diff.max_delta > 0.0 && diff.max_delta <= 0.1);
+
+        let diff = compare(&far_off, &expected, &config).unwrap();
+        assert_eq!((diff.similar_pixels, diff.mismatched_pixels), (0, 1));
+        assert!(diff.max_delta > 0.1);
+        assert_eq!(diff.mismatch_ratio(), 1.0);
+    }
+
+    #[test]
+    fn transparent_pixels_compare_equal() {
+        let expected = solid(1, 1, [0, 0, 0, 0]);
+        let actual = solid(1, 1, [255, 0, 0, 0]);
+        let config = SnapshotConfig::default().with_tolerance(0);
+
+        let diff = compare(&actual, &expected, &config).unwrap();
+        assert_eq!(diff.mismatched_pixels, 0);
+        assert_eq!(diff.max_delta, 0.0);
+    }
+
+    #[test]
+    fn size_mismatch_is_an_error() {
+        let result = compare(
+            &solid(2, 3, [0; 4]),
+            &solid(3, 2, [0; 4]),
+            &SnapshotConfig::default(),
+        );
+
+        assert!(matches!(
+            result,
+            Err(PineError::SnapshotSizeError {
+                actual: (2, 3),
+                expected: (3, 2),
+            })
+        ));
+    }
+
+    #[test]
+    fn diff_image_marks_similar_and_mismatched_pixels() {
+        let expected = solid(3, 1, [100, 100, 100, 255]);
+        let mut actual = expected.clone();
+        actual.put_pixel(1, 0, Rgba([110, 100, 100, 255]));
+        actual.put_pixel(2, 0, Rgba([255, 0, 0, 255]));
+        let config = SnapshotConfig::default().with_tolerance(0);
+
+        let diff = compare(&actual, &expected, &config).unwrap();
+        assert_eq!(diff.image.dimensions(), (3, 1));
+        assert_eq!(
+            *diff.image.get_pixel(0, 0),
+            fade(&Rgba([100, 100, 100, 255]))
+        );
+        assert_eq!(*diff.image.get_pixel(1, 0), Rgba([255, 255, 0, 255]));
+        assert_eq!(*diff.image.get_pixel(2, 0), Rgba([255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn missing_snapshot_is_an_error() {
+        if std::env::var_os(UPDATE_SNAPSHOTS_ENV).is_some() {
+            return;
+        }
+        let dir = std::env::temp_dir().join("pine-missing-snapshots");
+        let config = SnapshotConfig::default().with_dir(&dir);
+
+        let result = assert_image_snapshot(&solid(1, 1, [0; 4]), "missing", &config);
+        assert!(matches!(result, Err(PineError::MissingSnapshotError(_))));
+        assert!(!dir.join("missing.png").exists());
+    }
+}
@@ -0,0 +1,94 @@
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// How finished frames are presented to a window.
+pub enum PresentMode {
+    /// Wait for the vertical blank. No tearing, supported everywhere.
+    #[default]
+    Vsync,
+    /// Replace the queued frame with the newest one. No tearing, lower latency than vsync.
+    Mailbox,
+    /// Present right away. Lowest latency, but may tear.
+    Immediate,
+}
+
+impl PresentMode {
+    /// Returns the wgpu present modes to try, in order of preference.
+    fn candidates(self) -> &'static [wgpu::PresentMode] {
+        match self {
+            PresentMode::Vsync => &[wgpu::PresentMode::Fifo],
+            PresentMode::Mailbox => &[wgpu::PresentMode::Mailbox, wgpu::PresentMode::Fifo],
+            PresentMode::Immediate => &[
+                wgpu::PresentMode::Immediate,
+                wgpu::PresentMode::Mailbox,
+                wgpu::PresentMode::Fifo,
+            ],
+        }
+    }
+
+    /// Picks the closest mode supported by the surface.
+    ///
+    /// Fifo is guaranteed to be supported, so vsync is the last resort.
+    pub(crate) fn select(self, supported: &[wgpu::PresentMode]) -> wgpu::PresentMode {
+        let mode = self
+            .candidates()
+            .iter()
+            .copied()
+            .find(|mode| supported.contains(mode))
+            .unwrap_or(wgpu::PresentMode::Fifo);
+
+        if mode != self.candidates()[0] {
+            tracing::warn!("Present mode {:?} not supported, using {:?}", self, mode);
+        }
+        mode
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// The Surface config defines customizable options for presenting to a window.
+pub struct SurfaceConfig {
+    pub(crate) present_mode: PresentMode,
+    pub(crate) transparent: bool,
+}
+
+impl SurfaceConfig {
+    /// Sets how frames are presented.
+    pub fn with_present_mode(mut self, present_mode: PresentMode) -> Self {
+        self.present_mode = present_mode;
+        self
+    }
+
+    /// Sets whether the surface should blend with whatever is behind the window.
+    pub fn with_transparent(mut self, transparent: bool) -> Self {
+        self.transparent = transparent;
+        self
+    }
+
+    /// Picks the alpha mode matching the transparency of the surface.
+    pub(crate) fn select_alpha_mode(
+        &self,
+        supported: &[wgpu::CompositeAlphaMode],
+    ) -> wgpu::CompositeAlphaMode {
+        let preferred: &[wgpu::CompositeAlphaMode] = if self.transparent {
+            &[
+                wgpu::CompositeAlphaMode::PreMultiplied,
+                wgpu::CompositeAlphaMode::PostMultiplied,
+                wgpu::CompositeAlphaMode::Inherit,
+            ]
+        } else {
+            &[wgpu::CompositeAlphaMode::Opaque]
+        };
+
+        preferred
+            .iter()
+            .copied()
+            .find(|mode| supported.contains(mode))
+            .unwrap_or_else(|| {
+                if self.transparent {
+                    tracing::warn!("Surface doesn't support transparency");
+                }
+                supported
+                    .first()
+                    .copied()
+                    .unwrap_or(wgpu::CompositeAlphaMode::Auto)
+            })
+    }
+}
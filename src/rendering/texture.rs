@@ -0,0 +1,472 @@
+use wgpu::util::DeviceExt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// A handle to a texture owned by a renderer.
+///
+/// Handles are cheap to copy, so any number of sprites can share the same texture.
+pub struct TextureHandle(pub(crate) usize);
+
+impl TextureHandle {
+    /// The plain white texture every renderer starts out with. Used for colored rectangles.
+    pub const WHITE: Self = Self(0);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// A rectangular region of a texture in normalized texture coordinates.
+pub struct TextureRegion {
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+}
+
+impl TextureRegion {
+    /// The region covering the entire texture.
+    pub const FULL: Self = Self {
+        min: [0.0, 0.0],
+        max: [1.0, 1.0],
+    };
+
+    /// Constructs a region from a rectangle in pixels of a texture with the given size.
+    pub fn from_pixels(x: u32, y: u32, width: u32, height: u32, texture_size: (u32, u32)) -> Self {
+        let (texture_width, texture_height) = (texture_size.0 as f32, texture_size.1 as f32);
+        Self {
+            min: [x as f32 / texture_width, y as f32 / texture_height],
+            max: [
+                (x + width) as f32 / texture_width,
+                (y + height) as f32 / texture_height,
+            ],
+        }
+    }
+}
+
+impl Default for TextureRegion {
+    fn default() -> Self {
+        Self::FULL
+    }
+}
+
+impl From<TextureRegion> for [f32; 4] {
+    fn from(region: TextureRegion) -> Self {
+        [region.min[0], region.min[1], region.max[0], region.max[1]]
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A texture split into regions, e.g. the tiles of a tileset.
+///
+/// Sprites drawn from the same atlas share a texture and therefore end up in the same batch.
+pub struct TextureAtlas {
+    texture: TextureHandle,
+    regions: Vec<TextureRegion>,
+}
+
+impl TextureAtlas {
+    /// Constructs an atlas without any regions.
+    pub fn new(texture: TextureHandle) -> Self {
+        Self {
+            texture,
+            regions: vec![],
+        }
+    }
+
+    /// Constructs an atlas from a grid of equally sized tiles, indexed row by row.
+    pub fn from_grid(
+        texture: TextureHandle,
+        texture_size: (u32, u32),
+        tile_size: (u32, u32),
+        columns: u32,
+        rows: u32,
+    ) -> Self {
+        let mut atlas = Self::new(texture);
+        for row in 0..rows {
+            for column in 0..columns {
+                atlas.add_region(TextureRegion::from_pixels(
+                    column * tile_size.0,
+                    row * tile_size.1,
+                    tile_size.0,
+                    tile_size.1,
+                    texture_size,
+                ));
+            }
+        }
+        atlas
+    }
+
+    /// Adds a region to the atlas and returns its index.
+    pub fn add_region(&mut self, region: TextureRegion) -> usize {
+        self.regions.push(region);
+        self.regions.len() - 1
+    }
+
+    /// Returns the texture of the atlas.
+    pub fn texture(&self) -> TextureHandle {
+        self.texture
+    }
+
+    /// Returns the region at the given index, if any.
+    pub fn region(&self, index: usize) -> Option<TextureRegion> {
+        self.regions.get(index).copied()
+    }
+
+    /// Returns the number of regions in the atlas.
+    pub fn len(&self) -> usize {
+        self.regions.len()
+    }
+
+    /// Returns whether the atlas has no regions.
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+/// How a texture is filtered when it's drawn at a different size than its own.
+pub enum TextureFilter {
+    /// Picks the closest texel. Keeps pixel art crisp.
+    Nearest,
+    /// Blends neighbouring texels.
+    #[default]
+    Linear,
+}
+
+impl From<TextureFilter> for wgpu::FilterMode {
+    fn from(filter: TextureFilter) -> Self {
+        match filter {
+            TextureFilter::Nearest => wgpu::FilterMode::Nearest,
+            TextureFilter::Linear => wgpu::FilterMode::Linear,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Options for uploading a texture.
+pub struct TextureOptions {
+    pub filter: TextureFilter,
+    /// Whether to generate a full mipmap chain.
+    pub mipmaps: bool,
+}
+
+impl Default for TextureOptions {
+    fn default() -> Self {
+        Self {
+            filter: TextureFilter::Linear,
+            mipmaps: true,
+        }
+    }
+}
+
+impl TextureOptions {
+    /// Options suited for pixel art: nearest filtering and no mipmaps.
+    ///
+    /// Also the right choice for atlases, as mipmaps blend neighbouring regions together.
+    pub fn pixel_art() -> Self {
+        Self {
+            filter: TextureFilter::Nearest,
+            mipmaps: false,
+        }
+    }
+
+    /// Sets the texture filter.
+    pub fn with_filter(mut self, filter: TextureFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Sets whether to generate mipmaps.
+    pub fn with_mipmaps(mut self, mipmaps: bool) -> Self {
+        self.mipmaps = mipmaps;
+        self
+    }
+}
+
+#[derive(Debug)]
+/// A texture uploaded to the GPU along with the bind group used to draw with it.
+pub struct Texture {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub bind_group: wgpu::BindGroup,
+    pub size: (u32, u32),
+}
+
+impl Texture {
+    /// Uploads the given image to the GPU, generating mipmaps if asked to.
+    pub fn from_image(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        image: &image::RgbaImage,
+        mipmaps: bool,
+        label: Option<&str>,
+    ) -> Self {
+        let (width, height) = image.dimensions();
+        let mip_level_count = if mipmaps {
+            width.max(height).max(1).ilog2() + 1
+        } else {
+            1
+        };
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        Self::write_mip_level(queue, &texture, 0, image);
+        for (level, level_image) in (1..).zip(mip_levels(image, mip_level_count)) {
+            Self::write_mip_level(queue, &texture, level, &level_image);
+        }
+
+        Self::with_bind_group(device, layout, sampler, texture, (width, height), label)
+    }
+
+    /// Creates a texture of the given size and format that passes can draw into and sample from.
+    pub fn render_target(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        size: (u32, u32),
+        format: wgpu::TextureFormat,
+        label: Option<&str>,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size: wgpu::Extent3d {
+                width: size.0,
+                height: size.1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        Self::with_bind_group(device, layout, sampler, texture, size, label)
+    }
+
+    /// Uploads RGBA texels into a 3D texture, the slices following one another.
+    pub fn from_volume(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        size: (u32, u32, u32),
+        data: &[u8],
+        label: Option<&str>,
+    ) -> Self {
+        let texture = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label,
+                size: wgpu::Extent3d {
+                    width: size.0,
+                    height: size.1,
+                    depth_or_array_layers: size.2,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D3,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            data,
+        );
+        Self::with_bind_group(device, layout, sampler, texture, (size.0, size.1), label)
+    }
+
+    /// Wraps a texture along with a bind group of its view and the sampler.
+    fn with_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        texture: wgpu::Texture,
+        size: (u32, u32),
+        label: Option<&str>,
+    ) -> Self {
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label,
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+        });
+
+        Self {
+            texture,
+            view,
+            bind_group,
+            size,
+        }
+    }
+
+    /// Creates a sampler using the given filter.
+    pub fn create_sampler(device: &wgpu::Device, filter: TextureFilter) -> wgpu::Sampler {
+        device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some(match filter {
+                TextureFilter::Nearest => "Nearest sampler",
+                TextureFilter::Linear => "Linear sampler",
+            }),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: filter.into(),
+            min_filter: filter.into(),
+            mipmap_filter: filter.into(),
+            ..Default::default()
+        })
+    }
+
+    /// Uploads an image into a single mip level of the texture.
+    fn write_mip_level(
+        queue: &wgpu::Queue,
+        texture: &wgpu::Texture,
+        level: u32,
+        image: &image::RgbaImage,
+    ) {
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture,
+                mip_level: level,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            image,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * image.width()),
+                rows_per_image: Some(image.height()),
+            },
+            wgpu::Extent3d {
+                width: image.width(),
+                height: image.height(),
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+}
+
+/// Downsamples the image into the mip levels below the full-size one, halving the size each level
+/// until 1x1.
+///
+/// Filtering happens on linear colors, as averaging sRGB values darkens minified textures.
+fn mip_levels(image: &image::RgbaImage, mip_level_count: u32) -> Vec<image::RgbaImage> {
+    let (width, height) = image.dimensions();
+    let mut level_image = image::Rgba32FImage::from_fn(width, height, |x, y| {
+        let [r, g, b, a] = image.get_pixel(x, y).0;
+        image::Rgba([
+            srgb_to_linear(r),
+            srgb_to_linear(g),
+            srgb_to_linear(b),
+            a as f32 / 255.0,
+        ])
+    });
+
+    // Each level is downsampled from the previous one.
+    let mut levels = vec![];
+    for level in 1..mip_level_count {
+        let (level_width, level_height) = ((width >> level).max(1), (height >> level).max(1));
+        level_image = image::imageops::resize(
+            &level_image,
+            level_width,
+            level_height,
+            image::imageops::FilterType::Triangle,
+        );
+        levels.push(image::RgbaImage::from_fn(
+            level_width,
+            level_height,
+            |x, y| {
+                let [r, g, b, a] = level_image.get_pixel(x, y).0;
+                image::Rgba([
+                    linear_to_srgb(r),
+                    linear_to_srgb(g),
+                    linear_to_srgb(b),
+                    (a.clamp(0.0, 1.0) * 255.0).round() as u8,
+                ])
+            },
+        ));
+    }
+    levels
+}
+
+/// Decodes an sRGB channel into linear light, from 0 to 1.
+fn srgb_to_linear(channel: u8) -> f32 {
+    let c = channel as f32 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Encodes linear light, from 0 to 1, as an sRGB channel.
+fn linear_to_srgb(channel: f32) -> u8 {
+    let c = channel.clamp(0.0, 1.0);
+    let encoded = if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+    (encoded * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn srgb_round_trips() {
+        for channel in 0..=255 {
+            assert_eq!(linear_to_srgb(srgb_to_linear(channel)), channel);
+        }
+    }
+
+    #[test]
+    fn mip_levels_halve_down_to_one_pixel() {
+        let image = image::RgbaImage::new(8, 2);
+        let sizes: Vec<_> = mip_levels(&image, 4)
+            .iter()
+            .map(image::RgbaImage::dimensions)
+            .collect();
+
+        assert_eq!(sizes, [(4, 1), (2, 1), (1, 1)]);
+    }
+
+    #[test]
+    fn mip_levels_average_in_linear_space() {
+        let image = image::RgbaImage::from_fn(2, 2, |x, _| {
+            if x == 0 {
+                image::Rgba([0, 0, 0, 255])
+            } else {
+                image::Rgba([255, 255, 255, 255])
+            }
+        });
+
+        let levels = mip_levels(&image, 2);
+        let [r, g, b, a] = levels[0].get_pixel(0, 0).0;
+        // Half the light of white is 188 in sRGB, averaging the sRGB values would give 128.
+        assert!((186..=190).contains(&r), "got {}", r);
+        assert_eq!((r, g, a), (b, b, 255));
+    }
+}
@@ -0,0 +1,269 @@
+use super::texture::{Texture, TextureFilter, TextureHandle, TextureOptions};
+use crate::error::PineError;
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// What a cached texture was created from.
+enum TextureKey {
+    Path(PathBuf),
+    Name(String),
+}
+
+#[derive(Debug)]
+/// Owns the textures of the renderers and makes sure each image is only uploaded once.
+///
+/// Textures loaded from the same path (or inserted under the same name) with the same options
+/// share one GPU texture, so any number of sprites can use them without extra memory. Render
+/// targets and 3D textures live here as well, so every kind of texture has a [`TextureHandle`].
+pub struct TextureCache {
+    /// Indexed by handle. Removed textures leave a gap, so their handles are never reused.
+    textures: Vec<Option<Texture>>,
+    keys: HashMap<(TextureKey, TextureOptions), TextureHandle>,
+    layout: wgpu::BindGroupLayout,
+    volume_layout: wgpu::BindGroupLayout,
+    nearest_sampler: wgpu::Sampler,
+    linear_sampler: wgpu::Sampler,
+}
+
+impl TextureCache {
+    /// Constructs a new texture cache holding only the white texture.
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let mut cache = Self {
+            textures: vec![],
+            keys: HashMap::new(),
+            layout: create_layout(device, "Texture layout", wgpu::TextureViewDimension::D2),
+            volume_layout: create_layout(
+                device,
+                "3D texture layout",
+                wgpu::TextureViewDimension::D3,
+            ),
+            nearest_sampler: Texture::create_sampler(device, TextureFilter::Nearest),
+            linear_sampler: Texture::create_sampler(device, TextureFilter::Linear),
+        };
+
+        let white = image::RgbaImage::from_pixel(1, 1, image::Rgba([255, 255, 255, 255]));
+        let handle = cache.insert(
+            device,
+            queue,
+            &white,
+            TextureOptions::pixel_art(),
+            Some("White texture"),
+        );
+        debug_assert_eq!(handle, TextureHandle::WHITE);
+
+        cache
+    }
+
+    /// Loads a PNG or JPEG file into a texture.
+    ///
+    /// Returns the existing handle if the file was already loaded with the same options.
+    pub fn load(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: impl AsRef<Path>,
+        options: TextureOptions,
+    ) -> Result<TextureHandle, PineError> {
+        let path = path.as_ref();
+        // Different spellings of the same path should still hit the cache.
+        let canonical = path.canonicalize().map_err(PineError::IoError)?;
+        let key = (TextureKey::Path(canonical), options);
+
+        if let Some(handle) = self.keys.get(&key) {
+            return Ok(*handle);
+        }
+
+        let image = image::open(path)
+            .map_err(PineError::ImageError)?
+            .into_rgba8();
+        let label = path.to_string_lossy();
+        let handle = self.insert(device, queue, &image, options, Some(&label));
+        tracing::debug!("Loaded texture {:?} as {:?}", path, handle);
+
+        self.keys.insert(key, handle);
+        Ok(handle)
+    }
+
+    /// Uploads an image under the given name.
+    ///
+    /// Returns the existing handle if an image was already added under the same name with the
+    /// same options.
+    pub fn add_named(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        name: &str,
+        image: &image::RgbaImage,
+        options: TextureOptions,
+    ) -> TextureHandle {
+        let key = (TextureKey::Name(name.to_string()), options);
+        if let Some(handle) = self.keys.get(&key) {
+            return *handle;
+        }
+
+        let handle = self.insert(device, queue, image, options, Some(name));
+        self.keys.insert(key, handle);
+        handle
+    }
+
+    /// Uploads an image without deduplication.
+    pub fn add(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        image: &image::RgbaImage,
+        options: TextureOptions,
+    ) -> TextureHandle {
+        self.insert(device, queue, image, options, None)
+    }
+
+    /// Creates a texture passes can draw into and sample from, filtered linearly.
+    pub fn add_render_target(
+        &mut self,
+        device: &wgpu::Device,
+        size: (u32, u32),
+        format: wgpu::TextureFormat,
+        label: &str,
+    ) -> TextureHandle {
+        let texture = Texture::render_target(
+            device,
+            &self.layout,
+            &self.linear_sampler,
+            size,
+            format,
+            Some(label),
+        );
+        self.push(texture)
+    }
+
+    /// Uploads RGBA texels into a 3D texture, filtered linearly.
+    pub fn add_3d(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        size: (u32, u32, u32),
+        data: &[u8],
+    ) -> TextureHandle {
+        let texture = Texture::from_volume(
+            device,
+            queue,
+            &self.volume_layout,
+            &self.linear_sampler,
+            size,
+            data,
+            Some("3D texture"),
+        );
+        self.push(texture)
+    }
+
+    /// Removes a texture, returning it unless it was already removed.
+    ///
+    /// The white texture is never removed.
+    pub fn remove(&mut self, handle: TextureHandle) -> Option<Texture> {
+        if handle == TextureHandle::WHITE {
+            return None;
+        }
+        self.keys.retain(|_, cached| *cached != handle);
+        self.textures.get_mut(handle.0)?.take()
+    }
+
+    /// Returns the handle of a texture previously loaded from the given path, if any.
+    pub fn handle_for_path(
+        &self,
+        path: impl AsRef<Path>,
+        options: TextureOptions,
+    ) -> Option<TextureHandle> {
+        let canonical = path.as_ref().canonicalize().ok()?;
+        self.keys
+            .get(&(TextureKey::Path(canonical), options))
+            .copied()
+    }
+
+    /// Returns the texture behind the handle, if any.
+    pub fn get(&self, handle: TextureHandle) -> Option<&Texture> {
+        self.textures.get(handle.0)?.as_ref()
+    }
+
+    /// Returns the bind group layout 2D textures are created with.
+    pub fn layout(&self) -> &wgpu::BindGroupLayout {
+        &self.layout
+    }
+
+    /// Returns the bind group layout 3D textures are created with.
+    pub fn volume_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.volume_layout
+    }
+
+    /// Returns the number of textures in the cache, including the white texture.
+    pub fn len(&self) -> usize {
+        self.textures.iter().flatten().count()
+    }
+
+    /// Returns whether the cache holds no textures.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Uploads the image and stores the resulting texture.
+    fn insert(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        image: &image::RgbaImage,
+        options: TextureOptions,
+        label: Option<&str>,
+    ) -> TextureHandle {
+        let sampler = match options.filter {
+            TextureFilter::Nearest => &self.nearest_sampler,
+            TextureFilter::Linear => &self.linear_sampler,
+        };
+        let texture = Texture::from_image(
+            device,
+            queue,
+            &self.layout,
+            sampler,
+            image,
+            options.mipmaps,
+            label,
+        );
+        self.push(texture)
+    }
+
+    fn push(&mut self, texture: Texture) -> TextureHandle {
+        self.textures.push(Some(texture));
+        TextureHandle(self.textures.len() - 1)
+    }
+}
+
+/// Creates the layout of a texture at binding 0 and its sampler at binding 1.
+fn create_layout(
+    device: &wgpu::Device,
+    label: &str,
+    view_dimension: wgpu::TextureViewDimension,
+) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some(label),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ],
+    })
+}
@@ -0,0 +1,303 @@
+use std::time::{Duration, Instant};
+
+/// How much each new frame contributes to the smoothed frame time.
+const FPS_SMOOTHING: f64 = 0.1;
+
+#[derive(Debug, Clone)]
+/// Frame timing, along with the state of the fixed timestep.
+///
+/// Fixed updates run at a steady rate regardless of the frame rate. Time left over after the
+/// fixed updates of a frame is carried over to the next frame, and exposed as the interpolation
+/// alpha so rendering can blend between the last two fixed states.
+///
+/// The delta is the variable frame time, even while fixed updates run. Systems in
+/// [`Stage::FixedUpdate`](crate::world::schedule::Stage::FixedUpdate) read [`FixedTime`] instead.
+pub struct Time {
+    startup: Instant,
+    last_frame: Instant,
+    delta: Duration,
+    elapsed: Duration,
+    frame_count: u64,
+    smoothed_delta: f64,
+    fixed_delta: Duration,
+    max_fixed_steps: u32,
+    accumulator: Duration,
+    fixed_steps: u32,
+    /// The number of fixed updates run since startup, and the time they simulated.
+    total_fixed_steps: u64,
+    fixed_elapsed: Duration,
+}
+
+impl Time {
+    /// Constructs the timing state for the given fixed update rate in Hz.
+    ///
+    /// At most `max_fixed_steps` fixed updates run per frame. Should a frame take longer than
+    /// that many steps, the excess time is dropped instead of piling up, which would otherwise
+    /// make every following frame slower still.
+    pub(crate) fn new(fixed_rate: f64, max_fixed_steps: u32) -> Self {
+        let now = Instant::now();
+        Self {
+            startup: now,
+            last_frame: now,
+            delta: Duration::ZERO,
+            elapsed: Duration::ZERO,
+            frame_count: 0,
+            smoothed_delta: 0.0,
+            fixed_delta: Duration::from_secs_f64(1.0 / fixed_rate.max(f64::EPSILON)),
+            max_fixed_steps: max_fixed_steps.max(1),
+            accumulator: Duration::ZERO,
+            fixed_steps: 0,
+            total_fixed_steps: 0,
+            fixed_elapsed: Duration::ZERO,
+        }
+    }
+
+    /// Restarts the clock, so time spent before the first frame isn't counted.
+    pub(crate) fn reset(&mut self) {
+        let now = Instant::now();
+        self.startup = now;
+        self.last_frame = now;
+    }
+
+    /// Starts a new frame, adding the time since the last frame to the accumulator.
+    pub(crate) fn advance(&mut self) {
+        self.advance_to(Instant::now());
+    }
+
+    /// Starts a new frame at the given instant.
+    fn advance_to(&mut self, now: Instant) {
+        self.delta = now.duration_since(self.last_frame);
+        self.elapsed = now.duration_since(self.startup);
+        self.last_frame = now;
+        self.frame_count += 1;
+
+        let delta = self.delta.as_secs_f64();
+        self.smoothed_delta = if self.frame_count == 1 {
+            delta
+        } else {
+            self.smoothed_delta + (delta - self.smoothed_delta) * FPS_SMOOTHING
+        };
+
+        let max_accumulated = self.fixed_delta * self.max_fixed_steps;
+        self.accumulator += self.delta;
+        if self.accumulator > max_accumulated {
+            tracing::debug!(
+                "Fixed updates falling behind, dropping {:?}",
+                self.accumulator - max_accumulated
+            );
+            self.accumulator = max_accumulated;
+        }
+        self.fixed_steps = 0;
+    }
+
+    /// Takes a fixed step off the accumulator if there's enough time for one.
+    pub(crate) fn consume_fixed_step(&mut self) -> bool {
+        if self.accumulator < self.fixed_delta {
+            return false;
+        }
+
+        self.accumulator -= self.fixed_delta;
+        self.fixed_steps += 1;
+        self.total_fixed_steps += 1;
+        self.fixed_elapsed += self.fixed_delta;
+        true
+    }
+
+    /// Returns the time between the start of the last frame and the current one.
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    /// Returns the time since the first frame started.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn elapsed_seconds(&self) -> f32 {
+        self.elapsed.as_secs_f32()
+    }
+
+    /// Returns the number of frames started so far, including the current one.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Returns the frame rate, smoothed over the last few dozen frames.
+    pub fn fps(&self) -> f32 {
+        if self.smoothed_delta > 0.0 {
+            (1.0 / self.smoothed_delta) as f32
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns the time simulated by each fixed update.
+    pub fn fixed_delta(&self) -> Duration {
+        self.fixed_delta
+    }
+
+    pub fn fixed_delta_seconds(&self) -> f32 {
+        self.fixed_delta.as_secs_f32()
+    }
+
+    /// Returns the number of fixed updates run so far in the current frame.
+    pub fn fixed_steps(&self) -> u32 {
+        self.fixed_steps
+    }
+
+    /// Returns how far the current frame is between the last fixed update and the next one,
+    /// from 0 to 1.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator.as_secs_f64() / self.fixed_delta.as_secs_f64()) as f32
+    }
+
+    /// Returns the clock of the fixed updates, as of the last one.
+    pub fn fixed_time(&self) -> FixedTime {
+        FixedTime {
+            delta: self.fixed_delta,
+            elapsed: self.fixed_elapsed,
+            step_count: self.total_fixed_steps,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The clock of the fixed timestep, which every fixed update advances by the same step.
+///
+/// Updated before each run of [`Stage::FixedUpdate`](crate::world::schedule::Stage::FixedUpdate),
+/// so its systems can read it as a resource the way other stages read [`Time`].
+pub struct FixedTime {
+    delta: Duration,
+    elapsed: Duration,
+    step_count: u64,
+}
+
+impl FixedTime {
+    /// Returns the time simulated by each fixed update.
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    /// Returns the time simulated so far, up to and including the current fixed update.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn elapsed_seconds(&self) -> f32 {
+        self.elapsed.as_secs_f32()
+    }
+
+    /// Returns the number of fixed updates run so far, including the current one.
+    pub fn step_count(&self) -> u64 {
+        self.step_count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Advances the time by the given number of milliseconds since the last frame.
+    fn advance_ms(time: &mut Time, millis: u64) {
+        let now = time.last_frame + Duration::from_millis(millis);
+        time.advance_to(now);
+    }
+
+    /// Runs all fixed steps due, returning how many ran.
+    fn run_fixed_steps(time: &mut Time) -> u32 {
+        let mut steps = 0;
+        while time.consume_fixed_step() {
+            steps += 1;
+        }
+        steps
+    }
+
+    #[test]
+    fn frames_track_delta_and_elapsed_time() {
+        let mut time = Time::new(50.0, 5);
+        advance_ms(&mut time, 10);
+        advance_ms(&mut time, 30);
+
+        assert_eq!(time.delta(), Duration::from_millis(30));
+        assert_eq!(time.elapsed(), Duration::from_millis(40));
+        assert_eq!(time.frame_count(), 2);
+    }
+
+    #[test]
+    fn fixed_steps_consume_the_accumulated_time() {
+        let mut time = Time::new(50.0, 5);
+        assert_eq!(time.fixed_delta(), Duration::from_millis(20));
+
+        advance_ms(&mut time, 50);
+        assert_eq!(run_fixed_steps(&mut time), 2);
+        assert_eq!(time.fixed_steps(), 2);
+        assert!((time.alpha() - 0.5).abs() < 1e-4);
+
+        // The leftover 10ms carry over into the next frame.
+        advance_ms(&mut time, 10);
+        assert_eq!(time.fixed_steps(), 0);
+        assert_eq!(run_fixed_steps(&mut time), 1);
+        assert!(time.alpha().abs() < 1e-4);
+    }
+
+    #[test]
+    fn fixed_time_advances_by_the_fixed_step() {
+        let mut time = Time::new(50.0, 5);
+        assert_eq!(time.fixed_time().step_count(), 0);
+
+        advance_ms(&mut time, 45);
+        assert!(time.consume_fixed_step());
+        let fixed = time.fixed_time();
+        assert_eq!(fixed.delta(), Duration::from_millis(20));
+        assert_eq!(fixed.elapsed(), Duration::from_millis(20));
+        assert_eq!(fixed.step_count(), 1);
+
+        // Unlike the frame delta, which covers the whole frame.
+        assert_eq!(time.delta(), Duration::from_millis(45));
+        run_fixed_steps(&mut time);
+        advance_ms(&mut time, 20);
+        run_fixed_steps(&mut time);
+        assert_eq!(time.fixed_time().elapsed(), Duration::from_millis(60));
+        assert_eq!(time.fixed_time().step_count(), 3);
+    }
+
+    #[test]
+    fn accumulator_is_capped_at_max_steps() {
+        let mut time = Time::new(50.0, 3);
+
+        advance_ms(&mut time, 1000);
+        assert_eq!(run_fixed_steps(&mut time), 3);
+        assert_eq!(time.alpha(), 0.0);
+
+        // The dropped time doesn't catch up later.
+        advance_ms(&mut time, 20);
+        assert_eq!(run_fixed_steps(&mut time), 1);
+    }
+
+    #[test]
+    fn fps_is_smoothed() {
+        let mut time = Time::new(50.0, 5);
+        assert_eq!(time.fps(), 0.0);
+
+        advance_ms(&mut time, 10);
+        assert!((time.fps() - 100.0).abs() < 1e-3);
+
+        // A single slow frame only moves the average by the smoothing factor.
+        advance_ms(&mut time, 110);
+        let expected = 1.0 / (0.01 + (0.11 - 0.01) * FPS_SMOOTHING);
+        assert!((time.fps() as f64 - expected).abs() < 1e-3);
+
+        for _ in 0..200 {
+            advance_ms(&mut time, 20);
+        }
+        assert!((time.fps() - 50.0).abs() < 0.01);
+    }
+}
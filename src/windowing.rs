@@ -1,93 +1,431 @@
 use crate::{
     error::PineError,
-    rendering::{color::Color, Renderer, Renderer2D},
+    rendering::{
+        color::Color,
+        gpu::GpuContext,
+        post::PostProcessing,
+        recording::{FrameRecorder, RecordingRenderer},
+        surface::{PresentMode, SurfaceConfig},
+        Renderer, Renderer2D,
+    },
 };
 
 use winit::{
-    dpi::LogicalSize,
+    dpi::{LogicalPosition, LogicalSize},
     event_loop::EventLoopWindowTarget,
-    window::{Window as WinitWindow, WindowBuilder},
+    monitor::VideoMode,
+    window::{
+        CursorGrabMode, Fullscreen, Icon, Window as WinitWindow, WindowBuilder, WindowId,
+        WindowLevel,
+    },
+};
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
 };
 
 #[derive(Debug)]
 /// The Window struct holds a handle to the winit Window as well as the Renderer attached to said
 /// window (among other things).
 pub struct Window {
-    pub handle: WinitWindow,
+    /// The winit window, shared with the surface of the renderer.
+    pub handle: Arc<WinitWindow>,
     pub renderer: Box<dyn Renderer>,
     pub clear_color: Color,
+    /// The key the window was given in its config, if any.
+    pub key: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Changes in the lifecycle of a window.
+pub enum WindowLifecycleEvent {
+    /// The window was opened, either from the config or at runtime.
+    Opened { id: WindowId, key: Option<String> },
+    /// The window gained or lost focus.
+    Focused {
+        id: WindowId,
+        key: Option<String>,
+        focused: bool,
+    },
+    /// The window was closed, either by the user or programmatically.
+    Closed { id: WindowId, key: Option<String> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A window was resized, in physical pixels.
+pub struct WindowResized {
+    pub id: WindowId,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// How a window covers the screen when fullscreen.
+pub enum FullscreenMode {
+    /// A borderless window the size of the monitor. Switching apps is instant.
+    Borderless,
+    /// Takes over the primary monitor at its highest video mode.
+    ///
+    /// Falls back to borderless if the monitor doesn't report any video mode.
+    Exclusive,
 }
 
 #[derive(Debug, Clone)]
 /// The Window config defines customizable options for building a window.
 pub struct WindowConfig {
+    key: Option<String>,
     title: String,
     width: Option<u32>,
     height: Option<u32>,
+    position: Option<(i32, i32)>,
+    min_size: Option<(u32, u32)>,
+    max_size: Option<(u32, u32)>,
+    fullscreen: Option<FullscreenMode>,
     clear_color: Option<Color>,
     resizable: bool,
+    decorations: bool,
+    transparent: bool,
+    always_on_top: bool,
+    icon: Option<PathBuf>,
+    cursor_visible: bool,
+    cursor_grab: CursorGrabMode,
+    present_mode: PresentMode,
+    post_processing: Option<PostProcessing>,
+    recorder: Option<FrameRecorder>,
 }
 
 impl Default for WindowConfig {
     fn default() -> Self {
         Self {
+            key: None,
             title: "Pine".to_string(),
             width: Some(500),
             height: Some(500),
+            position: None,
+            min_size: None,
+            max_size: None,
+            fullscreen: None,
             clear_color: Some(Color::BLACK),
             resizable: true,
+            decorations: true,
+            transparent: false,
+            always_on_top: false,
+            icon: None,
+            cursor_visible: true,
+            cursor_grab: CursorGrabMode::None,
+            present_mode: PresentMode::default(),
+            post_processing: None,
+            recorder: None,
         }
     }
 }
 
 impl WindowConfig {
+    /// Sets the key the window can be looked up by. Keys must be unique among open windows.
+    pub fn with_key(mut self, key: &str) -> Self {
+        self.key = Some(key.to_string());
+        self
+    }
+
+    pub fn key(&self) -> Option<&str> {
+        self.key.as_deref()
+    }
+
     /// Sets the title of the window.
     pub fn with_title(mut self, title: &str) -> Self {
         self.title = title.to_string();
         self
     }
 
+    /// Sets the initial size of the window, in logical pixels.
+    pub fn with_size(mut self, width: u32, height: u32) -> Self {
+        self.width = Some(width);
+        self.height = Some(height);
+        self
+    }
+
+    /// Sets the initial position of the top-left corner of the window, in logical pixels.
+    ///
+    /// Without a position, the platform decides where the window goes.
+    pub fn with_position(mut self, x: i32, y: i32) -> Self {
+        self.position = Some((x, y));
+        self
+    }
+
+    /// Sets the size the window can't be resized below, in logical pixels.
+    pub fn with_min_size(mut self, width: u32, height: u32) -> Self {
+        self.min_size = Some((width, height));
+        self
+    }
+
+    /// Sets the size the window can't be resized above, in logical pixels.
+    pub fn with_max_size(mut self, width: u32, height: u32) -> Self {
+        self.max_size = Some((width, height));
+        self
+    }
+
+    /// Sets whether and how the window starts fullscreen.
+    pub fn with_fullscreen(mut self, fullscreen: Option<FullscreenMode>) -> Self {
+        self.fullscreen = fullscreen;
+        self
+    }
+
     /// Sets whether the window is resizable.
     pub fn with_resizable(mut self, resizable: bool) -> Self {
         self.resizable = resizable;
         self
     }
 
+    /// Sets whether the window has a title bar and borders.
+    pub fn with_decorations(mut self, decorations: bool) -> Self {
+        self.decorations = decorations;
+        self
+    }
+
+    /// Sets whether the window blends with whatever is behind it.
+    ///
+    /// Only the parts drawn with a translucent clear color or sprites show through.
+    pub fn with_transparent(mut self, transparent: bool) -> Self {
+        self.transparent = transparent;
+        self
+    }
+
+    /// Sets whether the window stays above all other windows.
+    pub fn with_always_on_top(mut self, always_on_top: bool) -> Self {
+        self.always_on_top = always_on_top;
+        self
+    }
+
+    /// Sets the image file used as the window icon. The file is loaded when the window is built.
+    pub fn with_icon(mut self, path: impl AsRef<Path>) -> Self {
+        self.icon = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Sets whether the cursor is visible while over the window.
+    pub fn with_cursor_visible(mut self, visible: bool) -> Self {
+        self.cursor_visible = visible;
+        self
+    }
+
+    /// Sets whether the cursor is confined to or locked in the window.
+    ///
+    /// If the platform doesn't support the mode, the other grabbing mode is tried instead.
+    pub fn with_cursor_grab(mut self, mode: CursorGrabMode) -> Self {
+        self.cursor_grab = mode;
+        self
+    }
+
+    /// Sets how frames are presented. Unsupported modes fall back to the closest supported one.
+    pub fn with_present_mode(mut self, present_mode: PresentMode) -> Self {
+        self.present_mode = present_mode;
+        self
+    }
+
+    /// Enables post-processing for the window, starting out with the given effects.
+    ///
+    /// Effects can be toggled and tweaked later through [`Renderer::post_processing_mut`]. Windows
+    /// without it can enable it later with [`Renderer::enable_post_processing`].
+    pub fn with_post_processing(mut self, post_processing: PostProcessing) -> Self {
+        self.post_processing = Some(post_processing);
+        self
+    }
+
+    /// Draws the window with a [`RecordingRenderer`] recording into the given recorder instead of
+    /// a [`Renderer2D`].
+    ///
+    /// The window still opens and receives input, but nothing is drawn into it, so it needs a
+    /// display like any other window. It doesn't need a GPU though, and records post-processing
+    /// like any other pass. Without a display, record with a [`RecordingRenderer`] directly.
+    pub fn with_recorder(mut self, recorder: FrameRecorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    pub fn recorder(&self) -> Option<&FrameRecorder> {
+        self.recorder.as_ref()
+    }
+
     /// Sets the clear color of the window.
     pub fn with_clear_color(mut self, color: Color) -> Self {
         self.clear_color = Some(color);
         self
     }
 
-    /// Constructs an actual Pine window from the config.
-    pub fn build(&self, elwt: &EventLoopWindowTarget<()>) -> Result<Window, PineError> {
+    /// Constructs an actual Pine window from the config, drawing with the given GPU context.
+    pub fn build(
+        &self,
+        elwt: &EventLoopWindowTarget<()>,
+        gpu: &Arc<GpuContext>,
+    ) -> Result<Window, PineError> {
+        let handle = self.build_handle(elwt)?;
+        if let Some(recorder) = &self.recorder {
+            return self.build_recording(handle, recorder);
+        }
+
+        let surface = gpu.create_surface(handle.clone())?;
+        self.build_with_surface(gpu, handle, surface)
+    }
+
+    /// Opens the winit window described by the config.
+    pub(crate) fn build_handle(
+        &self,
+        elwt: &EventLoopWindowTarget<()>,
+    ) -> Result<Arc<WinitWindow>, PineError> {
+        let level = if self.always_on_top {
+            WindowLevel::AlwaysOnTop
+        } else {
+            WindowLevel::Normal
+        };
+
         let mut builder = WindowBuilder::new()
             .with_title(self.title.as_str())
-            .with_resizable(self.resizable);
+            .with_resizable(self.resizable)
+            .with_decorations(self.decorations)
+            .with_transparent(self.transparent)
+            .with_window_level(level)
+            .with_fullscreen(self.fullscreen.map(|mode| Self::fullscreen(elwt, mode)))
+            .with_window_icon(self.load_icon()?);
 
         if let Some((width, height)) = self.width.zip(self.height) {
             builder = builder.with_inner_size(LogicalSize::new(width, height));
         }
+        if let Some((x, y)) = self.position {
+            builder = builder.with_position(LogicalPosition::new(x, y));
+        }
+        if let Some((width, height)) = self.min_size {
+            builder = builder.with_min_inner_size(LogicalSize::new(width, height));
+        }
+        if let Some((width, height)) = self.max_size {
+            builder = builder.with_max_inner_size(LogicalSize::new(width, height));
+        }
+
+        let handle = builder.build(elwt).map_err(|err| {
+            tracing::error!("Failed to build window");
+            PineError::OsError(err)
+        })?;
+
+        handle.set_cursor_visible(self.cursor_visible);
+        Self::grab_cursor(&handle, self.cursor_grab);
+
+        Ok(Arc::new(handle))
+    }
+
+    /// Resolves the fullscreen mode against the monitors available.
+    fn fullscreen(elwt: &EventLoopWindowTarget<()>, mode: FullscreenMode) -> Fullscreen {
+        match mode {
+            FullscreenMode::Borderless => Fullscreen::Borderless(None),
+            FullscreenMode::Exclusive => {
+                let video_mode = elwt
+                    .primary_monitor()
+                    .or_else(|| elwt.available_monitors().next())
+                    .and_then(|monitor| {
+                        monitor.video_modes().max_by_key(|mode: &VideoMode| {
+                            let size = mode.size();
+                            (size.width * size.height, mode.refresh_rate_millihertz())
+                        })
+                    });
+
+                match video_mode {
+                    Some(video_mode) => Fullscreen::Exclusive(video_mode),
+                    None => {
+                        tracing::warn!("No video mode available, using borderless fullscreen");
+                        Fullscreen::Borderless(None)
+                    }
+                }
+            }
+        }
+    }
+
+    /// Loads the window icon, if there is one.
+    fn load_icon(&self) -> Result<Option<Icon>, PineError> {
+        let Some(path) = &self.icon else {
+            return Ok(None);
+        };
+
+        let image = image::open(path)
+            .map_err(PineError::ImageError)?
+            .into_rgba8();
+        let (width, height) = image.dimensions();
+        let icon =
+            Icon::from_rgba(image.into_raw(), width, height).map_err(PineError::BadIconError)?;
+        Ok(Some(icon))
+    }
+
+    /// Grabs the cursor, trying the other grabbing mode if the requested one isn't supported.
+    fn grab_cursor(handle: &WinitWindow, mode: CursorGrabMode) {
+        let fallback = match mode {
+            CursorGrabMode::None => return,
+            CursorGrabMode::Confined => CursorGrabMode::Locked,
+            CursorGrabMode::Locked => CursorGrabMode::Confined,
+        };
+
+        if let Err(err) = handle
+            .set_cursor_grab(mode)
+            .or_else(|_| handle.set_cursor_grab(fallback))
+        {
+            tracing::warn!("Failed to grab cursor: {:?}", err);
+        }
+    }
+
+    /// Finishes a window opened with [`WindowConfig::build_handle`], given a surface for it.
+    pub(crate) fn build_with_surface(
+        &self,
+        gpu: &Arc<GpuContext>,
+        handle: Arc<WinitWindow>,
+        surface: wgpu::Surface<'static>,
+    ) -> Result<Window, PineError> {
+        let surface_config = SurfaceConfig::default()
+            .with_present_mode(self.present_mode)
+            .with_transparent(self.transparent);
+        let mut renderer = Box::new(Renderer2D::with_surface(
+            gpu.clone(),
+            handle.clone(),
+            surface,
+            &surface_config,
+        )?);
+        if let Some(post_processing) = &self.post_processing {
+            renderer.set_post_processing(post_processing.clone())?;
+        }
 
+        Ok(self.finish(handle, renderer))
+    }
+
+    /// Finishes a window opened with [`WindowConfig::build_handle`], recording its frames into
+    /// the recorder.
+    pub(crate) fn build_recording(
+        &self,
+        handle: Arc<WinitWindow>,
+        recorder: &FrameRecorder,
+    ) -> Result<Window, PineError> {
+        let size = handle.inner_size();
+        let mut renderer = Box::new(RecordingRenderer::new(
+            size.width,
+            size.height,
+            recorder.clone(),
+        )?);
+        renderer.set_scale_factor(handle.scale_factor());
+        if let Some(post_processing) = &self.post_processing {
+            renderer.set_post_processing(post_processing.clone())?;
+        }
+
+        Ok(self.finish(handle, renderer))
+    }
+
+    /// Puts the window together from its handle and renderer.
+    fn finish(&self, handle: Arc<WinitWindow>, renderer: Box<dyn Renderer>) -> Window {
         let clear_color = if let Some(color) = self.clear_color {
             color
         } else {
             Color::BLACK
         };
 
-        let handle = builder.build(elwt).map_err(|err| {
-            tracing::error!("Failed to build window");
-            return PineError::OsError(err);
-        })?;
-        let renderer = Box::new(
-            pollster::block_on(Renderer2D::new(&handle)).expect("Failed to construct renderer"),
-        );
-
-        let window = Window {
+        Window {
             handle,
             renderer,
             clear_color,
-        };
-        Ok(window)
+            key: self.key.clone(),
+        }
     }
 }
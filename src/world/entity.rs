@@ -0,0 +1,129 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// A handle to an entity of a [`World`](super::World).
+///
+/// Handles of despawned entities stay invalid, even once their slot is reused.
+pub struct Entity {
+    index: u32,
+    generation: u32,
+}
+
+impl Entity {
+    /// Returns the slot of the entity, shared with earlier entities that were despawned.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Returns how many entities used the slot before this one.
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+impl fmt::Display for Entity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}v{}", self.index, self.generation)
+    }
+}
+
+#[derive(Debug, Default)]
+/// Hands out entities and keeps track of which are alive.
+pub(crate) struct Entities {
+    /// The current generation of every slot, bumped when its entity is despawned.
+    generations: Vec<u32>,
+    alive: Vec<bool>,
+    free: Vec<u32>,
+    len: usize,
+}
+
+impl Entities {
+    pub(crate) fn alloc(&mut self) -> Entity {
+        self.len += 1;
+        if let Some(index) = self.free.pop() {
+            self.alive[index as usize] = true;
+            return Entity {
+                index,
+                generation: self.generations[index as usize],
+            };
+        }
+
+        let index = self.generations.len() as u32;
+        self.generations.push(0);
+        self.alive.push(true);
+        Entity {
+            index,
+            generation: 0,
+        }
+    }
+
+    /// Frees the entity's slot, returning whether the entity was alive.
+    pub(crate) fn free(&mut self, entity: Entity) -> bool {
+        if !self.contains(entity) {
+            return false;
+        }
+        let index = entity.index as usize;
+        self.alive[index] = false;
+        self.generations[index] += 1;
+        self.free.push(entity.index);
+        self.len -= 1;
+        true
+    }
+
+    pub(crate) fn contains(&self, entity: Entity) -> bool {
+        let index = entity.index as usize;
+        self.alive.get(index).copied().unwrap_or(false)
+            && self.generations[index] == entity.generation
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Iterates over the living entities in the order of their slots.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.alive
+            .iter()
+            .enumerate()
+            .filter(|(_, alive)| **alive)
+            .map(|(index, _)| Entity {
+                index: index as u32,
+                generation: self.generations[index],
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn freed_slots_are_reused_with_a_new_generation() {
+        let mut entities = Entities::default();
+        let first = entities.alloc();
+        let second = entities.alloc();
+
+        assert!(entities.free(first));
+        let reused = entities.alloc();
+        assert_eq!(reused.index(), first.index());
+        assert_eq!(reused.generation(), first.generation() + 1);
+
+        assert!(!entities.contains(first));
+        assert!(entities.contains(reused));
+        assert!(entities.contains(second));
+        assert_eq!(entities.len(), 2);
+    }
+
+    #[test]
+    fn stale_handles_cant_be_freed() {
+        let mut entities = Entities::default();
+        let entity = entities.alloc();
+        assert!(entities.free(entity));
+        assert!(!entities.free(entity));
+
+        let reused = entities.alloc();
+        assert!(!entities.free(entity));
+        assert!(entities.contains(reused));
+        assert_eq!(entities.iter().collect::<Vec<_>>(), [reused]);
+    }
+}
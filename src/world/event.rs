@@ -0,0 +1,228 @@
+use std::marker::PhantomData;
+
+#[derive(Debug, Clone)]
+/// A channel of events of one type, e.g. collisions or window resizes.
+///
+/// Events are double-buffered: they can be read during the frame they were sent in and the frame
+/// after, then they're dropped. Readers running every frame therefore see every event exactly
+/// once, no matter whether they run before or after the writer.
+///
+/// Writing is done through `&mut Events<T>`, e.g. with [`World::send_event`](super::World::send_event)
+/// or a [`ResMut`](super::query::ResMut) of the events. Each reader keeps its own
+/// [`EventReader`] to remember what it already read.
+///
+/// # Example
+///
+/// ```
+/// # use pine::world::event::{EventReader, Events};
+/// struct Scored(u32);
+///
+/// let mut events = Events::new();
+/// let mut reader = EventReader::new();
+/// events.send(Scored(10));
+///
+/// let points: u32 = reader.read(&events).map(|scored| scored.0).sum();
+/// assert_eq!(points, 10);
+/// assert_eq!(reader.read(&events).count(), 0);
+/// ```
+pub struct Events<T> {
+    /// The events sent during the previous frame.
+    previous: Vec<T>,
+    /// The events sent during the current frame.
+    current: Vec<T>,
+    /// The ID of the first event in `previous`. IDs count up with every event sent.
+    previous_start: usize,
+}
+
+impl<T> Default for Events<T> {
+    fn default() -> Self {
+        Self {
+            previous: vec![],
+            current: vec![],
+            previous_start: 0,
+        }
+    }
+}
+
+impl<T> Events<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn send(&mut self, event: T) {
+        self.current.push(event);
+    }
+
+    pub fn send_batch(&mut self, events: impl IntoIterator<Item = T>) {
+        self.current.extend(events);
+    }
+
+    /// Iterates over all buffered events, oldest first, regardless of what readers read.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.previous.iter().chain(&self.current)
+    }
+
+    /// Returns the number of buffered events.
+    pub fn len(&self) -> usize {
+        self.previous.len() + self.current.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drops the events of the previous frame and makes the current frame's events the previous
+    /// ones.
+    ///
+    /// The engine does this at the start of every frame for the events in the world.
+    pub fn update(&mut self) {
+        self.previous_start += self.previous.len();
+        self.previous = std::mem::take(&mut self.current);
+    }
+
+    /// Drops all buffered events. Readers skip them as if they read them.
+    pub fn clear(&mut self) {
+        self.previous_start += self.len();
+        self.previous.clear();
+        self.current.clear();
+    }
+
+    /// Returns a reader that only reads events sent from now on.
+    pub fn reader(&self) -> EventReader<T> {
+        EventReader {
+            next: self.end(),
+            marker: PhantomData,
+        }
+    }
+
+    /// The ID the next event sent gets.
+    fn end(&self) -> usize {
+        self.previous_start + self.len()
+    }
+}
+
+/// Remembers which events of a channel were already read.
+///
+/// A new reader starts with the events still buffered. Events dropped before the reader got to
+/// them are missed.
+pub struct EventReader<T> {
+    /// The ID of the next event to read.
+    next: usize,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Default for EventReader<T> {
+    fn default() -> Self {
+        Self {
+            next: 0,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Clone for EventReader<T> {
+    fn clone(&self) -> Self {
+        Self {
+            next: self.next,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for EventReader<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventReader")
+            .field("next", &self.next)
+            .finish()
+    }
+}
+
+impl<T> EventReader<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Iterates over the events not read yet, oldest first, and marks them as read.
+    pub fn read<'a>(&mut self, events: &'a Events<T>) -> impl Iterator<Item = &'a T> {
+        let skip = self.next.saturating_sub(events.previous_start);
+        self.next = events.end();
+        events.iter().skip(skip)
+    }
+
+    /// Returns whether there are events the reader didn't read yet, dropped ones aside.
+    pub fn has_unread(&self, events: &Events<T>) -> bool {
+        self.next.max(events.previous_start) < events.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(reader: &mut EventReader<u32>, events: &Events<u32>) -> Vec<u32> {
+        reader.read(events).copied().collect()
+    }
+
+    #[test]
+    fn events_live_for_two_frames() {
+        let mut events = Events::new();
+        let mut early = EventReader::new();
+        let mut late = EventReader::new();
+
+        events.send(1);
+        assert_eq!(read(&mut early, &events), [1]);
+
+        events.update();
+        events.send(2);
+        // A reader that runs before the writer still sees last frame's event.
+        assert_eq!(read(&mut late, &events), [1, 2]);
+        assert_eq!(read(&mut early, &events), [2]);
+
+        events.update();
+        assert_eq!(events.iter().copied().collect::<Vec<_>>(), [2]);
+        events.update();
+        assert!(events.is_empty());
+        assert_eq!(read(&mut EventReader::new(), &events), []);
+    }
+
+    #[test]
+    fn readers_that_fall_behind_skip_dropped_events() {
+        let mut events = Events::new();
+        let mut reader = EventReader::new();
+        events.send_batch([1, 2]);
+        events.update();
+        events.update();
+        assert!(!reader.has_unread(&events));
+
+        events.send(3);
+        events.update();
+        events.send(4);
+
+        assert!(reader.has_unread(&events));
+        assert_eq!(read(&mut reader, &events), [3, 4]);
+        assert!(!reader.has_unread(&events));
+    }
+
+    #[test]
+    fn cleared_events_count_as_read() {
+        let mut events = Events::new();
+        let mut reader = EventReader::new();
+        events.send(1);
+        events.update();
+        events.send(2);
+
+        events.clear();
+        assert!(!reader.has_unread(&events));
+        events.send(3);
+        assert_eq!(read(&mut reader, &events), [3]);
+    }
+
+    #[test]
+    fn new_readers_only_see_later_events() {
+        let mut events = Events::new();
+        events.send(1);
+        let mut reader = events.reader();
+        events.send(2);
+        assert_eq!(read(&mut reader, &events), [2]);
+    }
+}
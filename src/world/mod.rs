@@ -0,0 +1,568 @@
+pub mod entity;
+pub mod event;
+pub mod query;
+pub mod scene;
+pub mod schedule;
+pub mod storage;
+pub mod system;
+
+use self::{
+    entity::{Entities, Entity},
+    event::Events,
+    query::{Query, QueryParam},
+    storage::{Component, SparseSet, Storage},
+};
+use crate::error::PineError;
+
+use std::{
+    any::{type_name, Any, TypeId},
+    collections::HashMap,
+    fmt,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    sync::{RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError},
+};
+
+/// Moves the events of one type on to the next frame.
+type EventUpdate = fn(&World);
+
+/// The storage of one component type, locked separately so systems can use different components
+/// at the same time.
+struct Column {
+    name: &'static str,
+    storage: RwLock<Box<dyn Storage>>,
+}
+
+/// A value shared by all systems rather than attached to an entity, e.g. the frame timing.
+struct Resource {
+    name: &'static str,
+    value: RwLock<Box<dyn Any + Send + Sync>>,
+}
+
+/// Holds the entities of the game along with their components, and resources shared by all of
+/// them.
+///
+/// Components are stored per type in sparse sets. Queries lock the storages they use, so holding
+/// a mutable borrow of a component type while borrowing it again panics, like a `RefCell` would.
+#[derive(Default)]
+pub struct World {
+    entities: Entities,
+    columns: HashMap<TypeId, Column>,
+    resources: HashMap<TypeId, Resource>,
+    /// Updates the event channels added to the world, once per frame.
+    event_updates: Vec<(TypeId, EventUpdate)>,
+}
+
+impl fmt::Debug for World {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut components: Vec<_> = self.columns.values().map(|column| column.name).collect();
+        components.sort_unstable();
+        let mut resources: Vec<_> = self
+            .resources
+            .values()
+            .map(|resource| resource.name)
+            .collect();
+        resources.sort_unstable();
+
+        f.debug_struct("World")
+            .field("entities", &self.entities.len())
+            .field("components", &components)
+            .field("resources", &resources)
+            .finish()
+    }
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an entity with the given components.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pine::world::World;
+    /// struct Health(u32);
+    /// struct Speed(f32);
+    ///
+    /// let mut world = World::new();
+    /// let player = world.spawn((Health(100), Speed(2.0)));
+    /// assert_eq!(world.get::<Health>(player).map(|health| health.0), Some(100));
+    /// ```
+    pub fn spawn(&mut self, bundle: impl Bundle) -> Entity {
+        let entity = self.entities.alloc();
+        bundle.insert_into(self, entity);
+        entity
+    }
+
+    /// Removes the entity along with all its components. Returns whether the entity was alive.
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if !self.entities.free(entity) {
+            return false;
+        }
+        for column in self.columns.values_mut() {
+            column
+                .storage
+                .get_mut()
+                .unwrap_or_else(|err| err.into_inner())
+                .remove_entity(entity);
+        }
+        true
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.entities.contains(entity)
+    }
+
+    /// Returns the number of living entities.
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.len() == 0
+    }
+
+    /// Iterates over all living entities.
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.entities.iter()
+    }
+
+    /// Attaches a component to the entity, returning the component of the same type it had.
+    pub fn insert<T: Component>(
+        &mut self,
+        entity: Entity,
+        component: T,
+    ) -> Result<Option<T>, PineError> {
+        if !self.contains(entity) {
+            return Err(PineError::EntityNotFoundError(entity));
+        }
+        Ok(self.storage_mut::<T>().insert(entity, component))
+    }
+
+    /// Detaches a component from the entity and returns it.
+    pub fn remove<T: Component>(&mut self, entity: Entity) -> Option<T> {
+        let column = self.columns.get_mut(&TypeId::of::<T>())?;
+        column
+            .storage
+            .get_mut()
+            .unwrap_or_else(|err| err.into_inner())
+            .as_any_mut()
+            .downcast_mut::<SparseSet<T>>()?
+            .remove(entity)
+    }
+
+    /// Returns whether the entity has a component of the given type.
+    pub fn has<T: Component>(&self, entity: Entity) -> bool {
+        self.read_storage::<T>()
+            .is_some_and(|storage| storage.set().index(entity).is_some())
+    }
+
+    /// Returns the component of the entity.
+    ///
+    /// # Panics
+    ///
+    /// If the component type is borrowed mutably, e.g. by a running query.
+    pub fn get<T: Component>(&self, entity: Entity) -> Option<ComponentRef<'_, T>> {
+        let storage = self.read_storage::<T>()?;
+        let index = storage.set().index(entity)?;
+        Some(ComponentRef { storage, index })
+    }
+
+    /// Returns the component of the entity for modification.
+    ///
+    /// # Panics
+    ///
+    /// If the component type is borrowed, e.g. by a running query.
+    pub fn get_mut<T: Component>(&self, entity: Entity) -> Option<ComponentMut<'_, T>> {
+        let storage = self.write_storage::<T>()?;
+        let index = storage.set().index(entity)?;
+        Some(ComponentMut { storage, index })
+    }
+
+    /// Borrows the components and resources in `Q` to iterate over the entities having all of
+    /// them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pine::world::{entity::Entity, World};
+    /// struct Position(f32);
+    /// struct Velocity(f32);
+    ///
+    /// let mut world = World::new();
+    /// world.spawn((Position(0.0), Velocity(2.0)));
+    /// world.spawn((Position(5.0),));
+    ///
+    /// world
+    ///     .query::<(&mut Position, &Velocity)>()
+    ///     .for_each(|(position, velocity)| position.0 += velocity.0);
+    ///
+    /// let mut positions = vec![];
+    /// world
+    ///     .query::<(Entity, &Position)>()
+    ///     .for_each(|(_, position)| positions.push(position.0));
+    /// assert_eq!(positions, vec![2.0, 5.0]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If `Q` borrows a type mutably that's already borrowed, including by `Q` itself.
+    pub fn query<Q: QueryParam>(&self) -> Query<'_, Q> {
+        Query::new(self)
+    }
+
+    /// Adds a resource, returning the resource of the same type there was before.
+    pub fn insert_resource<R: Component>(&mut self, resource: R) -> Option<R> {
+        let previous = self.resources.insert(
+            TypeId::of::<R>(),
+            Resource {
+                name: type_name::<R>(),
+                value: RwLock::new(Box::new(resource)),
+            },
+        )?;
+        let value = previous
+            .value
+            .into_inner()
+            .unwrap_or_else(|err| err.into_inner());
+        value.downcast().ok().map(|value| *value)
+    }
+
+    pub fn remove_resource<R: Component>(&mut self) -> Option<R> {
+        let resource = self.resources.remove(&TypeId::of::<R>())?;
+        let value = resource
+            .value
+            .into_inner()
+            .unwrap_or_else(|err| err.into_inner());
+        value.downcast().ok().map(|value| *value)
+    }
+
+    pub fn contains_resource<R: Component>(&self) -> bool {
+        self.resources.contains_key(&TypeId::of::<R>())
+    }
+
+    /// Returns the resource of the given type.
+    ///
+    /// # Panics
+    ///
+    /// If the resource is borrowed mutably.
+    pub fn resource<R: Component>(&self) -> Option<ResourceRef<'_, R>> {
+        let resource = self.resources.get(&TypeId::of::<R>())?;
+        Some(ResourceRef {
+            guard: read_lock(resource.name, &resource.value),
+            marker: PhantomData,
+        })
+    }
+
+    /// Returns the resource of the given type for modification.
+    ///
+    /// # Panics
+    ///
+    /// If the resource is borrowed.
+    pub fn resource_mut<R: Component>(&self) -> Option<ResourceMut<'_, R>> {
+        let resource = self.resources.get(&TypeId::of::<R>())?;
+        Some(ResourceMut {
+            guard: write_lock(resource.name, &resource.value),
+            marker: PhantomData,
+        })
+    }
+
+    /// Adds a channel for events of the given type, stored as an [`Events`] resource and updated
+    /// by the engine every frame.
+    ///
+    /// Does nothing if the channel already exists.
+    pub fn add_event<T: Component>(&mut self) {
+        let id = TypeId::of::<T>();
+        if self.event_updates.iter().any(|(other, _)| *other == id) {
+            return;
+        }
+        if !self.contains_resource::<Events<T>>() {
+            self.insert_resource(Events::<T>::new());
+        }
+        self.event_updates.push((id, update_events::<T>));
+    }
+
+    /// Sends an event, adding its channel if needed.
+    ///
+    /// # Panics
+    ///
+    /// If the events are borrowed.
+    pub fn send_event<T: Component>(&mut self, event: T) {
+        self.add_event::<T>();
+        if let Some(mut events) = self.resource_mut::<Events<T>>() {
+            events.send(event);
+        }
+    }
+
+    /// Returns the channel of the given event type, if it was added.
+    ///
+    /// # Panics
+    ///
+    /// If the events are borrowed mutably.
+    pub fn events<T: Component>(&self) -> Option<ResourceRef<'_, Events<T>>> {
+        self.resource()
+    }
+
+    /// Moves every event channel on to the next frame.
+    pub(crate) fn update_events(&mut self) {
+        for (_, update) in &self.event_updates {
+            update(self);
+        }
+    }
+
+    /// Returns the storage of the component type, creating it if needed.
+    pub(crate) fn storage_mut<T: Component>(&mut self) -> &mut SparseSet<T> {
+        self.columns
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Column {
+                name: type_name::<T>(),
+                storage: RwLock::new(Box::<SparseSet<T>>::default()),
+            })
+            .storage
+            .get_mut()
+            .unwrap_or_else(|err| err.into_inner())
+            .as_any_mut()
+            .downcast_mut()
+            .expect("Component storage has the wrong type")
+    }
+
+    /// Locks the storage of the component type for reading, if there is one.
+    pub(crate) fn read_storage<T: Component>(&self) -> Option<StorageRead<'_, T>> {
+        let column = self.columns.get(&TypeId::of::<T>())?;
+        Some(StorageRead {
+            guard: read_lock(column.name, &column.storage),
+            marker: PhantomData,
+        })
+    }
+
+    /// Locks the storage of the component type for writing, if there is one.
+    pub(crate) fn write_storage<T: Component>(&self) -> Option<StorageWrite<'_, T>> {
+        let column = self.columns.get(&TypeId::of::<T>())?;
+        Some(StorageWrite {
+            guard: write_lock(column.name, &column.storage),
+            marker: PhantomData,
+        })
+    }
+
+    pub(crate) fn resource_lock<R: Component>(
+        &self,
+    ) -> Option<&RwLock<Box<dyn Any + Send + Sync>>> {
+        self.resources
+            .get(&TypeId::of::<R>())
+            .map(|resource| &resource.value)
+    }
+}
+
+fn update_events<T: Component>(world: &World) {
+    if let Some(mut events) = world.resource_mut::<Events<T>>() {
+        events.update();
+    }
+}
+
+/// Locks for reading, panicking instead of blocking if the value is borrowed mutably.
+///
+/// A panic while the value was borrowed doesn't make it unusable, so poisoning is ignored.
+fn read_lock<'a, T: ?Sized>(name: &str, lock: &'a RwLock<T>) -> RwLockReadGuard<'a, T> {
+    match lock.try_read() {
+        Ok(guard) => guard,
+        Err(TryLockError::Poisoned(err)) => err.into_inner(),
+        Err(TryLockError::WouldBlock) => panic!("{} is already borrowed mutably", name),
+    }
+}
+
+/// Locks for writing, panicking instead of blocking if the value is borrowed.
+fn write_lock<'a, T: ?Sized>(name: &str, lock: &'a RwLock<T>) -> RwLockWriteGuard<'a, T> {
+    match lock.try_write() {
+        Ok(guard) => guard,
+        Err(TryLockError::Poisoned(err)) => err.into_inner(),
+        Err(TryLockError::WouldBlock) => panic!("{} is already borrowed", name),
+    }
+}
+
+/// The storage of a component type, locked for reading by a query.
+pub struct StorageRead<'w, T> {
+    guard: RwLockReadGuard<'w, Box<dyn Storage>>,
+    marker: PhantomData<T>,
+}
+
+impl<T: Component> StorageRead<'_, T> {
+    pub(crate) fn set(&self) -> &SparseSet<T> {
+        self.guard
+            .as_any()
+            .downcast_ref()
+            .expect("Component storage has the wrong type")
+    }
+}
+
+/// The storage of a component type, locked for writing by a query.
+pub struct StorageWrite<'w, T> {
+    guard: RwLockWriteGuard<'w, Box<dyn Storage>>,
+    marker: PhantomData<T>,
+}
+
+impl<T: Component> StorageWrite<'_, T> {
+    pub(crate) fn set(&self) -> &SparseSet<T> {
+        self.guard
+            .as_any()
+            .downcast_ref()
+            .expect("Component storage has the wrong type")
+    }
+
+    pub(crate) fn set_mut(&mut self) -> &mut SparseSet<T> {
+        self.guard
+            .as_any_mut()
+            .downcast_mut()
+            .expect("Component storage has the wrong type")
+    }
+}
+
+/// A borrowed component. The storage of its type stays locked for reading while it lives.
+pub struct ComponentRef<'w, T> {
+    storage: StorageRead<'w, T>,
+    index: usize,
+}
+
+impl<T: Component> Deref for ComponentRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.storage.set().component(self.index)
+    }
+}
+
+/// A mutably borrowed component. The storage of its type stays locked while it lives.
+pub struct ComponentMut<'w, T> {
+    storage: StorageWrite<'w, T>,
+    index: usize,
+}
+
+impl<T: Component> Deref for ComponentMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.storage.set().component(self.index)
+    }
+}
+
+impl<T: Component> DerefMut for ComponentMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.storage.set_mut().component_mut(self.index)
+    }
+}
+
+/// A borrowed resource, locked for reading while it lives.
+pub struct ResourceRef<'w, R> {
+    guard: RwLockReadGuard<'w, Box<dyn Any + Send + Sync>>,
+    marker: PhantomData<R>,
+}
+
+impl<R: Component> Deref for ResourceRef<'_, R> {
+    type Target = R;
+
+    fn deref(&self) -> &R {
+        self.guard
+            .downcast_ref()
+            .expect("Resource has the wrong type")
+    }
+}
+
+/// A mutably borrowed resource, locked while it lives.
+pub struct ResourceMut<'w, R> {
+    guard: RwLockWriteGuard<'w, Box<dyn Any + Send + Sync>>,
+    marker: PhantomData<R>,
+}
+
+impl<R: Component> Deref for ResourceMut<'_, R> {
+    type Target = R;
+
+    fn deref(&self) -> &R {
+        self.guard
+            .downcast_ref()
+            .expect("Resource has the wrong type")
+    }
+}
+
+impl<R: Component> DerefMut for ResourceMut<'_, R> {
+    fn deref_mut(&mut self) -> &mut R {
+        self.guard
+            .downcast_mut()
+            .expect("Resource has the wrong type")
+    }
+}
+
+/// A set of components spawned together, implemented for tuples of up to eight components.
+pub trait Bundle: Send + Sync + 'static {
+    /// Attaches the components to the entity.
+    fn insert_into(self, world: &mut World, entity: Entity);
+}
+
+impl Bundle for () {
+    fn insert_into(self, _world: &mut World, _entity: Entity) {}
+}
+
+macro_rules! impl_bundle {
+    ($($component:ident),*) => {
+        impl<$($component: Component),*> Bundle for ($($component,)*) {
+            #[allow(non_snake_case)]
+            fn insert_into(self, world: &mut World, entity: Entity) {
+                let ($($component,)*) = self;
+                $(world.storage_mut::<$component>().insert(entity, $component);)*
+            }
+        }
+    };
+}
+
+impl_bundle!(A);
+impl_bundle!(A, B);
+impl_bundle!(A, B, C);
+impl_bundle!(A, B, C, D);
+impl_bundle!(A, B, C, D, E);
+impl_bundle!(A, B, C, D, E, F);
+impl_bundle!(A, B, C, D, E, F, G);
+impl_bundle!(A, B, C, D, E, F, G, H);
+
+#[cfg(test)]
+mod tests {
+    use super::{event::EventReader, *};
+
+    #[derive(Debug, PartialEq)]
+    struct Jumped;
+    #[derive(Debug, PartialEq)]
+    struct Landed(u32);
+
+    #[test]
+    fn sending_an_event_adds_its_channel() {
+        let mut world = World::new();
+        assert!(world.events::<Jumped>().is_none());
+        world.send_event(Jumped);
+        assert_eq!(world.events::<Jumped>().unwrap().len(), 1);
+
+        // Adding the channel again keeps its events.
+        world.add_event::<Jumped>();
+        assert_eq!(world.events::<Jumped>().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn updating_events_advances_every_channel() {
+        let mut world = World::new();
+        world.add_event::<Jumped>();
+        world.send_event(Jumped);
+        world.send_event(Landed(3));
+        let mut reader = EventReader::new();
+
+        world.update_events();
+        world.send_event(Landed(4));
+        let landed: Vec<_> = reader
+            .read(&world.events::<Landed>().unwrap())
+            .map(|landed| landed.0)
+            .collect();
+        assert_eq!(landed, [3, 4]);
+
+        world.update_events();
+        assert!(world.events::<Jumped>().unwrap().is_empty());
+        assert_eq!(world.events::<Landed>().unwrap().len(), 1);
+        world.update_events();
+        assert!(world.events::<Landed>().unwrap().is_empty());
+    }
+}
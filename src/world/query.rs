@@ -0,0 +1,369 @@
+use super::{
+    entity::Entity, read_lock, storage::Component, system::Access, write_lock, StorageRead,
+    StorageWrite, World,
+};
+
+use std::{
+    any::{type_name, Any},
+    marker::PhantomData,
+    sync::{RwLockReadGuard, RwLockWriteGuard},
+};
+
+/// Something a query can fetch for each entity: components, the entity itself or resources.
+///
+/// Implemented for `&T` and `&mut T` of components, [`Entity`], `Option` of another parameter,
+/// [`Res`] and [`ResMut`], and tuples of up to eight parameters.
+pub trait QueryParam {
+    /// What the parameter keeps locked while the query lives.
+    type Guard<'w>;
+    /// What the parameter yields for an entity.
+    type Item<'g>;
+
+    /// Locks what the parameter needs. Returns None if the query can't match anything, e.g.
+    /// because no entity ever had the component.
+    fn lock(world: &World) -> Option<Self::Guard<'_>>;
+
+    /// Returns the entities the parameter matches, if it narrows the query down at all.
+    fn entities<'a>(guard: &'a Self::Guard<'_>) -> Option<&'a [Entity]>;
+
+    /// Fetches the item of the entity, or None if the entity doesn't match.
+    fn fetch<'g>(guard: &'g mut Self::Guard<'_>, entity: Entity) -> Option<Self::Item<'g>>;
+
+    /// Records what the parameter borrows, so systems that don't conflict can run in parallel.
+    fn access(access: &mut Access);
+}
+
+impl<T: Component> QueryParam for &T {
+    type Guard<'w> = StorageRead<'w, T>;
+    type Item<'g> = &'g T;
+
+    fn lock(world: &World) -> Option<Self::Guard<'_>> {
+        world.read_storage()
+    }
+
+    fn entities<'a>(guard: &'a Self::Guard<'_>) -> Option<&'a [Entity]> {
+        Some(guard.set().entities())
+    }
+
+    fn fetch<'g>(guard: &'g mut Self::Guard<'_>, entity: Entity) -> Option<Self::Item<'g>> {
+        guard.set().get(entity)
+    }
+
+    fn access(access: &mut Access) {
+        access.read::<T>();
+    }
+}
+
+impl<T: Component> QueryParam for &mut T {
+    type Guard<'w> = StorageWrite<'w, T>;
+    type Item<'g> = &'g mut T;
+
+    fn lock(world: &World) -> Option<Self::Guard<'_>> {
+        world.write_storage()
+    }
+
+    fn entities<'a>(guard: &'a Self::Guard<'_>) -> Option<&'a [Entity]> {
+        Some(guard.set().entities())
+    }
+
+    fn fetch<'g>(guard: &'g mut Self::Guard<'_>, entity: Entity) -> Option<Self::Item<'g>> {
+        guard.set_mut().get_mut(entity)
+    }
+
+    fn access(access: &mut Access) {
+        access.write::<T>();
+    }
+}
+
+impl QueryParam for Entity {
+    type Guard<'w> = ();
+    type Item<'g> = Entity;
+
+    fn lock(_world: &World) -> Option<Self::Guard<'_>> {
+        Some(())
+    }
+
+    fn entities<'a>(_guard: &'a Self::Guard<'_>) -> Option<&'a [Entity]> {
+        None
+    }
+
+    fn fetch<'g>(_guard: &'g mut Self::Guard<'_>, entity: Entity) -> Option<Self::Item<'g>> {
+        Some(entity)
+    }
+
+    fn access(_access: &mut Access) {}
+}
+
+/// Matches every entity, yielding None for entities the inner parameter doesn't match.
+impl<Q: QueryParam> QueryParam for Option<Q> {
+    type Guard<'w> = Option<Q::Guard<'w>>;
+    type Item<'g> = Option<Q::Item<'g>>;
+
+    fn lock(world: &World) -> Option<Self::Guard<'_>> {
+        Some(Q::lock(world))
+    }
+
+    fn entities<'a>(_guard: &'a Self::Guard<'_>) -> Option<&'a [Entity]> {
+        None
+    }
+
+    fn fetch<'g>(guard: &'g mut Self::Guard<'_>, entity: Entity) -> Option<Self::Item<'g>> {
+        Some(guard.as_mut().and_then(|guard| Q::fetch(guard, entity)))
+    }
+
+    fn access(access: &mut Access) {
+        Q::access(access);
+    }
+}
+
+/// Borrows a resource for the whole query. Queries needing a missing resource match nothing.
+pub struct Res<R>(PhantomData<R>);
+
+impl<R: Component> QueryParam for Res<R> {
+    type Guard<'w> = RwLockReadGuard<'w, Box<dyn Any + Send + Sync>>;
+    type Item<'g> = &'g R;
+
+    fn lock(world: &World) -> Option<Self::Guard<'_>> {
+        Some(read_lock(type_name::<R>(), world.resource_lock::<R>()?))
+    }
+
+    fn entities<'a>(_guard: &'a Self::Guard<'_>) -> Option<&'a [Entity]> {
+        None
+    }
+
+    fn fetch<'g>(guard: &'g mut Self::Guard<'_>, _entity: Entity) -> Option<Self::Item<'g>> {
+        guard.downcast_ref()
+    }
+
+    fn access(access: &mut Access) {
+        access.read_resource::<R>();
+    }
+}
+
+/// Borrows a resource mutably for the whole query. Queries needing a missing resource match
+/// nothing.
+pub struct ResMut<R>(PhantomData<R>);
+
+impl<R: Component> QueryParam for ResMut<R> {
+    type Guard<'w> = RwLockWriteGuard<'w, Box<dyn Any + Send + Sync>>;
+    type Item<'g> = &'g mut R;
+
+    fn lock(world: &World) -> Option<Self::Guard<'_>> {
+        Some(write_lock(type_name::<R>(), world.resource_lock::<R>()?))
+    }
+
+    fn entities<'a>(_guard: &'a Self::Guard<'_>) -> Option<&'a [Entity]> {
+        None
+    }
+
+    fn fetch<'g>(guard: &'g mut Self::Guard<'_>, _entity: Entity) -> Option<Self::Item<'g>> {
+        guard.downcast_mut()
+    }
+
+    fn access(access: &mut Access) {
+        access.write_resource::<R>();
+    }
+}
+
+macro_rules! impl_query_param {
+    ($($param:ident),*) => {
+        #[allow(non_snake_case)]
+        impl<$($param: QueryParam),*> QueryParam for ($($param,)*) {
+            type Guard<'w> = ($($param::Guard<'w>,)*);
+            type Item<'g> = ($($param::Item<'g>,)*);
+
+            fn lock(world: &World) -> Option<Self::Guard<'_>> {
+                Some(($($param::lock(world)?,)*))
+            }
+
+            fn entities<'a>(guard: &'a Self::Guard<'_>) -> Option<&'a [Entity]> {
+                let ($($param,)*) = guard;
+                // Iterate over the smallest storage, as every match must be in all of them.
+                [$($param::entities($param)),*]
+                    .into_iter()
+                    .flatten()
+                    .min_by_key(|entities| entities.len())
+            }
+
+            fn fetch<'g>(
+                guard: &'g mut Self::Guard<'_>,
+                entity: Entity,
+            ) -> Option<Self::Item<'g>> {
+                let ($($param,)*) = guard;
+                Some(($($param::fetch($param, entity)?,)*))
+            }
+
+            fn access(access: &mut Access) {
+                $($param::access(access);)*
+            }
+        }
+    };
+}
+
+impl_query_param!(A);
+impl_query_param!(A, B);
+impl_query_param!(A, B, C);
+impl_query_param!(A, B, C, D);
+impl_query_param!(A, B, C, D, E);
+impl_query_param!(A, B, C, D, E, F);
+impl_query_param!(A, B, C, D, E, F, G);
+impl_query_param!(A, B, C, D, E, F, G, H);
+
+/// Components and resources borrowed from a [`World`], to visit the entities that have them.
+///
+/// The borrows are released when the query is dropped.
+pub struct Query<'w, Q: QueryParam> {
+    world: &'w World,
+    guard: Option<Q::Guard<'w>>,
+}
+
+impl<'w, Q: QueryParam> Query<'w, Q> {
+    pub(crate) fn new(world: &'w World) -> Self {
+        Self {
+            world,
+            guard: Q::lock(world),
+        }
+    }
+
+    /// Calls the closure for every matching entity.
+    pub fn for_each(&mut self, mut f: impl FnMut(Q::Item<'_>)) {
+        let Some(guard) = &mut self.guard else {
+            return;
+        };
+        for entity in Self::candidates(self.world, guard) {
+            if let Some(item) = Q::fetch(guard, entity) {
+                f(item);
+            }
+        }
+    }
+
+    /// Returns the item of the entity, if it matches.
+    pub fn get(&mut self, entity: Entity) -> Option<Q::Item<'_>> {
+        if !self.world.contains(entity) {
+            return None;
+        }
+        Q::fetch(self.guard.as_mut()?, entity)
+    }
+
+    /// Returns the matching entities.
+    pub fn entities(&mut self) -> Vec<Entity> {
+        let Some(guard) = &mut self.guard else {
+            return vec![];
+        };
+        Self::candidates(self.world, guard)
+            .into_iter()
+            .filter(|entity| Q::fetch(guard, *entity).is_some())
+            .collect()
+    }
+
+    /// Returns the number of matching entities.
+    pub fn count(&mut self) -> usize {
+        self.entities().len()
+    }
+
+    /// Returns the entities that may match, copied as the guard is borrowed mutably to fetch the
+    /// items.
+    fn candidates(world: &World, guard: &Q::Guard<'w>) -> Vec<Entity> {
+        match Q::entities(guard) {
+            Some(entities) => entities.to_vec(),
+            None => world.entities().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Position(i32);
+    #[derive(Debug, PartialEq)]
+    struct Velocity(i32);
+    #[derive(Debug, PartialEq)]
+    struct Gravity(i32);
+
+    #[test]
+    fn tuples_match_entities_with_every_component() {
+        let mut world = World::new();
+        let moving = world.spawn((Position(0), Velocity(2)));
+        world.spawn((Position(5),));
+        world.spawn((Velocity(1),));
+
+        world
+            .query::<(&mut Position, &Velocity)>()
+            .for_each(|(position, velocity)| position.0 += velocity.0);
+
+        assert_eq!(world.query::<(&Position, &Velocity)>().entities(), [moving]);
+        let mut positions = vec![];
+        world
+            .query::<(Entity, &Position)>()
+            .for_each(|(entity, position)| positions.push((entity, position.0)));
+        positions.sort();
+        assert_eq!(positions[0], (moving, 2));
+        assert_eq!(positions.len(), 2);
+    }
+
+    #[test]
+    fn options_match_entities_without_the_component() {
+        let mut world = World::new();
+        let with = world.spawn((Position(1), Velocity(3)));
+        let without = world.spawn((Position(2),));
+
+        let mut items = vec![];
+        world
+            .query::<(Entity, &Position, Option<&Velocity>)>()
+            .for_each(|(entity, position, velocity)| {
+                items.push((entity, position.0, velocity.map(|velocity| velocity.0)))
+            });
+        items.sort();
+        assert_eq!(items, [(with, 1, Some(3)), (without, 2, None)]);
+
+        // Components no entity ever had match as None too.
+        let mut query = world.query::<(&Position, Option<&Gravity>)>();
+        assert_eq!(query.count(), 2);
+        assert_eq!(query.get(without).map(|(_, gravity)| gravity), Some(None));
+    }
+
+    #[test]
+    fn despawned_entities_stop_matching() {
+        let mut world = World::new();
+        let [a, b, c] = [1, 2, 3].map(|x| world.spawn((Position(x),)));
+
+        assert!(world.despawn(a));
+        assert!(!world.despawn(a));
+        let reused = world.spawn((Position(4),));
+        assert_eq!(reused.index(), a.index());
+
+        let mut query = world.query::<&Position>();
+        assert!(query.get(a).is_none());
+        assert_eq!(query.get(b), Some(&Position(2)));
+        assert_eq!(query.get(c), Some(&Position(3)));
+        assert_eq!(query.get(reused), Some(&Position(4)));
+        assert_eq!(query.count(), 3);
+        drop(query);
+
+        assert!(world.get::<Position>(a).is_none());
+        assert!(world.insert(a, Velocity(0)).is_err());
+    }
+
+    #[test]
+    fn resources_are_fetched_for_every_entity() {
+        let mut world = World::new();
+        world.spawn((Position(0),));
+        world.spawn((Position(10),));
+
+        world.insert_resource(Gravity(-1));
+        world
+            .query::<(&mut Position, Res<Gravity>)>()
+            .for_each(|(position, gravity)| position.0 += gravity.0);
+        let mut positions = vec![];
+        world
+            .query::<&Position>()
+            .for_each(|position| positions.push(position.0));
+        positions.sort();
+        assert_eq!(positions, [-1, 9]);
+
+        world.remove_resource::<Gravity>();
+        assert_eq!(world.query::<(&Position, Res<Gravity>)>().count(), 0);
+    }
+}
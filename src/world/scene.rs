@@ -0,0 +1,47 @@
+use super::World;
+use crate::{
+    rendering::{
+        scene::{Renderable, Transform},
+        scene_graph::NodeId,
+    },
+    windowing::Window,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Ties an entity to a node in the scene graph of a window.
+///
+/// Every frame after the systems ran, the entity's [`Transform`] and [`Renderable`] components
+/// are copied to the node, so systems move sprites around without touching the scene graph.
+pub struct SceneLink {
+    /// The index of the window, as used by [`Context`](crate::prelude::Context).
+    pub window: usize,
+    pub node: NodeId,
+}
+
+impl SceneLink {
+    pub fn new(window: usize, node: NodeId) -> Self {
+        Self { window, node }
+    }
+}
+
+/// Copies the transforms and renderables of linked entities to their scene nodes.
+pub(crate) fn sync_scenes(world: &World, windows: &mut [Window]) {
+    world
+        .query::<(&SceneLink, Option<&Transform>, Option<&Renderable>)>()
+        .for_each(|(link, transform, renderable)| {
+            let Some(node) = windows
+                .get_mut(link.window)
+                .and_then(|window| window.renderer.scene_graph_mut().get_mut(link.node))
+            else {
+                return;
+            };
+
+            // Only touch changed transforms, as that marks the node's subtree for propagation.
+            if let Some(transform) = transform.filter(|transform| *transform != node.transform()) {
+                node.set_transform(*transform);
+            }
+            if let Some(renderable) = renderable {
+                node.set_renderable(Some(*renderable));
+            }
+        });
+}
@@ -0,0 +1,406 @@
+use super::{system::System, World};
+use crate::error::PineError;
+
+use std::{collections::BTreeSet, fmt};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// When in the frame the systems of a stage run.
+pub enum Stage {
+    /// Runs once, after the setup callback and before the first frame.
+    Startup,
+    /// Runs every frame before the update callback.
+    PreUpdate,
+    /// Runs at the fixed rate, after the fixed update callback. Zero or more times per frame.
+    ///
+    /// Systems read the fixed step from [`FixedTime`](crate::time::FixedTime), as the delta of
+    /// [`Time`](crate::time::Time) is the frame time.
+    FixedUpdate,
+    /// Runs every frame after the update callback.
+    #[default]
+    Update,
+    /// Runs every frame after [`Stage::Update`].
+    PostUpdate,
+    /// Runs every frame right before linked entities are copied to the scene graphs and the
+    /// draw callback runs.
+    RenderPrepare,
+}
+
+impl Stage {
+    /// All stages, in the order they run.
+    pub const ALL: [Stage; 6] = [
+        Stage::Startup,
+        Stage::PreUpdate,
+        Stage::FixedUpdate,
+        Stage::Update,
+        Stage::PostUpdate,
+        Stage::RenderPrepare,
+    ];
+}
+
+#[derive(Debug, Default)]
+/// The systems of a stage, grouped into batches that run one after the other.
+struct StageSystems {
+    systems: Vec<System>,
+    /// Indices into `systems`. The systems of a batch don't conflict and run in parallel.
+    batches: Vec<Vec<usize>>,
+}
+
+#[derive(Debug, Default)]
+/// Orders systems into stages and runs non-conflicting systems in parallel.
+///
+/// Within a stage, systems run after the systems they're declared to run after. Systems that
+/// conflict, i.e. one of them writes something the other uses, run in the order they were
+/// added. Everything else may run at the same time on the threads of rayon's global pool.
+pub struct Schedule {
+    stages: Vec<(Stage, StageSystems)>,
+    /// Whether systems were added since the batches were last resolved.
+    dirty: bool,
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a system to the stage it was configured for.
+    ///
+    /// The order is resolved by [`Schedule::build`], or when the stage first runs.
+    pub fn add_system(&mut self, system: System) {
+        let stage = system.stage();
+        let index = match self
+            .stages
+            .binary_search_by_key(&stage, |(stage, _)| *stage)
+        {
+            Ok(index) => index,
+            Err(index) => {
+                self.stages.insert(index, (stage, StageSystems::default()));
+                index
+            }
+        };
+        self.stages[index].1.systems.push(system);
+        self.dirty = true;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    /// Returns the systems of the stage, in the order they were added.
+    pub fn systems(&self, stage: Stage) -> impl Iterator<Item = &System> {
+        self.stage(stage)
+            .into_iter()
+            .flat_map(|stage| stage.systems.iter())
+    }
+
+    /// Resolves the order of the systems and groups them into batches.
+    ///
+    /// Fails if two systems share a name or the ordering constraints form a cycle. Constraints
+    /// naming systems that aren't in the same stage are ignored with a warning.
+    pub fn build(&mut self) -> Result<(), PineError> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        let mut names = BTreeSet::new();
+        for system in self.stages.iter().flat_map(|(_, stage)| &stage.systems) {
+            if !names.insert(system.name()) {
+                return Err(PineError::DuplicateSystemError(system.name().to_string()));
+            }
+        }
+
+        for (_, stage) in &mut self.stages {
+            stage.batches = resolve(&stage.systems)?;
+        }
+        self.dirty = false;
+        tracing::debug!("Resolved schedule:\n{}", self);
+        Ok(())
+    }
+
+    /// Runs the systems of the stage.
+    ///
+    /// Systems whose run condition doesn't hold are skipped. Should the schedule fail to
+    /// resolve, the error is logged and nothing runs.
+    pub fn run(&mut self, stage: Stage, world: &mut World) {
+        if let Err(err) = self.build() {
+            tracing::error!("Not running systems: {}", err);
+            return;
+        }
+        let Some(stage) = self
+            .stages
+            .iter_mut()
+            .find(|(other, _)| *other == stage)
+            .map(|(_, stage)| stage)
+        else {
+            return;
+        };
+
+        for batch in &stage.batches {
+            let mut systems: Vec<&mut System> = stage
+                .systems
+                .iter_mut()
+                .enumerate()
+                .filter(|(i, _)| batch.contains(i))
+                .map(|(_, system)| system)
+                .collect();
+            systems.retain_mut(|system| system.should_run(world));
+
+            match systems.as_mut_slice() {
+                [] => {}
+                [system] if system.is_exclusive() => system.run_unchecked(world),
+                systems => run_parallel(systems, world),
+            }
+        }
+    }
+
+    fn stage(&self, stage: Stage) -> Option<&StageSystems> {
+        self.stages
+            .iter()
+            .find(|(other, _)| *other == stage)
+            .map(|(_, stage)| stage)
+    }
+}
+
+/// Runs the systems at the same time, one of them on the current thread and the others on the
+/// worker threads, which live across frames.
+fn run_parallel(systems: &mut [&mut System], world: &World) {
+    let Some((first, rest)) = systems.split_first_mut() else {
+        return;
+    };
+    rayon::in_place_scope(|scope| {
+        for system in rest {
+            scope.spawn(|_| system.run_shared(world));
+        }
+        first.run_shared(world);
+    });
+}
+
+/// Orders the systems of a stage and groups them into batches of systems that can run together.
+fn resolve(systems: &[System]) -> Result<Vec<Vec<usize>>, PineError> {
+    let count = systems.len();
+    let index_of = |name: &str| systems.iter().position(|system| system.name() == name);
+
+    let mut dependents = vec![vec![]; count];
+    let mut dependencies = vec![0; count];
+    let mut add_edge = |from: usize, to: usize| {
+        if !dependents[from].contains(&to) {
+            dependents[from].push(to);
+            dependencies[to] += 1;
+        }
+    };
+    for (i, system) in systems.iter().enumerate() {
+        let edges = system
+            .before_systems()
+            .iter()
+            .map(|name| (name, true))
+            .chain(system.after_systems().iter().map(|name| (name, false)));
+        for (name, before) in edges {
+            match index_of(name) {
+                Some(j) if before => add_edge(i, j),
+                Some(j) => add_edge(j, i),
+                None => tracing::warn!(
+                    "System {:?} is ordered against {:?}, which isn't in its stage",
+                    system.name(),
+                    name
+                ),
+            }
+        }
+    }
+
+    // Kahn's algorithm, preferring the order the systems were added in.
+    let mut ready: BTreeSet<usize> = (0..count).filter(|&i| dependencies[i] == 0).collect();
+    let mut order = Vec::with_capacity(count);
+    while let Some(i) = ready.pop_first() {
+        order.push(i);
+        for &j in &dependents[i] {
+            dependencies[j] -= 1;
+            if dependencies[j] == 0 {
+                ready.insert(j);
+            }
+        }
+    }
+    if order.len() < count {
+        let cycle = (0..count)
+            .filter(|i| !order.contains(i))
+            .map(|i| systems[i].name().to_string())
+            .collect();
+        return Err(PineError::SystemOrderCycleError(cycle));
+    }
+
+    // Each system goes into the batch after the last one holding a system it must follow, be it
+    // by an ordering constraint or by conflicting with a system ordered before it.
+    let mut batch_of = vec![0; count];
+    let mut batches: Vec<Vec<usize>> = vec![];
+    for (position, &i) in order.iter().enumerate() {
+        let batch = order[..position]
+            .iter()
+            .filter(|&&j| dependents[j].contains(&i) || !systems[j].is_compatible(&systems[i]))
+            .map(|&j| batch_of[j] + 1)
+            .max()
+            .unwrap_or(0);
+        batch_of[i] = batch;
+        if batch == batches.len() {
+            batches.push(vec![]);
+        }
+        batches[batch].push(i);
+    }
+    Ok(batches)
+}
+
+impl fmt::Display for Schedule {
+    /// Lists the batches of every stage along with what their systems borrow.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (stage, systems) in &self.stages {
+            writeln!(f, "{:?}:", stage)?;
+            if self.dirty {
+                writeln!(f, "  (not resolved yet)")?;
+                continue;
+            }
+            for (i, batch) in systems.batches.iter().enumerate() {
+                writeln!(f, "  batch {}:", i)?;
+                for &system in batch {
+                    let system = &systems.systems[system];
+                    match system.access() {
+                        Some(access) => writeln!(f, "    {} ({})", system.name(), access)?,
+                        None => writeln!(f, "    {} (exclusive)", system.name())?,
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::system::Access;
+
+    struct Position;
+    struct Velocity;
+    #[derive(Default)]
+    struct Log(Vec<&'static str>);
+
+    fn reading<T: Send + Sync + 'static>(name: &str) -> System {
+        let mut access = Access::new();
+        access.read::<T>();
+        System::parallel(name, access, |_| {})
+    }
+
+    fn writing<T: Send + Sync + 'static>(name: &str) -> System {
+        let mut access = Access::new();
+        access.write::<T>();
+        System::parallel(name, access, |_| {})
+    }
+
+    fn logging(name: &'static str) -> System {
+        System::new(name, move |world| {
+            world.resource_mut::<Log>().unwrap().0.push(name);
+        })
+    }
+
+    fn batches(systems: &[System]) -> Vec<Vec<&str>> {
+        resolve(systems)
+            .unwrap()
+            .into_iter()
+            .map(|batch| batch.into_iter().map(|i| systems[i].name()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn accesses_conflict_when_either_writes() {
+        let mut reads = Access::new();
+        reads.read::<Position>();
+        let mut writes = Access::new();
+        writes.write::<Position>();
+        let mut other = Access::new();
+        other.write::<Velocity>();
+        let mut resource = Access::new();
+        resource.write_resource::<Position>();
+
+        assert!(reads.is_compatible(&reads));
+        assert!(!reads.is_compatible(&writes));
+        assert!(!writes.is_compatible(&reads));
+        assert!(!writes.is_compatible(&writes));
+        assert!(writes.is_compatible(&other));
+        assert!(writes.is_compatible(&resource));
+    }
+
+    #[test]
+    fn compatible_systems_share_a_batch() {
+        let systems = [
+            reading::<Position>("a"),
+            reading::<Position>("b"),
+            writing::<Velocity>("c"),
+        ];
+        assert_eq!(batches(&systems), [vec!["a", "b", "c"]]);
+    }
+
+    #[test]
+    fn conflicting_writes_land_in_a_later_batch() {
+        let systems = [
+            writing::<Position>("a"),
+            reading::<Velocity>("b"),
+            reading::<Position>("c"),
+            writing::<Position>("d"),
+        ];
+        assert_eq!(batches(&systems), [vec!["a", "b"], vec!["c"], vec!["d"]]);
+    }
+
+    #[test]
+    fn systems_run_after_what_they_follow() {
+        let systems = [
+            reading::<Position>("a").after("c"),
+            reading::<Position>("b"),
+            reading::<Position>("c").before("b"),
+        ];
+        assert_eq!(batches(&systems), [vec!["c"], vec!["a", "b"]]);
+
+        let mut schedule = Schedule::new();
+        schedule.add_system(logging("first").after("second"));
+        schedule.add_system(logging("second"));
+        schedule.add_system(logging("third").after("first"));
+        let mut world = World::new();
+        world.insert_resource(Log::default());
+        schedule.run(Stage::Update, &mut world);
+        assert_eq!(
+            world.resource::<Log>().unwrap().0,
+            ["second", "first", "third"]
+        );
+    }
+
+    #[test]
+    fn cycles_are_errors() {
+        let systems = [
+            reading::<Position>("a").after("b"),
+            reading::<Position>("b").after("a"),
+            reading::<Position>("c"),
+        ];
+        match resolve(&systems) {
+            Err(PineError::SystemOrderCycleError(cycle)) => assert_eq!(cycle, ["a", "b"]),
+            other => panic!("expected a cycle, got {:?}", other),
+        }
+
+        let mut schedule = Schedule::new();
+        schedule.add_system(logging("a").before("a"));
+        assert!(schedule.build().is_err());
+    }
+
+    #[test]
+    fn systems_are_skipped_when_their_condition_fails() {
+        let mut schedule = Schedule::new();
+        schedule.add_system(logging("always"));
+        schedule.add_system(logging("never").run_if(|_| false));
+        schedule.add_system(
+            logging("once").run_if(|world| world.resource::<Log>().unwrap().0.len() < 2),
+        );
+        let mut world = World::new();
+        world.insert_resource(Log::default());
+
+        schedule.run(Stage::Update, &mut world);
+        schedule.run(Stage::Update, &mut world);
+        assert_eq!(
+            world.resource::<Log>().unwrap().0,
+            ["always", "once", "always"]
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use super::entity::Entity;
+
+use std::any::Any;
+
+/// Data that can be attached to entities.
+///
+/// Implemented for every type that can be shared between threads, so systems can run in
+/// parallel.
+pub trait Component: Any + Send + Sync {}
+
+impl<T: Any + Send + Sync> Component for T {}
+
+/// A component storage with its type erased, so the world can keep all of them in one map.
+pub(crate) trait Storage: Send + Sync {
+    /// Removes the component of the entity, if it has one.
+    fn remove_entity(&mut self, entity: Entity);
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+#[derive(Debug)]
+/// Stores components of one type densely, with a sparse index from entities into them.
+///
+/// Lookups, insertions and removals are O(1), and iterating touches only entities that have the
+/// component.
+pub(crate) struct SparseSet<T> {
+    /// For every entity slot, where its component is in `entities` and `components`.
+    sparse: Vec<Option<usize>>,
+    entities: Vec<Entity>,
+    components: Vec<T>,
+}
+
+impl<T> Default for SparseSet<T> {
+    fn default() -> Self {
+        Self {
+            sparse: vec![],
+            entities: vec![],
+            components: vec![],
+        }
+    }
+}
+
+impl<T: Component> SparseSet<T> {
+    /// Inserts the component, returning the one the entity had before.
+    pub(crate) fn insert(&mut self, entity: Entity, component: T) -> Option<T> {
+        if let Some(i) = self.index(entity) {
+            return Some(std::mem::replace(&mut self.components[i], component));
+        }
+
+        let slot = entity.index() as usize;
+        if slot >= self.sparse.len() {
+            self.sparse.resize(slot + 1, None);
+        }
+        self.sparse[slot] = Some(self.entities.len());
+        self.entities.push(entity);
+        self.components.push(component);
+        None
+    }
+
+    pub(crate) fn remove(&mut self, entity: Entity) -> Option<T> {
+        let i = self.index(entity)?;
+        self.sparse[entity.index() as usize] = None;
+        self.entities.swap_remove(i);
+        let component = self.components.swap_remove(i);
+        // The last component took the place of the removed one.
+        if let Some(moved) = self.entities.get(i) {
+            self.sparse[moved.index() as usize] = Some(i);
+        }
+        Some(component)
+    }
+
+    pub(crate) fn get(&self, entity: Entity) -> Option<&T> {
+        self.index(entity).map(|i| &self.components[i])
+    }
+
+    pub(crate) fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        self.index(entity).map(|i| &mut self.components[i])
+    }
+
+    /// Returns the entities that have the component, in storage order.
+    pub(crate) fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    /// Returns where the component of the entity is stored, checking it isn't a stale handle.
+    pub(crate) fn index(&self, entity: Entity) -> Option<usize> {
+        let i = (*self.sparse.get(entity.index() as usize)?)?;
+        (self.entities[i] == entity).then_some(i)
+    }
+
+    pub(crate) fn component(&self, i: usize) -> &T {
+        &self.components[i]
+    }
+
+    pub(crate) fn component_mut(&mut self, i: usize) -> &mut T {
+        &mut self.components[i]
+    }
+}
+
+impl<T: Component> Storage for SparseSet<T> {
+    fn remove_entity(&mut self, entity: Entity) {
+        self.remove(entity);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::entity::Entities;
+
+    #[test]
+    fn removing_keeps_the_moved_component_reachable() {
+        let mut entities = Entities::default();
+        let [a, b, c] = [(); 3].map(|_| entities.alloc());
+        let mut set = SparseSet::default();
+        set.insert(a, "a");
+        set.insert(b, "b");
+        set.insert(c, "c");
+
+        // The last component is swapped into the removed one's place.
+        assert_eq!(set.remove(a), Some("a"));
+        assert_eq!(set.entities(), [c, b]);
+        assert_eq!(set.get(b), Some(&"b"));
+        assert_eq!(set.get(c), Some(&"c"));
+        assert_eq!(set.get(a), None);
+        assert_eq!(set.remove(a), None);
+
+        // Removing the last component moves nothing.
+        assert_eq!(set.remove(b), Some("b"));
+        assert_eq!(set.get(c), Some(&"c"));
+    }
+
+    #[test]
+    fn stale_handles_dont_reach_the_new_entity_in_their_slot() {
+        let mut entities = Entities::default();
+        let stale = entities.alloc();
+        let mut set = SparseSet::default();
+        set.insert(stale, 1);
+
+        entities.free(stale);
+        set.remove(stale);
+        let reused = entities.alloc();
+        set.insert(reused, 2);
+
+        assert_eq!(set.get(stale), None);
+        assert_eq!(set.get_mut(stale), None);
+        assert_eq!(set.remove(stale), None);
+        assert_eq!(set.get(reused), Some(&2));
+    }
+
+    #[test]
+    fn inserting_twice_replaces_the_component() {
+        let mut entities = Entities::default();
+        let entity = entities.alloc();
+        let mut set = SparseSet::default();
+
+        assert_eq!(set.insert(entity, 1), None);
+        assert_eq!(set.insert(entity, 2), Some(1));
+        assert_eq!(set.entities(), [entity]);
+        assert_eq!(set.get(entity), Some(&2));
+    }
+}
@@ -0,0 +1,308 @@
+use super::{
+    query::{Query, QueryParam},
+    schedule::Stage,
+    storage::Component,
+    World,
+};
+
+use std::{
+    any::{type_name, TypeId},
+    fmt,
+};
+
+type ExclusiveFn = Box<dyn FnMut(&mut World) + Send>;
+type ParallelFn = Box<dyn FnMut(&World) + Send>;
+type Condition = Box<dyn FnMut(&World) -> bool + Send>;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// The component types and resources a system borrows.
+///
+/// Systems whose accesses don't conflict, i.e. neither writes anything the other uses, can run
+/// at the same time.
+pub struct Access {
+    reads: Vec<(TypeId, &'static str)>,
+    writes: Vec<(TypeId, &'static str)>,
+    resource_reads: Vec<(TypeId, &'static str)>,
+    resource_writes: Vec<(TypeId, &'static str)>,
+}
+
+impl Access {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that components of the type are read.
+    pub fn read<T: Component>(&mut self) {
+        add::<T>(&mut self.reads);
+    }
+
+    /// Records that components of the type are written.
+    pub fn write<T: Component>(&mut self) {
+        add::<T>(&mut self.writes);
+    }
+
+    pub fn read_resource<R: Component>(&mut self) {
+        add::<R>(&mut self.resource_reads);
+    }
+
+    pub fn write_resource<R: Component>(&mut self) {
+        add::<R>(&mut self.resource_writes);
+    }
+
+    /// Returns the access of a query.
+    pub fn of<Q: QueryParam>() -> Self {
+        let mut access = Self::new();
+        Q::access(&mut access);
+        access
+    }
+
+    /// Returns whether systems with the two accesses can run at the same time.
+    pub fn is_compatible(&self, other: &Access) -> bool {
+        let overlaps = |a: &[(TypeId, &str)], b: &[(TypeId, &str)]| {
+            a.iter()
+                .any(|(id, _)| b.iter().any(|(other, _)| id == other))
+        };
+        !overlaps(&self.writes, &other.reads)
+            && !overlaps(&self.writes, &other.writes)
+            && !overlaps(&self.reads, &other.writes)
+            && !overlaps(&self.resource_writes, &other.resource_reads)
+            && !overlaps(&self.resource_writes, &other.resource_writes)
+            && !overlaps(&self.resource_reads, &other.resource_writes)
+    }
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let groups = [
+            ("reads", &self.reads),
+            ("writes", &self.writes),
+            ("reads resources", &self.resource_reads),
+            ("writes resources", &self.resource_writes),
+        ];
+        let mut first = true;
+        for (label, types) in groups {
+            if types.is_empty() {
+                continue;
+            }
+            let names: Vec<_> = types.iter().map(|(_, name)| short_name(name)).collect();
+            if !first {
+                write!(f, "; ")?;
+            }
+            write!(f, "{} {}", label, names.join(", "))?;
+            first = false;
+        }
+        if first {
+            write!(f, "no access")?;
+        }
+        Ok(())
+    }
+}
+
+fn add<T: 'static>(types: &mut Vec<(TypeId, &'static str)>) {
+    let id = TypeId::of::<T>();
+    if !types.iter().any(|(other, _)| *other == id) {
+        types.push((id, type_name::<T>()));
+    }
+}
+
+/// Strips the module paths from a type name, e.g. `Vec<pine::Foo>` becomes `Vec<Foo>`.
+fn short_name(name: &str) -> String {
+    let mut short = String::with_capacity(name.len());
+    let mut segment = String::new();
+    for c in name.chars() {
+        if c.is_alphanumeric() || c == '_' || c == ':' {
+            segment.push(c);
+        } else {
+            short.push_str(segment.rsplit("::").next().unwrap_or_default());
+            segment.clear();
+            short.push(c);
+        }
+    }
+    short.push_str(segment.rsplit("::").next().unwrap_or_default());
+    short
+}
+
+/// How a system gets at the world.
+enum Run {
+    /// Runs alone with mutable access, e.g. to spawn and despawn entities.
+    Exclusive(ExclusiveFn),
+    /// Runs alongside other systems it doesn't conflict with.
+    Parallel(Access, ParallelFn),
+}
+
+/// Game logic run against the [`World`] in a [`Stage`] of every frame.
+///
+/// Systems built from queries declare what they borrow, so the
+/// [`Schedule`](super::schedule::Schedule) can run them in parallel.
+pub struct System {
+    name: String,
+    run: Run,
+    stage: Stage,
+    before: Vec<String>,
+    after: Vec<String>,
+    condition: Option<Condition>,
+}
+
+impl fmt::Debug for System {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("System")
+            .field("name", &self.name)
+            .field("stage", &self.stage)
+            .field("access", &self.access())
+            .field("before", &self.before)
+            .field("after", &self.after)
+            .finish_non_exhaustive()
+    }
+}
+
+impl System {
+    /// Constructs a system with full access to the world, e.g. to spawn and despawn entities.
+    ///
+    /// Exclusive systems never run at the same time as other systems.
+    pub fn new(name: &str, run: impl FnMut(&mut World) + Send + 'static) -> Self {
+        Self::with_run(name, Run::Exclusive(Box::new(run)))
+    }
+
+    /// Constructs a system borrowing only what the access declares.
+    ///
+    /// NB: borrowing anything else may panic, as other systems could be using it at the same
+    /// time.
+    pub fn parallel(name: &str, access: Access, run: impl FnMut(&World) + Send + 'static) -> Self {
+        Self::with_run(name, Run::Parallel(access, Box::new(run)))
+    }
+
+    /// Constructs a system handed the query every time it runs.
+    pub fn query<Q: QueryParam + 'static>(
+        name: &str,
+        mut run: impl FnMut(&mut Query<Q>) + Send + 'static,
+    ) -> Self {
+        Self::parallel(name, Access::of::<Q>(), move |world| {
+            run(&mut world.query::<Q>())
+        })
+    }
+
+    /// Constructs a system calling the closure for every entity matching the query.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pine::world::{query::Res, system::System};
+    /// # use pine::prelude::Time;
+    /// struct Position(f32);
+    /// struct Velocity(f32);
+    ///
+    /// let movement = System::for_each::<(&mut Position, &Velocity, Res<Time>)>(
+    ///     "movement",
+    ///     |(position, velocity, time)| position.0 += velocity.0 * time.delta_seconds(),
+    /// );
+    /// ```
+    pub fn for_each<Q: QueryParam + 'static>(
+        name: &str,
+        mut f: impl FnMut(Q::Item<'_>) + Send + 'static,
+    ) -> Self {
+        Self::query::<Q>(name, move |query| query.for_each(&mut f))
+    }
+
+    fn with_run(name: &str, run: Run) -> Self {
+        Self {
+            name: name.to_string(),
+            run,
+            stage: Stage::default(),
+            before: vec![],
+            after: vec![],
+            condition: None,
+        }
+    }
+
+    /// Sets the stage the system runs in. Defaults to [`Stage::Update`].
+    pub fn in_stage(mut self, stage: Stage) -> Self {
+        self.stage = stage;
+        self
+    }
+
+    /// Makes the system run before the named system of the same stage.
+    pub fn before(mut self, system: &str) -> Self {
+        self.before.push(system.to_string());
+        self
+    }
+
+    /// Makes the system run after the named system of the same stage.
+    pub fn after(mut self, system: &str) -> Self {
+        self.after.push(system.to_string());
+        self
+    }
+
+    /// Only runs the system when the condition holds, e.g. while the game isn't paused.
+    ///
+    /// The condition is checked right before the system would run.
+    pub fn run_if(mut self, condition: impl FnMut(&World) -> bool + Send + 'static) -> Self {
+        self.condition = Some(Box::new(condition));
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn stage(&self) -> Stage {
+        self.stage
+    }
+
+    /// Returns what the system borrows, or None if it needs exclusive access.
+    pub fn access(&self) -> Option<&Access> {
+        match &self.run {
+            Run::Exclusive(_) => None,
+            Run::Parallel(access, _) => Some(access),
+        }
+    }
+
+    pub fn is_exclusive(&self) -> bool {
+        matches!(self.run, Run::Exclusive(_))
+    }
+
+    pub(crate) fn before_systems(&self) -> &[String] {
+        &self.before
+    }
+
+    pub(crate) fn after_systems(&self) -> &[String] {
+        &self.after
+    }
+
+    /// Returns whether the system can run at the same time as the other one.
+    pub(crate) fn is_compatible(&self, other: &System) -> bool {
+        match (self.access(), other.access()) {
+            (Some(access), Some(other)) => access.is_compatible(other),
+            _ => false,
+        }
+    }
+
+    /// Checks the run condition of the system.
+    pub(crate) fn should_run(&mut self, world: &World) -> bool {
+        self.condition
+            .as_mut()
+            .is_none_or(|condition| condition(world))
+    }
+
+    /// Runs the system once if its run condition holds.
+    pub fn run(&mut self, world: &mut World) {
+        if self.should_run(world) {
+            self.run_unchecked(world);
+        }
+    }
+
+    /// Runs the system without checking its run condition.
+    pub(crate) fn run_unchecked(&mut self, world: &mut World) {
+        match &mut self.run {
+            Run::Exclusive(run) => run(world),
+            Run::Parallel(_, run) => run(world),
+        }
+    }
+
+    /// Runs a non-exclusive system without checking its run condition. Does nothing for
+    /// exclusive systems, which need the world to themselves.
+    pub(crate) fn run_shared(&mut self, world: &World) {
+        if let Run::Parallel(_, run) = &mut self.run {
+            run(world);
+        }
+    }
+}